@@ -0,0 +1,125 @@
+use std::sync::mpsc::TryRecvError;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::{App, AuthPromptState};
+
+impl App {
+    pub(crate) fn poll_auth_prompts(&mut self) {
+        if self.auth_prompt.is_some() {
+            return;
+        }
+        let next = match self.auth_prompt_rx.try_recv() {
+            Ok(request) => Some((request, None)),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => {
+                self.pending_connects.iter().find_map(|(key, pending)| {
+                    let request = pending.prompts_rx.try_recv().ok()?;
+                    Some((request, Some(key.clone())))
+                })
+            }
+        };
+        let Some((request, connect_key)) = next else {
+            return;
+        };
+        if request.fields.is_empty() {
+            let _ = request.reply.send(Some(vec![]));
+            return;
+        }
+        self.set_status(format!("Authentication required for {}", request.target));
+        self.auth_prompt = Some(AuthPromptState {
+            answers: vec![String::new(); request.fields.len()],
+            request,
+            active: 0,
+            connect_key,
+        });
+    }
+
+    pub(super) fn handle_auth_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = &mut self.auth_prompt else {
+            return;
+        };
+        let last = prompt.answers.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => {
+                if let Some(prompt) = self.auth_prompt.take() {
+                    let _ = prompt.request.reply.send(None);
+                }
+                self.set_status("Authentication cancelled");
+            }
+            KeyCode::Enter if prompt.active < last => prompt.active += 1,
+            KeyCode::Enter => {
+                if let Some(prompt) = self.auth_prompt.take() {
+                    let _ = prompt.request.reply.send(Some(prompt.answers));
+                }
+            }
+            KeyCode::Tab | KeyCode::Down => prompt.active = (prompt.active + 1).min(last),
+            KeyCode::BackTab | KeyCode::Up => prompt.active = prompt.active.saturating_sub(1),
+            KeyCode::Backspace => {
+                prompt.answers[prompt.active].pop();
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                prompt.answers[prompt.active].push(ch);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::auth_prompt::{AuthPromptField, AuthPromptRequest};
+
+    fn send_request(app: &App, fields: usize) -> mpsc::Receiver<Option<Vec<String>>> {
+        let (reply, answers) = mpsc::channel();
+        app.auth_prompt_tx
+            .send(AuthPromptRequest {
+                target: "user@host".to_string(),
+                name: String::new(),
+                instructions: String::new(),
+                fields: (0..fields)
+                    .map(|index| AuthPromptField {
+                        text: format!("Prompt {index}:"),
+                        echo: index == 0,
+                    })
+                    .collect(),
+                reply,
+            })
+            .unwrap();
+        answers
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_auth_prompt_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn auth_prompt_collects_answers_in_order() {
+        let mut app = App::for_test();
+        let answers = send_request(&app, 2);
+        app.poll_auth_prompts();
+        assert!(app.auth_prompt.is_some());
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('4'));
+        press(&mut app, KeyCode::Char('2'));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.auth_prompt.is_none());
+        assert_eq!(
+            answers.recv().unwrap(),
+            Some(vec!["a".to_string(), "42".to_string()])
+        );
+    }
+
+    #[test]
+    fn auth_prompt_escape_cancels() {
+        let mut app = App::for_test();
+        let answers = send_request(&app, 1);
+        app.poll_auth_prompts();
+        press(&mut app, KeyCode::Esc);
+        assert!(app.auth_prompt.is_none());
+        assert_eq!(answers.recv().unwrap(), None);
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::App;
+use crate::bundle::{default_bundle_path, read_bundle, write_bundle};
+use crate::model::{
+    BundleExportState, BundleField, BundleImportState, ConnectionConfig, ImportCandidate,
+    ImportSource, SshImportState, connection_key, same_identity,
+};
+use crate::secret::Secret;
+use crate::ssh::expand_tilde;
+
+impl App {
+    pub(crate) fn open_bundle_export(&mut self) {
+        if self.connections.is_empty() {
+            self.set_status("No saved connections");
+            return;
+        }
+        self.bundle_export = Some(BundleExportState {
+            checked: vec![false; self.connections.len()],
+            selected: self.selected_saved.min(self.connections.len() - 1),
+            include_history: false,
+            picking: true,
+            path: default_bundle_path()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            passphrase: Secret::default(),
+            confirm: Secret::default(),
+            active_field: BundleField::File,
+        });
+        self.set_status("Select connections to export");
+    }
+
+    pub(super) fn handle_bundle_export_key(&mut self, key: KeyEvent) {
+        let Some(export) = &mut self.bundle_export else {
+            return;
+        };
+        if key.code == KeyCode::Esc {
+            self.bundle_export = None;
+            self.set_status("Export cancelled");
+            return;
+        }
+        if export.picking {
+            match key.code {
+                KeyCode::Up => export.selected = export.selected.saturating_sub(1),
+                KeyCode::Down if export.selected + 1 < export.checked.len() => {
+                    export.selected += 1;
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(checked) = export.checked.get_mut(export.selected) {
+                        *checked = !*checked;
+                    }
+                }
+                KeyCode::Char('a') => {
+                    let check = export.checked.contains(&false);
+                    export.checked.fill(check);
+                }
+                KeyCode::Char('h') => export.include_history = !export.include_history,
+                KeyCode::Enter if export.checked.contains(&true) => {
+                    export.picking = false;
+                    self.set_status("Choose a file and a one-time passphrase");
+                }
+                KeyCode::Enter => self.set_status("Select at least one connection"),
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Tab | KeyCode::Down => {
+                export.active_field = match export.active_field {
+                    BundleField::File => BundleField::Passphrase,
+                    BundleField::Passphrase => BundleField::Confirm,
+                    BundleField::Confirm => BundleField::File,
+                };
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                export.active_field = match export.active_field {
+                    BundleField::File => BundleField::Confirm,
+                    BundleField::Passphrase => BundleField::File,
+                    BundleField::Confirm => BundleField::Passphrase,
+                };
+            }
+            KeyCode::Enter => self.run_bundle_export(),
+            KeyCode::Backspace => match export.active_field {
+                BundleField::File => {
+                    export.path.pop();
+                }
+                BundleField::Passphrase => {
+                    export.passphrase.pop();
+                }
+                BundleField::Confirm => {
+                    export.confirm.pop();
+                }
+            },
+            KeyCode::Char(ch) => match export.active_field {
+                BundleField::File => export.path.push(ch),
+                BundleField::Passphrase => export.passphrase.push(ch),
+                BundleField::Confirm => export.confirm.push(ch),
+            },
+            _ => {}
+        }
+    }
+
+    fn run_bundle_export(&mut self) {
+        let Some(export) = &self.bundle_export else {
+            return;
+        };
+        let raw = export.path.trim();
+        if raw.is_empty() {
+            self.set_status("Export path is empty");
+            return;
+        }
+        if export.passphrase.is_empty() {
+            self.set_status("Passphrase is required");
+            return;
+        }
+        if export.passphrase != export.confirm {
+            self.set_status("Passphrase confirmation does not match");
+            return;
+        }
+        let path = expand_tilde(raw);
+        let connections = self.bundle_selection(&export.checked);
+        if let Err(err) = write_bundle(
+            &path,
+            &connections,
+            export.include_history,
+            export.passphrase.expose(),
+        ) {
+            self.set_status(format!("Export failed: {err:#}"));
+            return;
+        }
+        self.bundle_export = None;
+        self.set_status(format!(
+            "Exported {} connection(s) to {}; share the passphrase separately",
+            connections.len(),
+            path.display()
+        ));
+    }
+
+    fn bundle_selection(&self, checked: &[bool]) -> Vec<ConnectionConfig> {
+        let mut wanted: HashSet<String> = self
+            .connections
+            .iter()
+            .zip(checked)
+            .filter(|(_, checked)| **checked)
+            .map(|(conn, _)| connection_key(conn))
+            .collect();
+        loop {
+            let missing: Vec<String> = self
+                .connections
+                .iter()
+                .filter(|conn| wanted.contains(&connection_key(conn)))
+                .filter_map(|conn| self.jump_target(conn.jump_host.as_deref()?))
+                .map(connection_key)
+                .filter(|key| !wanted.contains(key))
+                .collect();
+            if missing.is_empty() {
+                break;
+            }
+            wanted.extend(missing);
+        }
+        self.connections
+            .iter()
+            .filter(|conn| wanted.contains(&connection_key(conn)))
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn open_bundle_import(&mut self) {
+        self.bundle_import = Some(BundleImportState {
+            path: default_bundle_path()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            passphrase: Secret::default(),
+            active_field: BundleField::File,
+        });
+        self.set_status("Choose the bundle and enter its passphrase");
+    }
+
+    pub(super) fn handle_bundle_import_key(&mut self, key: KeyEvent) {
+        let Some(import) = &mut self.bundle_import else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.bundle_import = None;
+                self.set_status("Import cancelled");
+            }
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                import.active_field = match import.active_field {
+                    BundleField::File => BundleField::Passphrase,
+                    _ => BundleField::File,
+                };
+            }
+            KeyCode::Enter if import.active_field == BundleField::File => {
+                import.active_field = BundleField::Passphrase;
+            }
+            KeyCode::Enter => self.run_bundle_import(),
+            KeyCode::Backspace => match import.active_field {
+                BundleField::File => {
+                    import.path.pop();
+                }
+                _ => {
+                    import.passphrase.pop();
+                }
+            },
+            KeyCode::Char(ch) => match import.active_field {
+                BundleField::File => import.path.push(ch),
+                _ => import.passphrase.push(ch),
+            },
+            _ => {}
+        }
+    }
+
+    fn run_bundle_import(&mut self) {
+        let Some(import) = &mut self.bundle_import else {
+            return;
+        };
+        let raw = import.path.trim();
+        if raw.is_empty() {
+            self.set_status("Bundle path is empty");
+            return;
+        }
+        let path = expand_tilde(raw);
+        let result = read_bundle(&path, import.passphrase.expose());
+        import.passphrase.clear();
+        match result {
+            Ok(Some(connections)) => {
+                self.bundle_import = None;
+                self.preview_bundle(&path, connections);
+            }
+            Ok(None) => self.set_status("Wrong passphrase for this bundle"),
+            Err(err) => self.set_status(format!("Import failed: {err:#}")),
+        }
+    }
+
+    fn preview_bundle(&mut self, path: &Path, connections: Vec<ConnectionConfig>) {
+        if connections.is_empty() {
+            self.set_status("The bundle holds no connections");
+            return;
+        }
+        let candidates = connections
+            .into_iter()
+            .map(|config| {
+                let duplicate = self
+                    .connections
+                    .iter()
+                    .any(|existing| same_identity(existing, &config));
+                ImportCandidate {
+                    config,
+                    checked: !duplicate,
+                    duplicate,
+                }
+            })
+            .collect();
+        self.ssh_import = Some(SshImportState {
+            source: path.to_path_buf(),
+            origin: ImportSource::Bundle,
+            candidates,
+            selected: 0,
+        });
+        self.set_status(format!(
+            "Select connections to import from {}",
+            path.display()
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+    use crate::model::{AuthConfig, KeepaliveConfig};
+
+    fn conn(host: &str, password: &str) -> ConnectionConfig {
+        ConnectionConfig {
+            name: host.to_string(),
+            user: "u".to_string(),
+            host: host.to_string(),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: password.into(),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for ch in text.chars() {
+            press(app, KeyCode::Char(ch));
+        }
+    }
+
+    #[test]
+    fn export_pulls_in_jump_hosts() {
+        let mut app = App::for_test();
+        let bastion = conn("bastion", "a");
+        let mut target = conn("app", "b");
+        target.jump_host = Some(crate::model::jump_reference(&bastion));
+        app.connections = vec![bastion, target, conn("other", "c")];
+        let selection = app.bundle_selection(&[false, true, false]);
+        let hosts: Vec<&str> = selection.iter().map(|c| c.host.as_str()).collect();
+        assert_eq!(hosts, ["bastion", "app"]);
+    }
+
+    #[test]
+    fn bundle_round_trip_through_the_dialogs() {
+        let path =
+            std::env::temp_dir().join(format!("ss-ssh-bundle-dialog-{}.json", std::process::id()));
+        let mut source = App::for_test();
+        source.connections = vec![conn("db", "secret"), conn("web", "pw")];
+        source.open_bundle_export();
+        press(&mut source, KeyCode::Char('a'));
+        press(&mut source, KeyCode::Enter);
+        let export = source.bundle_export.as_mut().unwrap();
+        export.path = path.to_string_lossy().into_owned();
+        export.active_field = BundleField::Passphrase;
+        type_text(&mut source, "one-time");
+        press(&mut source, KeyCode::Tab);
+        type_text(&mut source, "one-time");
+        press(&mut source, KeyCode::Enter);
+        assert!(source.bundle_export.is_none(), "{}", source.status);
+
+        let mut target = App::for_test();
+        target.connections = vec![conn("db", "old")];
+        target.open_bundle_import();
+        target.bundle_import.as_mut().unwrap().path = path.to_string_lossy().into_owned();
+        press(&mut target, KeyCode::Enter);
+        type_text(&mut target, "wrong");
+        press(&mut target, KeyCode::Enter);
+        assert_eq!(target.status, "Wrong passphrase for this bundle");
+        type_text(&mut target, "one-time");
+        press(&mut target, KeyCode::Enter);
+
+        let preview = target.ssh_import.as_ref().unwrap();
+        assert_eq!(preview.origin, ImportSource::Bundle);
+        assert!(preview.candidates[0].duplicate && !preview.candidates[0].checked);
+        press(&mut target, KeyCode::Char(' '));
+        press(&mut target, KeyCode::Enter);
+        assert_eq!(target.connections.len(), 2);
+        assert_eq!(target.connections[0].auth, conn("db", "secret").auth);
+
+        let saved = crate::storage::load_store(&target.config_path).unwrap();
+        let decrypted =
+            crate::storage::decrypt_connection(saved.connections[0].clone(), &target.master_key);
+        assert!(decrypted.is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+}
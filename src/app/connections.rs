@@ -1,14 +1,24 @@
-use std::time::SystemTime;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::{Instant, SystemTime};
 
 use anyhow::Result;
+use ssh2::Session;
+
 use crate::app::constants::NOT_CONNECTED_MESSAGE;
-use crate::app::App;
+use crate::app::{App, ConnectIntent, NoticeAction, PendingConnect};
 use crate::model::{
-    AuthConfig, AuthKind, ConnectionConfig, HistoryEntry, HistoryState, Mode,
-    NewConnectionState, OpenConnection, TryResult,
+    AskedSecrets, AuthConfig, AuthKind, ConnectionConfig, ConnectionHealth,
+    DEFAULT_KEEPALIVE_INTERVAL_SECS, DEFAULT_KEEPALIVE_MAX_MISSED, DEFAULT_SSH_PORT, HistoryEntry,
+    HistoryState, KeepaliveConfig, KeepaliveStatus, Mode, NewConnectionState, OpenConnection,
+    SecretKind, TryResult, format_forwards, jump_reference, parse_count, parse_forwards,
+    parse_host_port, parse_port,
 };
-use crate::ssh::connect_ssh;
-use crate::storage::save_store;
+use crate::secret::SecretSource;
+use crate::ssh::{ConnectHooks, ConnectPhase, SharedSession, connect_ssh_with_hooks};
+use crate::storage::{build_store, create_master_from_password, save_store};
 
 impl App {
     pub(crate) fn history_range(&self, history_len: usize, has_error: bool) -> (usize, usize) {
@@ -31,31 +41,251 @@ impl App {
         (history_len - 1) / page_size
     }
 
-    pub(crate) fn selected_connected_connection(&self) -> Option<ConnectionConfig> {
+    pub(crate) fn selected_connected_connection(&self) -> Option<&OpenConnection> {
         let conn = self.connections.get(self.selected_saved)?;
+        self.open_connections.iter().find(|candidate| {
+            crate::model::same_identity(&candidate.config, conn)
+                && candidate.keepalive.health != ConnectionHealth::Dead
+        })
+    }
+
+    pub(crate) fn selected_connection_lost(&self) -> bool {
+        let Some(conn) = self.connections.get(self.selected_saved) else {
+            return false;
+        };
+        self.open_connections.iter().any(|candidate| {
+            crate::model::same_identity(&candidate.config, conn)
+                && candidate.keepalive.health == ConnectionHealth::Dead
+        })
+    }
+
+    pub(crate) fn connect_selected(&mut self, action: Option<NoticeAction>) {
+        if let Some(config) = self.connections.get(self.selected_saved).cloned() {
+            self.start_connect(config, ConnectIntent::Open(action));
+        } else {
+            self.set_status("No saved connection selected");
+        }
+    }
+
+    pub(super) fn start_connect(&mut self, mut config: ConnectionConfig, intent: ConnectIntent) {
+        let key = crate::model::connection_key(&config);
+        if self.pending_connects.contains_key(&key) {
+            self.set_status(format!("Still connecting to {}", config.label()));
+            return;
+        }
+        match self.resolve_jump_chain(&config) {
+            Ok(via) => config.via = via,
+            Err(err) => {
+                self.fail_connect(config, intent, err);
+                return;
+            }
+        }
+        let (tx, rx) = mpsc::channel();
+        let (phase_tx, phase_rx) = mpsc::channel();
+        let (answers_tx, answers_rx) = mpsc::channel();
+        let (prompts_tx, prompts_rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let hooks = ConnectHooks {
+            prompts: Some(prompts_tx),
+            phases: Some(phase_tx),
+            cancel: Some(cancel.clone()),
+            answers: Some(answers_tx),
+        };
+        let worker_config = self.with_remembered_answers(&config);
+        std::thread::spawn(move || {
+            let _ = tx.send(connect_ssh_with_hooks(&worker_config, &hooks));
+        });
+        self.set_status(format!("Connecting to {} (Esc to abort)", config.label()));
+        let pending = PendingConnect {
+            config,
+            intent,
+            rx,
+            phase: ConnectPhase::Resolve,
+            phase_rx,
+            cancel,
+            answers_rx,
+            prompts_rx,
+            started: Instant::now(),
+        };
+        self.pending_connects.insert(key, pending);
+    }
+
+    pub(super) fn with_remembered_answers(&self, config: &ConnectionConfig) -> ConnectionConfig {
+        self.open_connections
+            .iter()
+            .find(|open| crate::model::same_identity(&open.config, config))
+            .map_or_else(|| config.clone(), |open| config.with_answers(&open.asked))
+    }
+
+    pub(crate) fn connect_to_abort(&self) -> Option<String> {
+        let in_form = self.mode == Mode::NewConnection;
+        let active_tab = self
+            .active_terminal_tab
+            .checked_sub(1)
+            .and_then(|index| self.terminal_tabs.get(index))
+            .map(|tab| tab.id);
+        let selected = self
+            .connections
+            .get(self.selected_saved)
+            .map(crate::model::connection_key);
+        self.pending_connects
+            .iter()
+            .find(|(key, pending)| match pending.intent {
+                ConnectIntent::Test | ConnectIntent::Save => in_form,
+                ConnectIntent::ReopenTerminal { id } => active_tab == Some(id),
+                _ => !in_form && active_tab.is_none() && selected.as_ref() == Some(*key),
+            })
+            .map(|(key, _)| key.clone())
+    }
+
+    pub(crate) fn abort_pending_connect(&mut self, key: &str) {
+        let Some(pending) = self.pending_connects.remove(key) else {
+            return;
+        };
+        pending.cancel.store(true, Ordering::Relaxed);
         if self
+            .auth_prompt
+            .as_ref()
+            .is_some_and(|prompt| prompt.connect_key.as_deref() == Some(key))
+            && let Some(prompt) = self.auth_prompt.take()
+        {
+            let _ = prompt.request.reply.send(None);
+        }
+        match pending.intent {
+            ConnectIntent::Reconnect => {
+                self.cancel_reconnect(&crate::model::connection_key(&pending.config));
+            }
+            ConnectIntent::Test | ConnectIntent::Save => {
+                self.new_connection_feedback = Some("Connection aborted".to_string());
+            }
+            _ => {}
+        }
+        self.set_status(format!("Connection to {} aborted", pending.config.label()));
+    }
+
+    pub(crate) fn poll_pending_connects(&mut self) {
+        let mut finished = Vec::new();
+        for (key, pending) in &mut self.pending_connects {
+            while let Ok(phase) = pending.phase_rx.try_recv() {
+                pending.phase = phase;
+            }
+            match pending.rx.try_recv() {
+                Ok(result) => finished.push((key.clone(), result)),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    finished.push((key.clone(), Err(anyhow::anyhow!("connect worker stopped"))));
+                }
+            }
+        }
+        for (key, result) in finished {
+            let Some(pending) = self.pending_connects.remove(&key) else {
+                continue;
+            };
+            match result {
+                Ok(session) => {
+                    let answers = pending.answers_rx.try_iter().collect();
+                    self.finish_connect(pending.config, pending.intent, session, answers);
+                }
+                Err(err) => self.fail_connect(pending.config, pending.intent, err),
+            }
+        }
+    }
+
+    fn finish_connect(
+        &mut self,
+        config: ConnectionConfig,
+        intent: ConnectIntent,
+        session: Session,
+        answers: AskedSecrets,
+    ) {
+        match intent {
+            ConnectIntent::Open(action) => {
+                let label = config.label();
+                if let Err(err) = self.open_session(config, session, answers) {
+                    self.set_status(format!("Connection failed: {err}"));
+                } else if let Some(action) = action {
+                    self.run_notice_action(action, &label);
+                }
+            }
+            ConnectIntent::Test => {
+                self.try_result = Some(TryResult {
+                    success: true,
+                    message: "Connection OK (not saved)".to_string(),
+                });
+            }
+            ConnectIntent::Save => match self.open_session(config, session, answers) {
+                Ok(()) => {
+                    self.mode = Mode::Normal;
+                    self.edit_key = None;
+                    self.new_connection_feedback = None;
+                }
+                Err(err) => {
+                    self.new_connection_feedback = Some(format!("Connection failed: {err}"));
+                }
+            },
+            ConnectIntent::Terminal { cols, rows } => {
+                self.remember_answers(&config, answers);
+                if let Err(err) = self.open_terminal_with_session(&config, session, cols, rows) {
+                    self.set_status(format!("Failed to open terminal: {err}"));
+                }
+            }
+            ConnectIntent::Reconnect => self.finish_reconnect(config, session, answers),
+            ConnectIntent::ReopenTerminal { id } => {
+                self.remember_answers(&config, answers);
+                if let Err(err) = self.reattach_terminal(id, session) {
+                    self.set_status(format!("Failed to reopen terminal: {err}"));
+                }
+            }
+        }
+    }
+
+    fn remember_answers(&mut self, config: &ConnectionConfig, answers: AskedSecrets) {
+        if let Some(open) = self
             .open_connections
-            .iter()
-            .any(|candidate| crate::model::same_identity(&candidate.config, conn))
+            .iter_mut()
+            .find(|open| crate::model::same_identity(&open.config, config))
         {
-            Some(conn.clone())
-        } else {
-            None
+            open.asked.extend(answers);
         }
     }
 
-    pub(crate) fn connect_selected(&mut self) -> Option<ConnectionConfig> {
-        if let Some(config) = self.connections.get(self.selected_saved).cloned() {
-            if let Err(err) = self.connect_and_open(config.clone()) {
+    fn fail_connect(
+        &mut self,
+        config: ConnectionConfig,
+        intent: ConnectIntent,
+        err: anyhow::Error,
+    ) {
+        if self.prompt_host_key(&err, &config, intent) {
+            return;
+        }
+        if self.notify_host_key_changed(&config, &err) {
+            if matches!(intent, ConnectIntent::Reconnect) {
+                self.cancel_reconnect(&crate::model::connection_key(&config));
+            }
+            return;
+        }
+        match intent {
+            ConnectIntent::Open(_) => {
                 self.record_connect_error(&config, &err);
                 self.set_status(format!("Connection failed: {err}"));
-                None
-            } else {
-                Some(config)
             }
-        } else {
-            self.set_status("No saved connection selected");
-            None
+            ConnectIntent::Test => {
+                self.try_result = Some(TryResult {
+                    success: false,
+                    message: format!("Connection failed: {err}"),
+                });
+            }
+            ConnectIntent::Save => {
+                self.record_connect_error(&config, &err);
+                self.new_connection_feedback = Some(format!("Connection failed: {err}"));
+            }
+            ConnectIntent::Terminal { .. } => {
+                self.set_status(format!("Failed to open terminal: {err}"));
+            }
+            ConnectIntent::Reconnect => self.retry_reconnect(&config, &err),
+            ConnectIntent::ReopenTerminal { .. } => {
+                self.set_status(format!("Reconnect failed: {err}"));
+            }
         }
     }
 
@@ -69,7 +299,8 @@ impl App {
             .iter()
             .position(|conn| crate::model::same_identity(&conn.config, config))
         {
-            self.open_connections.remove(index);
+            let closed = self.open_connections.remove(index);
+            self.stop_forwards(&crate::model::connection_key(&closed.config));
             if self.selected_tab > 0 && self.selected_tab >= index {
                 self.selected_tab = self.selected_tab.saturating_sub(1);
             }
@@ -105,38 +336,39 @@ impl App {
         state.name = config.name.clone();
         state.user = config.user.clone();
         state.host = config.host.clone();
+        state.port = config.port.to_string();
+        state.jump_host = config.jump_host.clone();
+        state.keepalive_interval = config.keepalive.interval_secs.to_string();
+        state.keepalive_missed = config.keepalive.max_missed.to_string();
+        state.forwards = format_forwards(&config.forwards);
         match &config.auth {
             AuthConfig::Password { password } => {
                 state.auth_kind = AuthKind::PasswordOnly;
-                state.password = password.clone();
+                load_secret_source(&mut state, password);
             }
             AuthConfig::PrivateKey { path, password } => {
                 state.key_path = path.clone();
                 if let Some(pass) = password {
                     state.auth_kind = AuthKind::PrivateKeyWithPassword;
-                    state.password = pass.clone();
+                    load_secret_source(&mut state, pass);
                 } else {
                     state.auth_kind = AuthKind::PrivateKey;
                 }
             }
+            AuthConfig::Agent { identity } => {
+                state.auth_kind = AuthKind::Agent;
+                state.agent_identity = identity.clone().unwrap_or_default();
+            }
+            AuthConfig::KeyboardInteractive => {
+                state.auth_kind = AuthKind::KeyboardInteractive;
+            }
         }
         state
     }
 
     pub(crate) fn run_test_connection(&mut self) {
         match self.build_connection_config() {
-            Ok(config) => {
-                self.try_result = Some(match self.try_connect(&config) {
-                    Ok(()) => TryResult {
-                        success: true,
-                        message: "Connection OK (not saved)".to_string(),
-                    },
-                    Err(err) => TryResult {
-                        success: false,
-                        message: format!("Connection failed: {err}"),
-                    },
-                });
-            }
+            Ok(config) => self.start_connect(config, ConnectIntent::Test),
             Err(err) => {
                 self.try_result = Some(TryResult {
                     success: false,
@@ -149,16 +381,18 @@ impl App {
     pub(crate) fn run_save_connection(&mut self) {
         match self.build_connection_config() {
             Ok(config) => {
-                let snapshot = config.clone();
-                match self.save_or_connect(config) {
+                if self.edit_key.is_none() {
+                    self.start_connect(config, ConnectIntent::Save);
+                    return;
+                }
+                match self.save_edited_connection(config) {
                     Ok(()) => {
                         self.mode = Mode::Normal;
-                        self.edit_index = None;
+                        self.edit_key = None;
                         self.new_connection_feedback = None;
                     }
                     Err(err) => {
-                        self.record_connect_error(&snapshot, &err);
-                        self.new_connection_feedback = Some(format!("Connection failed: {err}"));
+                        self.new_connection_feedback = Some(format!("Save failed: {err}"));
                     }
                 }
             }
@@ -198,16 +432,18 @@ impl App {
         if self.new_connection.host.trim().is_empty() {
             anyhow::bail!("Host is required");
         }
+        let (host, host_port) =
+            parse_host_port(&self.new_connection.host).map_err(anyhow::Error::msg)?;
+        let port = match host_port {
+            Some(port) => port,
+            None if self.new_connection.port.trim().is_empty() => DEFAULT_SSH_PORT,
+            None => parse_port(&self.new_connection.port).map_err(anyhow::Error::msg)?,
+        };
 
         let auth = match self.new_connection.auth_kind {
-            AuthKind::PasswordOnly => {
-                if self.new_connection.password.is_empty() {
-                    anyhow::bail!("Password is required");
-                }
-                AuthConfig::Password {
-                    password: self.new_connection.password.clone(),
-                }
-            }
+            AuthKind::PasswordOnly => AuthConfig::Password {
+                password: form_secret_source(&self.new_connection, "Password")?,
+            },
             AuthKind::PrivateKey => {
                 if self.new_connection.key_path.trim().is_empty() {
                     anyhow::bail!("Private key path is required");
@@ -221,63 +457,200 @@ impl App {
                 if self.new_connection.key_path.trim().is_empty() {
                     anyhow::bail!("Private key path is required");
                 }
-                if self.new_connection.password.is_empty() {
-                    anyhow::bail!("Key password is required");
-                }
                 AuthConfig::PrivateKey {
                     path: self.new_connection.key_path.clone(),
-                    password: Some(self.new_connection.password.clone()),
+                    password: Some(form_secret_source(&self.new_connection, "Key password")?),
+                }
+            }
+            AuthKind::Agent => {
+                let identity = self.new_connection.agent_identity.trim();
+                AuthConfig::Agent {
+                    identity: (!identity.is_empty()).then(|| identity.to_string()),
                 }
             }
+            AuthKind::KeyboardInteractive => AuthConfig::KeyboardInteractive,
+        };
+        let interval_secs = parse_count(&self.new_connection.keepalive_interval)
+            .map_err(anyhow::Error::msg)?
+            .unwrap_or(DEFAULT_KEEPALIVE_INTERVAL_SECS);
+        let max_missed = parse_count(&self.new_connection.keepalive_missed)
+            .map_err(anyhow::Error::msg)?
+            .unwrap_or(DEFAULT_KEEPALIVE_MAX_MISSED)
+            .max(1);
+        let keepalive = KeepaliveConfig {
+            interval_secs,
+            max_missed,
         };
+        let forwards = parse_forwards(&self.new_connection.forwards).map_err(anyhow::Error::msg)?;
 
         Ok(ConnectionConfig {
             name: self.new_connection.name.trim().to_string(),
             user: self.new_connection.user.trim().to_string(),
-            host: self.new_connection.host.trim().to_string(),
+            host,
+            port,
             auth,
             history: vec![],
             last_remote_dir: None,
+            jump_host: self.new_connection.jump_host.clone(),
+            via: vec![],
+            keepalive,
+            forwards,
         })
     }
 
-    fn connect_and_open(&mut self, mut config: ConnectionConfig) -> Result<()> {
-        let session = connect_ssh(&config)?;
+    pub(crate) fn resolve_jump_chain(
+        &self,
+        config: &ConnectionConfig,
+    ) -> Result<Vec<ConnectionConfig>> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::from([jump_reference(config)]);
+        let mut next = config.jump_host.clone();
+        while let Some(reference) = next {
+            let Some(hop) = self.jump_target(&reference) else {
+                anyhow::bail!("Jump host {reference} is not a saved connection");
+            };
+            if !seen.insert(reference) {
+                anyhow::bail!("Jump hosts loop back to {}", hop.label());
+            }
+            let mut hop = hop.clone();
+            hop.via.clear();
+            next = hop.jump_host.clone();
+            chain.push(hop);
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+
+    pub(crate) fn jump_target(&self, reference: &str) -> Option<&ConnectionConfig> {
+        self.connections
+            .iter()
+            .find(|conn| jump_reference(conn) == reference)
+    }
+
+    pub(crate) fn jump_host_label(&self, reference: Option<&str>) -> String {
+        let Some(reference) = reference else {
+            return "(direct)".to_string();
+        };
+        self.jump_target(reference)
+            .map(|conn| conn.label())
+            .unwrap_or_else(|| format!("{reference} (missing)"))
+    }
+
+    pub(super) fn cycle_jump_host(&mut self, forward: bool) {
+        let editing = self
+            .edit_key
+            .as_deref()
+            .and_then(|key| self.saved_position(key))
+            .map(|index| jump_reference(&self.connections[index]));
+        let mut options: Vec<Option<String>> = vec![None];
+        for reference in self.connections.iter().map(jump_reference) {
+            if Some(&reference) != editing.as_ref() && !options.contains(&Some(reference.clone())) {
+                options.push(Some(reference));
+            }
+        }
+        let current = options
+            .iter()
+            .position(|option| *option == self.new_connection.jump_host)
+            .unwrap_or(0);
+        let next = if forward {
+            (current + 1) % options.len()
+        } else {
+            (current + options.len() - 1) % options.len()
+        };
+        self.new_connection.jump_host = options.swap_remove(next);
+    }
+
+    pub(super) fn open_session(
+        &mut self,
+        mut config: ConnectionConfig,
+        session: Session,
+        answers: AskedSecrets,
+    ) -> Result<()> {
         config.history.push(HistoryEntry {
             ts: crate::model::now_epoch(),
             state: HistoryState::Success,
         });
+        let mut asked = self
+            .open_connections
+            .iter_mut()
+            .find(|open| crate::model::same_identity(&open.config, &config))
+            .map(|open| std::mem::take(&mut open.asked))
+            .unwrap_or_default();
+        asked.extend(answers);
+        self.open_connections
+            .retain(|open| !crate::model::same_identity(&open.config, &config));
+        let session = SharedSession::new(session);
         self.open_connections.push(OpenConnection {
             config: config.clone(),
-            session,
+            session: session.clone(),
             connected_at: SystemTime::now(),
+            keepalive: KeepaliveStatus::default(),
+            asked,
         });
         self.selected_tab = self.open_connections.len().saturating_sub(1);
-        self.upsert_connection(config.clone());
+        self.start_forwards(&config, session);
+        let key = crate::model::connection_key(&config);
+        let label = config.label();
+        self.upsert_connection(config);
         self.save_store()?;
-        self.sort_connections_by_recent(Some(crate::model::connection_key(&config)));
-        self.last_error
-            .remove(&crate::model::connection_key(&config));
-        self.set_status(format!("Connected to {}", config.label()));
+        self.sort_connections_by_recent(Some(key.clone()));
+        self.last_error.remove(&key);
+        self.set_status(format!("Connected to {label}"));
         Ok(())
     }
 
-    fn save_or_connect(&mut self, mut config: ConnectionConfig) -> Result<()> {
-        if let Some(index) = self.edit_index {
+    fn save_edited_connection(&mut self, mut config: ConnectionConfig) -> Result<()> {
+        if let Some(index) = self
+            .edit_key
+            .as_deref()
+            .and_then(|key| self.saved_position(key))
+        {
             if let Some(existing) = self.connections.get(index) {
                 config.history = existing.history.clone();
                 config.last_remote_dir = existing.last_remote_dir.clone();
+                let old_reference = jump_reference(existing);
+                let new_reference = jump_reference(&config);
+                let shared =
+                    self.connections.iter().enumerate().any(|(other, conn)| {
+                        other != index && jump_reference(conn) == old_reference
+                    });
+                for conn in &mut self.connections {
+                    if !shared && conn.jump_host.as_deref() == Some(old_reference.as_str()) {
+                        conn.jump_host = Some(new_reference.clone());
+                    }
+                }
             }
             self.connections.remove(index);
-            self.upsert_connection(config);
-            self.save_store()?;
-            self.set_status("Connection updated");
-            return Ok(());
         }
-        self.connect_and_open(config)
+        self.upsert_connection(config);
+        self.save_store()?;
+        self.set_status("Connection updated");
+        Ok(())
     }
 
-    fn upsert_connection(&mut self, connection: ConnectionConfig) {
+    pub(super) fn clear_dangling_jump_hosts(&mut self) -> Vec<String> {
+        let saved: HashSet<String> = self.connections.iter().map(jump_reference).collect();
+        let mut cleared = Vec::new();
+        for conn in &mut self.connections {
+            if conn
+                .jump_host
+                .as_ref()
+                .is_some_and(|reference| !saved.contains(reference))
+            {
+                conn.jump_host = None;
+                cleared.push(conn.label());
+            }
+        }
+        cleared
+    }
+
+    pub(crate) fn saved_position(&self, key: &str) -> Option<usize> {
+        self.connections
+            .iter()
+            .position(|conn| crate::model::connection_key(conn) == key)
+    }
+
+    pub(super) fn upsert_connection(&mut self, connection: ConnectionConfig) {
         if let Some(existing) = self
             .connections
             .iter_mut()
@@ -289,31 +662,47 @@ impl App {
         self.connections.push(connection);
     }
 
-    pub(super) fn save_store(&self) -> Result<()> {
-        let stored = crate::model::StoreFile {
-            master: self.master.clone(),
-            connections: self
-                .connections
-                .iter()
-                .map(|conn| crate::storage::encrypt_connection(conn, &self.master_key))
-                .collect::<Result<Vec<_>>>()?,
-            last_local_dir: self
-                .last_local_dir
-                .as_ref()
-                .map(|value| value.to_string_lossy().into_owned()),
-        };
-        save_store(&self.config_path, &stored)
+    pub(super) fn save_store(&mut self) -> Result<()> {
+        // The secrets are wiped while locked; writing now would lose them.
+        if self.is_locked() {
+            self.save_deferred = true;
+            return Ok(());
+        }
+        self.merge_external_changes()?;
+        let stored = build_store(
+            &self.master,
+            &self.master_key,
+            &self.connections,
+            self.last_local_dir.as_deref(),
+            self.sealed_vault,
+        )?;
+        save_store(&self.config_path, &stored)?;
+        self.mark_store_synced();
+        Ok(())
+    }
+
+    pub(super) fn rekey_store(&mut self, password: &str) -> Result<()> {
+        self.merge_external_changes()?;
+        let (master, master_key) = create_master_from_password(password)?;
+        let stored = build_store(
+            &master,
+            &master_key,
+            &self.connections,
+            self.last_local_dir.as_deref(),
+            self.sealed_vault,
+        )?;
+        save_store(&self.config_path, &stored)?;
+        self.master = master;
+        self.master_key = master_key;
+        self.mark_store_synced();
+        Ok(())
     }
 
     pub(crate) fn update_last_remote_dir(&mut self, dir: String) -> Result<()> {
-        let Some(conn) = self.selected_connected_connection() else {
+        if self.selected_connected_connection().is_none() {
             return Ok(());
-        };
-        if let Some(existing) = self
-            .connections
-            .iter_mut()
-            .find(|candidate| crate::model::same_identity(candidate, &conn))
-        {
+        }
+        if let Some(existing) = self.connections.get_mut(self.selected_saved) {
             existing.last_remote_dir = Some(dir);
             self.save_store()?;
         }
@@ -326,16 +715,40 @@ impl App {
         let pre_history = base_lines + 2;
         inner_height.saturating_sub(pre_history).max(1)
     }
+}
+
+pub(super) fn load_secret_source(state: &mut NewConnectionState, source: &SecretSource) {
+    match source {
+        SecretSource::Stored(secret) => {
+            state.secret_kind = SecretKind::Stored;
+            state.password = secret.clone();
+        }
+        SecretSource::Command { command } => {
+            state.secret_kind = SecretKind::Command;
+            state.secret_command = command.clone();
+        }
+        SecretSource::Ask { ask } => state.secret_kind = SecretKind::Ask(*ask),
+    }
+}
 
-    fn try_connect(&self, config: &ConnectionConfig) -> Result<()> {
-        let _session = connect_ssh(config)?;
-        Ok(())
+fn form_secret_source(state: &NewConnectionState, label: &str) -> Result<SecretSource> {
+    match state.secret_kind {
+        SecretKind::Stored if state.password.is_empty() => anyhow::bail!("{label} is required"),
+        SecretKind::Stored => Ok(SecretSource::Stored(state.password.clone())),
+        SecretKind::Command if state.secret_command.trim().is_empty() => {
+            anyhow::bail!("{label} command is required")
+        }
+        SecretKind::Command => Ok(SecretSource::Command {
+            command: state.secret_command.trim().to_string(),
+        }),
+        SecretKind::Ask(ask) => Ok(SecretSource::Ask { ask }),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::secret::AskPolicy;
 
     #[test]
     fn history_range_handles_empty() {
@@ -364,4 +777,359 @@ mod tests {
         assert_eq!(app.max_history_page(0, false), 0);
         assert_eq!(app.max_history_page(10, false), 9);
     }
+
+    fn saved(name: &str, jump_host: Option<&ConnectionConfig>) -> ConnectionConfig {
+        ConnectionConfig {
+            name: name.to_string(),
+            user: "user".to_string(),
+            host: format!("{name}.example"),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: "pw".into(),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: jump_host.map(jump_reference),
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    #[test]
+    fn resolve_jump_chain_orders_hops_outermost_first() {
+        let mut app = App::for_test();
+        let edge = saved("edge", None);
+        let inner = saved("inner", Some(&edge));
+        let target = saved("target", Some(&inner));
+        app.connections = vec![target.clone(), inner, edge];
+        let chain = app.resolve_jump_chain(&target).unwrap();
+        let names: Vec<_> = chain.iter().map(|hop| hop.name.as_str()).collect();
+        assert_eq!(names, vec!["edge", "inner"]);
+    }
+
+    #[test]
+    fn resolve_jump_chain_rejects_loops_and_missing_hosts() {
+        let mut app = App::for_test();
+        let mut first = saved("first", None);
+        let second = saved("second", Some(&first));
+        first.jump_host = Some(jump_reference(&second));
+        app.connections = vec![first.clone(), second];
+        assert!(app.resolve_jump_chain(&first).is_err());
+
+        let orphan = ConnectionConfig {
+            jump_host: Some("gone@host:22".to_string()),
+            ..saved("orphan", None)
+        };
+        assert!(app.resolve_jump_chain(&orphan).is_err());
+    }
+
+    #[test]
+    fn answers_asked_while_connected_live_with_the_open_connection() {
+        let mut app = App::for_test();
+        let config = ConnectionConfig {
+            auth: AuthConfig::Password {
+                password: SecretSource::Ask {
+                    ask: AskPolicy::WhileConnected,
+                },
+            },
+            ..saved("db", None)
+        };
+        let (tx, rx) = mpsc::channel();
+        let (answers_tx, answers_rx) = mpsc::channel();
+        let key = crate::model::connection_key(&config);
+        app.pending_connects.insert(
+            key.clone(),
+            PendingConnect {
+                config: config.clone(),
+                intent: ConnectIntent::Open(None),
+                rx,
+                phase: ConnectPhase::Auth,
+                phase_rx: mpsc::channel().1,
+                cancel: Arc::new(AtomicBool::new(false)),
+                answers_rx,
+                prompts_rx: mpsc::channel().1,
+                started: Instant::now(),
+            },
+        );
+        answers_tx.send((key, "typed".into())).unwrap();
+        tx.send(Ok(Session::new().unwrap())).unwrap();
+        app.poll_pending_connects();
+
+        let expected = AuthConfig::Password {
+            password: "typed".into(),
+        };
+        assert_eq!(app.with_remembered_answers(&config).auth, expected);
+        assert_eq!(
+            app.connections[0].auth, config.auth,
+            "answer must not be saved"
+        );
+        let saved = crate::storage::load_store(&app.config_path).unwrap();
+        assert!(!serde_json::to_string(&saved).unwrap().contains("typed"));
+
+        app.lock().unwrap();
+        assert_eq!(app.with_remembered_answers(&config).auth, config.auth);
+    }
+
+    #[test]
+    fn asked_secret_round_trips_through_the_form() {
+        let mut app = App::for_test();
+        app.new_connection.user = "user".to_string();
+        app.new_connection.host = "host".to_string();
+        app.new_connection.secret_kind = SecretKind::Ask(AskPolicy::EveryTime);
+        let config = app.build_connection_config().unwrap();
+        let ask = SecretSource::Ask {
+            ask: AskPolicy::EveryTime,
+        };
+        assert_eq!(config.auth, AuthConfig::Password { password: ask });
+        let form = app.prefill_new_connection(&config);
+        assert_eq!(form.secret_kind, SecretKind::Ask(AskPolicy::EveryTime));
+        assert!(form.password.is_empty());
+    }
+
+    #[test]
+    fn secret_command_round_trips_through_the_form() {
+        let mut app = App::for_test();
+        app.new_connection.user = "user".to_string();
+        app.new_connection.host = "host".to_string();
+        app.new_connection.auth_kind = AuthKind::PrivateKeyWithPassword;
+        app.new_connection.key_path = "~/.ssh/id_ed25519".to_string();
+        app.new_connection.secret_kind = SecretKind::Command;
+        assert!(app.build_connection_config().is_err());
+
+        app.new_connection.secret_command = " pass show prod/key ".to_string();
+        let config = app.build_connection_config().unwrap();
+        let command = SecretSource::Command {
+            command: "pass show prod/key".to_string(),
+        };
+        assert_eq!(
+            config.auth,
+            AuthConfig::PrivateKey {
+                path: "~/.ssh/id_ed25519".to_string(),
+                password: Some(command),
+            }
+        );
+        let form = app.prefill_new_connection(&config);
+        assert_eq!(form.secret_kind, SecretKind::Command);
+        assert_eq!(form.secret_command, "pass show prod/key");
+        assert!(form.password.is_empty());
+    }
+
+    #[test]
+    fn build_connection_config_parses_port() {
+        let mut app = App::for_test();
+        app.new_connection.user = "user".to_string();
+        app.new_connection.host = "host".to_string();
+        app.new_connection.port = "2222".to_string();
+        app.new_connection.password = "pw".into();
+        let config = app.build_connection_config().unwrap();
+        assert_eq!((config.host.as_str(), config.port), ("host", 2222));
+
+        app.new_connection.host = "[::1]:2200".to_string();
+        let config = app.build_connection_config().unwrap();
+        assert_eq!((config.host.as_str(), config.port), ("::1", 2200));
+
+        app.new_connection.host = "host".to_string();
+        app.new_connection.port = "abc".to_string();
+        assert!(app.build_connection_config().is_err());
+    }
+
+    #[test]
+    fn build_connection_config_reads_keepalive() {
+        let mut app = App::for_test();
+        app.new_connection.user = "user".to_string();
+        app.new_connection.host = "host".to_string();
+        app.new_connection.password = "pw".into();
+        app.new_connection.keepalive_interval = "0".to_string();
+        app.new_connection.keepalive_missed = String::new();
+        let config = app.build_connection_config().unwrap();
+        assert_eq!(config.keepalive.interval_secs, 0);
+        assert_eq!(config.keepalive.max_missed, DEFAULT_KEEPALIVE_MAX_MISSED);
+
+        app.new_connection.keepalive_interval = "-5".to_string();
+        assert!(app.build_connection_config().is_err());
+    }
+
+    #[test]
+    fn changed_host_key_shows_notice_for_every_intent() {
+        for intent in [
+            ConnectIntent::Open(None),
+            ConnectIntent::Test,
+            ConnectIntent::Save,
+        ] {
+            let mut app = App::for_test();
+            let info = crate::known_hosts::HostKeyInfo::from_blob("edge.example", 22, &[]);
+            let err = crate::known_hosts::HostKeyError::Changed(info).into();
+            app.fail_connect(saved("edge", None), intent, err);
+            let notice = app.notice.as_ref().map(|notice| notice.title.as_str());
+            assert_eq!(notice, Some("Host key changed"));
+            assert!(app.try_result.is_none());
+        }
+    }
+
+    #[test]
+    fn delete_confirm_survives_a_connect_finishing_meanwhile() {
+        let mut app = App::for_test();
+        let mut recent = saved("recent", None);
+        recent.history.push(HistoryEntry {
+            ts: crate::model::now_epoch() - 60,
+            state: HistoryState::Success,
+        });
+        let older = saved("older", None);
+        app.connections = vec![recent.clone(), older.clone()];
+        app.selected_saved = 1;
+        let key =
+            |code| crossterm::event::KeyEvent::new(code, crossterm::event::KeyModifiers::NONE);
+        app.handle_key(key(crossterm::event::KeyCode::Char('x')))
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        app.pending_connects.insert(
+            crate::model::connection_key(&older),
+            PendingConnect {
+                config: older.clone(),
+                intent: ConnectIntent::Open(None),
+                rx,
+                phase: ConnectPhase::Resolve,
+                phase_rx: mpsc::channel().1,
+                cancel: Arc::new(AtomicBool::new(false)),
+                answers_rx: mpsc::channel().1,
+                prompts_rx: mpsc::channel().1,
+                started: Instant::now(),
+            },
+        );
+        tx.send(Err(anyhow::anyhow!("refused"))).unwrap();
+        app.poll_pending_connects();
+        assert_eq!(app.connections[0].name, "older");
+
+        app.handle_key(key(crossterm::event::KeyCode::Char('y')))
+            .unwrap();
+        let names: Vec<_> = app
+            .connections
+            .iter()
+            .map(|conn| conn.name.as_str())
+            .collect();
+        assert_eq!(names, ["recent"]);
+    }
+
+    #[test]
+    fn jump_hosts_survive_auth_changes_and_are_dropped_on_delete() {
+        let mut app = App::for_test();
+        let bastion = saved("bastion", None);
+        app.connections = vec![bastion.clone(), saved("app", Some(&bastion))];
+        app.edit_key = Some(crate::model::connection_key(&bastion));
+        let edited = ConnectionConfig {
+            auth: AuthConfig::Agent { identity: None },
+            ..bastion.clone()
+        };
+        app.save_edited_connection(edited).unwrap();
+        let target = app
+            .connections
+            .iter()
+            .find(|conn| conn.name == "app")
+            .unwrap();
+        assert_eq!(app.resolve_jump_chain(target).unwrap().len(), 1);
+
+        app.selected_saved = app
+            .connections
+            .iter()
+            .position(|conn| conn.name == "bastion")
+            .unwrap();
+        let key =
+            |code| crossterm::event::KeyEvent::new(code, crossterm::event::KeyModifiers::NONE);
+        app.handle_key(key(crossterm::event::KeyCode::Char('x')))
+            .unwrap();
+        app.handle_key(key(crossterm::event::KeyCode::Char('y')))
+            .unwrap();
+        assert_eq!(app.connections.len(), 1);
+        assert_eq!(app.connections[0].jump_host, None);
+        assert!(app.status.contains("app"));
+    }
+
+    #[test]
+    fn connects_to_different_connections_run_side_by_side() {
+        let mut app = App::for_test();
+        let first = ConnectionConfig {
+            host: "127.0.0.1".to_string(),
+            port: 1,
+            ..saved("first", None)
+        };
+        let second = ConnectionConfig {
+            name: "second".to_string(),
+            port: 2,
+            ..first.clone()
+        };
+        app.start_connect(first.clone(), ConnectIntent::Open(None));
+        app.start_connect(second.clone(), ConnectIntent::Open(None));
+        app.start_connect(first.clone(), ConnectIntent::Open(None));
+        assert_eq!(app.pending_connects.len(), 2);
+        assert!(app.status.contains("Still connecting"));
+
+        let (reply, answers) = mpsc::channel();
+        app.auth_prompt = Some(crate::app::AuthPromptState {
+            request: crate::auth_prompt::AuthPromptRequest {
+                target: second.label(),
+                name: String::new(),
+                instructions: String::new(),
+                fields: vec![],
+                reply,
+            },
+            answers: vec![],
+            active: 0,
+            connect_key: Some(crate::model::connection_key(&second)),
+        });
+        app.abort_pending_connect(&crate::model::connection_key(&first));
+        assert_eq!(app.pending_connects.len(), 1);
+        assert!(
+            app.auth_prompt.is_some(),
+            "the other connect's prompt stays"
+        );
+        app.abort_pending_connect(&crate::model::connection_key(&second));
+        assert!(app.pending_connects.is_empty());
+        assert!(app.auth_prompt.is_none());
+        assert_eq!(answers.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn esc_aborts_pending_connect_and_flags_worker() {
+        let mut app = App::for_test();
+        app.new_connection.user = "user".to_string();
+        app.new_connection.host = "host".to_string();
+        app.new_connection.password = "pw".into();
+        let config = app.build_connection_config().unwrap();
+        app.connections = vec![config.clone()];
+        let key = crate::model::connection_key(&config);
+        let (_tx, rx) = mpsc::channel();
+        let (phase_tx, phase_rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        app.pending_connects.insert(
+            key.clone(),
+            PendingConnect {
+                config,
+                intent: ConnectIntent::Open(None),
+                rx,
+                phase: ConnectPhase::Resolve,
+                phase_rx,
+                cancel: cancel.clone(),
+                answers_rx: mpsc::channel().1,
+                prompts_rx: mpsc::channel().1,
+                started: Instant::now(),
+            },
+        );
+        phase_tx.send(ConnectPhase::Handshake).unwrap();
+        app.poll_pending_connects();
+        assert_eq!(
+            app.pending_connects.get(&key).map(|pending| pending.phase),
+            Some(ConnectPhase::Handshake)
+        );
+
+        let esc = crossterm::event::KeyEvent::new(
+            crossterm::event::KeyCode::Esc,
+            crossterm::event::KeyModifiers::NONE,
+        );
+        app.handle_key(esc).unwrap();
+        assert!(app.pending_connects.is_empty());
+        assert!(cancel.load(Ordering::Relaxed));
+        assert!(app.status.contains("aborted"));
+    }
 }
@@ -10,6 +10,11 @@ pub(crate) const LOG_RETENTION_DAYS: i64 = 7;
 pub(crate) const LOG_MAX_ENTRIES: usize = 10_000;
 pub(crate) const LOG_MAX_IN_MEMORY: usize = 100;
 
+pub(crate) const AUTO_LOCK_DEFAULT_MINUTES: u64 = 15;
+pub(crate) const AUTO_LOCK_ENV: &str = "SS_SSH_AUTO_LOCK_MINUTES";
+pub(crate) const UNLOCK_RETRY_BASE: std::time::Duration = std::time::Duration::from_secs(1);
+pub(crate) const UNLOCK_RETRY_MAX: std::time::Duration = std::time::Duration::from_secs(30);
+
 pub(crate) const NOT_CONNECTED_MESSAGE: &str = "Selected connection is not connected";
 
 pub(crate) const TRANSFER_LOG_THRESHOLD_BYTES: u64 = 1024 * 1024;
@@ -0,0 +1,50 @@
+use crate::app::App;
+use crate::forward::{ForwardStatus, Forwarder};
+use crate::model::{ConnectionConfig, connection_key};
+use crate::ssh::SharedSession;
+
+impl App {
+    pub(super) fn start_forwards(&mut self, config: &ConnectionConfig, session: SharedSession) {
+        let key = connection_key(config);
+        self.forwarders.remove(&key);
+        if config.forwards.is_empty() {
+            return;
+        }
+        self.forwarders
+            .insert(key, Forwarder::start(config.forwards.clone(), session));
+        self.log_line(&format!(
+            "Starting {} forward(s) for {}",
+            config.forwards.len(),
+            config.label()
+        ));
+    }
+
+    pub(super) fn stop_forwards(&mut self, key: &str) {
+        self.forwarders.remove(key);
+    }
+
+    pub(crate) fn poll_forwards(&mut self) {
+        let mut reports = Vec::new();
+        for (key, forwarder) in &self.forwarders {
+            for err in forwarder.take_errors() {
+                let label = self
+                    .connections
+                    .iter()
+                    .find(|conn| connection_key(conn) == *key)
+                    .map(ConnectionConfig::label)
+                    .unwrap_or_else(|| key.clone());
+                reports.push(format!("Forward on {label} failed: {err}"));
+            }
+        }
+        for report in reports {
+            self.set_status(report);
+        }
+    }
+
+    pub(crate) fn forward_status(&self, key: &str) -> Vec<ForwardStatus> {
+        self.forwarders
+            .get(key)
+            .map(Forwarder::status)
+            .unwrap_or_default()
+    }
+}
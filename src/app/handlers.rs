@@ -1,47 +1,80 @@
-use anyhow::{Context, Result};
-use base64::Engine;
-use base64::engine::general_purpose::STANDARD as Base64;
+use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+use crate::app::connections::load_secret_source;
 use crate::app::constants::{
     NOT_CONNECTED_MESSAGE, NOTICE_NO_SUBFOLDERS_MESSAGE, NOTICE_NO_SUBFOLDERS_TITLE,
     NOTICE_NOT_CONNECTED_MESSAGE, NOTICE_NOT_CONNECTED_TITLE, STATUS_CANCELLED,
 };
 use crate::app::{App, NoticeAction};
 use crate::model::{
-    AppAction, AuthKind, Field, MasterField, Mode, Notice, TransferDirection, TransferStep,
+    AppAction, AuthKind, Field, KeyCandidate, MasterField, Mode, Notice, SecretKind,
+    TransferDirection, TransferStep,
 };
-use crate::storage::{create_master_from_password, save_store};
+use crate::secret::AskPolicy;
+use crate::storage::{remove_unsealed_backups, unlock_master};
 
 impl App {
     pub(crate) fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
+        self.note_activity();
+        if self.is_locked() {
+            self.handle_unlock_key(key);
+            return Ok(false);
+        }
+        if self.auth_prompt.is_some() {
+            self.handle_auth_prompt_key(key);
+            return Ok(false);
+        }
+        if self.host_key_prompt.is_some() {
+            self.handle_host_key_prompt_key(key);
+            return Ok(false);
+        }
+        if !self.save_conflicts.is_empty() {
+            self.handle_save_conflict_key(key);
+            return Ok(false);
+        }
         if self.notice.is_some() {
             if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
                 self.notice = None;
                 if matches!(key.code, KeyCode::Enter) {
                     if let Some(action) = self.notice_action.take() {
-                        if let Some(config) = self.connect_selected() {
-                            match action {
-                                NoticeAction::ConnectTerminal => {
-                                    self.pending_action = Some(AppAction::OpenTerminal);
-                                }
-                                NoticeAction::ConnectUpload => {
-                                    self.start_upload(config);
-                                }
-                                NoticeAction::ConnectDownload => {
-                                    self.start_download(config);
-                                }
-                            }
-                        }
+                        self.connect_selected(Some(action));
                     }
                 }
             } else if matches!(key.code, KeyCode::Char('c')) {
                 self.notice = None;
                 self.notice_action = None;
-                self.connect_selected();
+                self.connect_selected(None);
             }
             return Ok(false);
         }
+        if self.ssh_import.is_some() {
+            return self.handle_ssh_import_key(key);
+        }
+        if self.ssh_export.is_some() {
+            self.handle_ssh_export_key(key);
+            return Ok(false);
+        }
+        if self.bundle_export.is_some() {
+            self.handle_bundle_export_key(key);
+            return Ok(false);
+        }
+        if self.bundle_import.is_some() {
+            self.handle_bundle_import_key(key);
+            return Ok(false);
+        }
+        let in_shell = self
+            .active_terminal_tab
+            .checked_sub(1)
+            .and_then(|index| self.terminal_tabs.get(index))
+            .is_some_and(|tab| !tab.disconnected);
+        if key.code == KeyCode::Esc
+            && !in_shell
+            && let Some(connect) = self.connect_to_abort()
+        {
+            self.abort_pending_connect(&connect);
+            return Ok(false);
+        }
         if self.handle_terminal_tabs_key(key)? {
             return Ok(false);
         }
@@ -74,6 +107,20 @@ impl App {
         }
     }
 
+    pub(super) fn run_notice_action(&mut self, action: NoticeAction, label: &str) {
+        match action {
+            NoticeAction::ConnectTerminal => {
+                self.pending_action = Some(AppAction::OpenTerminal);
+            }
+            NoticeAction::ConnectUpload => {
+                self.start_upload(label);
+            }
+            NoticeAction::ConnectDownload => {
+                self.start_download(label);
+            }
+        }
+    }
+
     pub(crate) fn notice_action_label(&self) -> Option<&'static str> {
         match self.notice_action {
             Some(NoticeAction::ConnectTerminal) => Some("connect and open the terminal"),
@@ -97,7 +144,7 @@ impl App {
             KeyCode::Char('n') => {
                 self.mode = Mode::NewConnection;
                 self.new_connection = crate::model::NewConnectionState::default();
-                self.edit_index = None;
+                self.edit_key = None;
                 self.new_connection_feedback = None;
                 self.set_status("Fill fields and press Enter to connect");
             }
@@ -105,7 +152,7 @@ impl App {
                 if let Some(config) = self.connections.get(self.selected_saved).cloned() {
                     self.mode = Mode::NewConnection;
                     self.new_connection = self.prefill_new_connection(&config);
-                    self.edit_index = Some(self.selected_saved);
+                    self.edit_key = Some(crate::model::connection_key(&config));
                     self.new_connection_feedback = None;
                     self.set_status("Edit fields and press Enter to save");
                 } else {
@@ -113,10 +160,25 @@ impl App {
                 }
             }
             KeyCode::Char('c') => {
-                if self.selected_connected_connection().is_some() {
+                let selected_key = self
+                    .connections
+                    .get(self.selected_saved)
+                    .map(crate::model::connection_key);
+                if let Some(key) = selected_key
+                    && self.cancel_reconnect(&key)
+                {
+                    self.set_status("Reconnect cancelled");
+                } else if self.selected_connected_connection().is_some() {
                     self.disconnect_selected();
                 } else {
-                    self.connect_selected();
+                    self.connect_selected(None);
+                }
+            }
+            KeyCode::Char('r') => {
+                if self.selected_connection_lost() {
+                    self.connect_selected(None);
+                } else {
+                    self.set_status("Selected connection was not lost");
                 }
             }
             KeyCode::Char('v') => {
@@ -134,8 +196,9 @@ impl App {
                 }
             }
             KeyCode::Char('u') => {
-                if let Some(conn) = self.selected_connected_connection() {
-                    self.start_upload(conn);
+                if let Some(open) = self.selected_connected_connection() {
+                    let label = open.config.label();
+                    self.start_upload(&label);
                 } else {
                     self.notice = Some(Notice {
                         title: NOTICE_NOT_CONNECTED_TITLE.to_string(),
@@ -145,8 +208,9 @@ impl App {
                 }
             }
             KeyCode::Char('d') => {
-                if let Some(conn) = self.selected_connected_connection() {
-                    self.start_download(conn);
+                if let Some(open) = self.selected_connected_connection() {
+                    let label = open.config.label();
+                    self.start_download(&label);
                 } else {
                     self.notice = Some(Notice {
                         title: NOTICE_NOT_CONNECTED_TITLE.to_string(),
@@ -155,6 +219,18 @@ impl App {
                     self.notice_action = Some(NoticeAction::ConnectDownload);
                 }
             }
+            KeyCode::Char('i') => {
+                self.open_ssh_import();
+            }
+            KeyCode::Char('p') => {
+                self.open_ssh_export();
+            }
+            KeyCode::Char('b') => {
+                self.open_bundle_export();
+            }
+            KeyCode::Char('B') => {
+                self.open_bundle_import();
+            }
             KeyCode::Char('o') => {
                 self.mode = Mode::ChangeMasterPassword;
                 self.master_change = crate::model::MasterPasswordState::default();
@@ -165,7 +241,10 @@ impl App {
                     self.set_status("No saved connections");
                 } else {
                     self.mode = Mode::ConfirmDelete;
-                    self.delete_index = Some(self.selected_saved);
+                    self.delete_key = self
+                        .connections
+                        .get(self.selected_saved)
+                        .map(crate::model::connection_key);
                     self.set_status("Confirm delete");
                 }
             }
@@ -232,7 +311,7 @@ impl App {
         match key.code {
             KeyCode::Esc => {
                 self.mode = Mode::Normal;
-                self.edit_index = None;
+                self.edit_key = None;
                 self.new_connection_feedback = None;
                 self.set_status(STATUS_CANCELLED);
             }
@@ -243,16 +322,39 @@ impl App {
                     let next = match (self.new_connection.auth_kind, key.code) {
                         (AuthKind::PasswordOnly, KeyCode::Right) => AuthKind::PrivateKey,
                         (AuthKind::PrivateKey, KeyCode::Right) => AuthKind::PrivateKeyWithPassword,
-                        (AuthKind::PrivateKeyWithPassword, KeyCode::Right) => {
-                            AuthKind::PasswordOnly
-                        }
-                        (AuthKind::PasswordOnly, KeyCode::Left) => AuthKind::PrivateKeyWithPassword,
+                        (AuthKind::PrivateKeyWithPassword, KeyCode::Right) => AuthKind::Agent,
+                        (AuthKind::Agent, KeyCode::Right) => AuthKind::KeyboardInteractive,
+                        (AuthKind::KeyboardInteractive, KeyCode::Right) => AuthKind::PasswordOnly,
+                        (AuthKind::PasswordOnly, KeyCode::Left) => AuthKind::KeyboardInteractive,
                         (AuthKind::PrivateKey, KeyCode::Left) => AuthKind::PasswordOnly,
                         (AuthKind::PrivateKeyWithPassword, KeyCode::Left) => AuthKind::PrivateKey,
+                        (AuthKind::Agent, KeyCode::Left) => AuthKind::PrivateKeyWithPassword,
+                        (AuthKind::KeyboardInteractive, KeyCode::Left) => AuthKind::Agent,
                         (current, _) => current,
                     };
                     self.new_connection.auth_kind = next;
                 }
+                if self.new_connection.active_field == Field::SecretSource {
+                    let kinds = [
+                        SecretKind::Stored,
+                        SecretKind::Command,
+                        SecretKind::Ask(AskPolicy::EveryTime),
+                        SecretKind::Ask(AskPolicy::WhileConnected),
+                    ];
+                    let current = kinds
+                        .iter()
+                        .position(|kind| *kind == self.new_connection.secret_kind)
+                        .unwrap_or(0);
+                    let next = if key.code == KeyCode::Right {
+                        current + 1
+                    } else {
+                        current + kinds.len() - 1
+                    };
+                    self.new_connection.secret_kind = kinds[next % kinds.len()];
+                }
+                if self.new_connection.active_field == Field::JumpHost {
+                    self.cycle_jump_host(key.code == KeyCode::Right);
+                }
             }
             KeyCode::F(2) => {
                 if self.new_connection.active_field == Field::KeyPath {
@@ -260,7 +362,10 @@ impl App {
                 }
             }
             KeyCode::F(3) => {
-                if self.new_connection.active_field == Field::KeyPath {
+                if matches!(
+                    self.new_connection.active_field,
+                    Field::KeyPath | Field::AgentIdentity
+                ) {
                     self.open_key_picker();
                 }
             }
@@ -287,23 +392,34 @@ impl App {
         match key.code {
             KeyCode::Esc => {
                 self.mode = Mode::Normal;
+                self.master_change = crate::model::MasterPasswordState::default();
                 self.set_status(STATUS_CANCELLED);
             }
             KeyCode::Tab | KeyCode::Down => self.advance_master_field(true),
             KeyCode::BackTab | KeyCode::Up => self.advance_master_field(false),
-            KeyCode::Enter => {
-                if self.master_change.active_field == MasterField::ActionSave {
-                    match self.apply_master_password_change() {
-                        Ok(()) => {
-                            self.mode = Mode::Normal;
-                            self.set_status("Master password updated");
-                        }
-                        Err(err) => {
-                            self.set_status(format!("Master password not changed: {err}"));
-                        }
+            KeyCode::Enter => match self.master_change.active_field {
+                MasterField::ActionSave => match self.apply_master_password_change() {
+                    Ok(()) => {
+                        self.mode = Mode::Normal;
+                        self.set_status("Master password updated");
                     }
-                }
-            }
+                    Err(err) => {
+                        self.set_status(format!("Master password not changed: {err}"));
+                    }
+                },
+                MasterField::ActionLayout => match self.toggle_vault_layout() {
+                    Ok(()) => {
+                        self.mode = Mode::Normal;
+                        self.set_status(if self.sealed_vault {
+                            "Vault sealed: names, hosts and history are encrypted too"
+                        } else {
+                            "Vault unsealed: only passwords are encrypted"
+                        });
+                    }
+                    Err(err) => self.set_status(format!("Vault layout not changed: {err}")),
+                },
+                _ => {}
+            },
             KeyCode::Backspace => {
                 self.edit_master_field(EditAction::Backspace);
             }
@@ -322,20 +438,29 @@ impl App {
         match key.code {
             KeyCode::Esc | KeyCode::Char('n') => {
                 self.mode = Mode::Normal;
-                self.delete_index = None;
+                self.delete_key = None;
                 self.set_status(STATUS_CANCELLED);
             }
             KeyCode::Enter | KeyCode::Char('y') => {
-                if let Some(index) = self.delete_index.take() {
-                    if index < self.connections.len() {
+                if let Some(key) = self.delete_key.take() {
+                    if let Some(index) = self.saved_position(&key) {
                         let removed = self.connections.remove(index);
                         self.last_error
                             .remove(&crate::model::connection_key(&removed));
+                        let direct = self.clear_dangling_jump_hosts();
                         self.save_store()?;
-                        if self.selected_saved >= self.connections.len() && self.selected_saved > 0 {
+                        if self.selected_saved >= self.connections.len() && self.selected_saved > 0
+                        {
                             self.selected_saved -= 1;
                         }
-                        self.set_status("Connection removed");
+                        if direct.is_empty() {
+                            self.set_status("Connection removed");
+                        } else {
+                            self.set_status(format!(
+                                "Connection removed; {} no longer use it as jump host",
+                                direct.join(", ")
+                            ));
+                        }
                     }
                 }
                 self.mode = Mode::Normal;
@@ -421,11 +546,12 @@ impl App {
                     if let Some(entry) = picker.entries.get(picker.selected).cloned() {
                         if entry.is_dir {
                             if only_dirs {
-                                let subdirectories = crate::app::helpers::read_dir_entries_filtered(
-                                    &entry.path,
-                                    true,
-                                    picker.show_hidden,
-                                )?;
+                                let subdirectories =
+                                    crate::app::helpers::read_dir_entries_filtered(
+                                        &entry.path,
+                                        true,
+                                        picker.show_hidden,
+                                    )?;
                                 if subdirectories.is_empty() {
                                     self.notice = Some(Notice {
                                         title: NOTICE_NO_SUBFOLDERS_TITLE.to_string(),
@@ -452,7 +578,8 @@ impl App {
                                 self.open_remote_picker()?;
                             }
                         } else {
-                            self.new_connection.key_path = entry.path.to_string_lossy().into_owned();
+                            self.new_connection.key_path =
+                                entry.path.to_string_lossy().into_owned();
                             self.file_picker = None;
                         }
                     }
@@ -556,26 +683,14 @@ impl App {
                 if let Some(entry) = picker.entries.get(picker.selected).cloned() {
                     if entry.is_dir {
                         if only_dirs {
-                            let Some(conn) = self.selected_connected_connection() else {
+                            if self.selected_connected_connection().is_none() {
                                 self.set_status(NOT_CONNECTED_MESSAGE);
                                 self.remote_picker = Some(picker);
                                 return Ok(false);
-                            };
-                            if !self
-                                .ssh_backend
-                                .remote_has_subdirectories(
-                                    Some(&self.open_connections),
-                                    &conn,
-                                    &entry.path,
-                                )?
-                            {
-                                self.notice = Some(Notice {
-                                    title: NOTICE_NO_SUBFOLDERS_TITLE.to_string(),
-                                    message: NOTICE_NO_SUBFOLDERS_MESSAGE.to_string(),
-                                });
-                                self.remote_picker = Some(picker);
-                                return Ok(false);
                             }
+                            self.remote_picker = Some(picker);
+                            self.descend_remote_dir(entry.path)?;
+                            return Ok(false);
                         }
                         let new_cwd = entry.path;
                         picker.cwd = new_cwd.clone();
@@ -659,7 +774,10 @@ impl App {
                 match self.transfer.as_ref().map(|t| t.direction) {
                     Some(TransferDirection::Upload) => {
                         if let Some(transfer) = &self.transfer {
-                            let start = transfer.target_dir.clone().unwrap_or_else(|| "/".to_string());
+                            let start = transfer
+                                .target_dir
+                                .clone()
+                                .unwrap_or_else(|| "/".to_string());
                             self.open_remote_picker_at(start, true)?;
                         }
                     }
@@ -693,11 +811,23 @@ impl App {
                     }
                 }
                 KeyCode::Enter => {
-                    if let Some(key) = picker.keys.get(picker.selected) {
-                        self.new_connection.key_path = key.path.clone();
-                        if let Some(password) = &key.password {
-                            self.new_connection.password = password.clone();
-                            self.new_connection.auth_kind = AuthKind::PrivateKeyWithPassword;
+                    if let Some(key) = picker.keys.get(picker.selected).cloned() {
+                        match key {
+                            KeyCandidate::File { path, password } => {
+                                self.new_connection.key_path = path;
+                                if let Some(password) = password {
+                                    load_secret_source(&mut self.new_connection, &password);
+                                    self.new_connection.auth_kind =
+                                        AuthKind::PrivateKeyWithPassword;
+                                } else if self.new_connection.auth_kind == AuthKind::Agent {
+                                    self.new_connection.auth_kind = AuthKind::PrivateKey;
+                                }
+                            }
+                            KeyCandidate::Agent { fingerprint, .. } => {
+                                self.new_connection.agent_identity = fingerprint;
+                                self.new_connection.auth_kind = AuthKind::Agent;
+                                self.new_connection.active_field = Field::AgentIdentity;
+                            }
                         }
                         self.key_picker = None;
                     }
@@ -726,17 +856,36 @@ impl App {
     }
 
     fn active_fields(&self) -> Vec<Field> {
-        let mut fields = vec![Field::Name, Field::User, Field::Host, Field::AuthType];
+        let mut fields = vec![
+            Field::Name,
+            Field::User,
+            Field::Host,
+            Field::Port,
+            Field::JumpHost,
+            Field::AuthType,
+        ];
+        let asked = matches!(self.new_connection.secret_kind, SecretKind::Ask(_));
         match self.new_connection.auth_kind {
             AuthKind::PasswordOnly => {
-                fields.push(Field::Password);
+                fields.push(Field::SecretSource);
+                if !asked {
+                    fields.push(Field::Password);
+                }
             }
             AuthKind::PrivateKey => fields.push(Field::KeyPath),
             AuthKind::PrivateKeyWithPassword => {
                 fields.push(Field::KeyPath);
-                fields.push(Field::Password);
+                fields.push(Field::SecretSource);
+                if !asked {
+                    fields.push(Field::Password);
+                }
             }
+            AuthKind::Agent => fields.push(Field::AgentIdentity),
+            AuthKind::KeyboardInteractive => {}
         }
+        fields.push(Field::KeepaliveInterval);
+        fields.push(Field::KeepaliveMissed);
+        fields.push(Field::Forwards);
         fields.push(Field::ActionTest);
         fields.push(Field::ActionSave);
         fields
@@ -747,10 +896,35 @@ impl App {
             Field::Name => &mut self.new_connection.name,
             Field::User => &mut self.new_connection.user,
             Field::Host => &mut self.new_connection.host,
+            Field::Port | Field::KeepaliveInterval | Field::KeepaliveMissed => {
+                if let EditAction::Insert(ch) = action
+                    && !ch.is_ascii_digit()
+                {
+                    return;
+                }
+                match self.new_connection.active_field {
+                    Field::KeepaliveInterval => &mut self.new_connection.keepalive_interval,
+                    Field::KeepaliveMissed => &mut self.new_connection.keepalive_missed,
+                    _ => &mut self.new_connection.port,
+                }
+            }
             Field::KeyPath => &mut self.new_connection.key_path,
-            Field::Password => &mut self.new_connection.password,
+            Field::Password if self.new_connection.secret_kind == SecretKind::Command => {
+                &mut self.new_connection.secret_command
+            }
+            Field::Password => {
+                match action {
+                    EditAction::Insert(ch) => self.new_connection.password.push(ch),
+                    EditAction::Backspace => {
+                        self.new_connection.password.pop();
+                    }
+                }
+                return;
+            }
+            Field::AgentIdentity => &mut self.new_connection.agent_identity,
+            Field::Forwards => &mut self.new_connection.forwards,
             Field::ActionTest | Field::ActionSave => return,
-            Field::AuthType => return,
+            Field::JumpHost | Field::AuthType | Field::SecretSource => return,
         };
         match action {
             EditAction::Insert(ch) => target.push(ch),
@@ -766,6 +940,7 @@ impl App {
             MasterField::New,
             MasterField::Confirm,
             MasterField::ActionSave,
+            MasterField::ActionLayout,
         ];
         let pos = fields
             .iter()
@@ -786,7 +961,7 @@ impl App {
             MasterField::Current => &mut self.master_change.current,
             MasterField::New => &mut self.master_change.new_password,
             MasterField::Confirm => &mut self.master_change.confirm,
-            MasterField::ActionSave => return,
+            MasterField::ActionSave | MasterField::ActionLayout => return,
         };
         match action {
             EditAction::Insert(ch) => target.push(ch),
@@ -807,32 +982,33 @@ impl App {
             anyhow::bail!("New password confirmation does not match");
         }
 
-        let salt = Base64
-            .decode(&self.master.salt_b64)
-            .context("decode salt")?;
-        let current_key = crate::storage::derive_key(&self.master_change.current, &salt);
-        let check = crate::storage::decrypt_string(&self.master.check, &current_key)
-            .context("verify current password")?;
-        if check != "ssh-client-check" {
+        if unlock_master(&self.master, self.master_change.current.expose())?.is_none() {
             anyhow::bail!("Current master password incorrect");
         }
 
-        let (new_master, new_key) = create_master_from_password(&self.master_change.new_password)?;
-        let stored = crate::model::StoreFile {
-            master: new_master.clone(),
-            connections: self
-                .connections
-                .iter()
-                .map(|conn| crate::storage::encrypt_connection(conn, &new_key))
-                .collect::<Result<Vec<_>>>()?,
-            last_local_dir: self
-                .last_local_dir
-                .as_ref()
-                .map(|value| value.to_string_lossy().into_owned()),
-        };
-        save_store(&self.config_path, &stored)?;
-        self.master = new_master;
-        self.master_key = new_key;
+        let new_password = self.master_change.new_password.clone();
+        self.rekey_store(new_password.expose())?;
+        self.master_change = crate::model::MasterPasswordState::default();
+        Ok(())
+    }
+
+    fn toggle_vault_layout(&mut self) -> Result<()> {
+        if self.master_change.current.is_empty() {
+            anyhow::bail!("Current password is required");
+        }
+        if unlock_master(&self.master, self.master_change.current.expose())?.is_none() {
+            anyhow::bail!("Current master password incorrect");
+        }
+        self.merge_external_changes()?;
+        self.sealed_vault = !self.sealed_vault;
+        if let Err(err) = self.save_store() {
+            self.sealed_vault = !self.sealed_vault;
+            return Err(err);
+        }
+        if self.sealed_vault {
+            remove_unsealed_backups(&self.config_path)?;
+            let _ = std::fs::remove_file(&self.log_path);
+        }
         self.master_change = crate::model::MasterPasswordState::default();
         Ok(())
     }
@@ -841,9 +1017,11 @@ impl App {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use crate::app::ssh_backend::MockSshBackend;
-    use crate::model::{AuthConfig, ConnectionConfig, OpenConnection, RemoteEntry, RemotePickerState};
+    use crate::model::{
+        AuthConfig, ConnectionConfig, OpenConnection, RemoteEntry, RemotePickerState,
+    };
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use std::sync::Arc;
     use std::time::SystemTime;
 
@@ -863,7 +1041,7 @@ mod tests {
         let should_quit = app.handle_key(key).unwrap();
         assert!(!should_quit);
         assert!(matches!(app.mode, Mode::NewConnection));
-        assert!(app.edit_index.is_none());
+        assert!(app.edit_key.is_none());
     }
 
     #[test]
@@ -874,13 +1052,87 @@ mod tests {
         assert!(should_quit);
     }
 
+    #[test]
+    fn cancelling_master_change_drops_typed_passwords() {
+        let mut app = App::for_test();
+        app.mode = Mode::ChangeMasterPassword;
+        for ch in "old".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                .unwrap();
+        }
+        assert_eq!(app.master_change.current.expose(), "old");
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert!(app.master_change.current.is_empty());
+        assert!(matches!(app.mode, Mode::Normal));
+    }
+
+    #[test]
+    fn vault_layout_switches_both_ways_from_the_options() {
+        let mut app = App::for_test();
+        app.connections = vec![ConnectionConfig {
+            name: "db".to_string(),
+            user: "u".to_string(),
+            host: "db.internal".to_string(),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: "pw".into(),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        }];
+        app.save_store().unwrap();
+        let switch_layout = |app: &mut App| {
+            app.mode = Mode::ChangeMasterPassword;
+            for ch in "test-password".chars() {
+                app.handle_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                    .unwrap();
+            }
+            app.master_change.active_field = MasterField::ActionLayout;
+            app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+                .unwrap();
+        };
+
+        app.log_path = app.config_path.with_file_name("ss-ssh.log");
+        app.set_status("Connected to db.internal");
+        assert!(app.log_path.exists());
+
+        switch_layout(&mut app);
+        assert!(app.sealed_vault, "{}", app.status);
+        let content = std::fs::read_to_string(&app.config_path).unwrap();
+        assert!(!content.contains("db.internal"));
+        app.set_status("Connected to db.internal");
+        assert!(!app.log_path.exists());
+        assert!(app.last_log.contains("db.internal"));
+        assert!(!crate::storage::backup_path(&app.config_path, 1).exists());
+
+        let store = crate::storage::load_store(&app.config_path).unwrap();
+        let opened = crate::storage::unlock_store(&store, "test-password")
+            .unwrap()
+            .unwrap();
+        assert!(opened.sealed);
+        assert_eq!(opened.connections, app.connections);
+
+        switch_layout(&mut app);
+        assert!(!app.sealed_vault);
+        let content = std::fs::read_to_string(&app.config_path).unwrap();
+        assert!(content.contains("db.internal"));
+    }
+
     #[test]
     fn handle_terminal_notice_when_not_connected() {
         let mut app = App::for_test();
         let key = KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE);
         app.handle_key(key).unwrap();
         assert!(app.notice.is_some());
-        assert!(matches!(app.notice_action, Some(NoticeAction::ConnectTerminal)));
+        assert!(matches!(
+            app.notice_action,
+            Some(NoticeAction::ConnectTerminal)
+        ));
     }
 
     #[test]
@@ -900,17 +1152,24 @@ mod tests {
             name: "test".to_string(),
             user: "user".to_string(),
             host: "host".to_string(),
+            port: 22,
             auth: AuthConfig::Password {
-                password: "pw".to_string(),
+                password: "pw".into(),
             },
             history: vec![],
             last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
         };
         app.connections.push(connection.clone());
         app.open_connections.push(OpenConnection {
             config: connection,
-            session: ssh2::Session::new().unwrap(),
+            session: crate::ssh::SharedSession::new(ssh2::Session::new().unwrap()),
             connected_at: SystemTime::now(),
+            keepalive: crate::model::KeepaliveStatus::default(),
+            asked: Default::default(),
         });
         app.selected_saved = 0;
         app.remote_picker = Some(RemotePickerState {
@@ -929,7 +1188,11 @@ mod tests {
 
         let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
         app.handle_remote_picker_key(key).unwrap();
+        assert!(app.notice.is_none());
+        let fetch = app.remote_fetch.take().unwrap().recv().unwrap();
+        app.apply_remote_fetch(fetch);
         assert!(app.notice.is_some());
+        assert_eq!(app.remote_picker.as_ref().unwrap().cwd, "/");
     }
 }
 
@@ -0,0 +1,205 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::{App, ConnectIntent, HostKeyPrompt};
+use crate::known_hosts::{HostKeyError, save_known_host, trust_host_key_once};
+use crate::model::{ConnectionConfig, Notice, TryResult};
+
+impl App {
+    pub(super) fn prompt_host_key(
+        &mut self,
+        err: &anyhow::Error,
+        config: &ConnectionConfig,
+        retry: ConnectIntent,
+    ) -> bool {
+        let Some(HostKeyError::Unknown(info)) = err.downcast_ref::<HostKeyError>() else {
+            return false;
+        };
+        self.set_status(format!("Verify host key for {}", info.address()));
+        self.host_key_prompt = Some(HostKeyPrompt {
+            info: info.clone(),
+            config: config.clone(),
+            retry,
+        });
+        true
+    }
+
+    pub(super) fn notify_host_key_changed(
+        &mut self,
+        config: &ConnectionConfig,
+        err: &anyhow::Error,
+    ) -> bool {
+        let Some(HostKeyError::Changed(info)) = err.downcast_ref::<HostKeyError>() else {
+            return false;
+        };
+        self.record_connect_error(config, err);
+        self.notice = Some(Notice {
+            title: "Host key changed".to_string(),
+            message: format!(
+                "The host key for {} does not match ~/.ssh/known_hosts.\n{} {}\nThis could mean someone is intercepting the connection. Remove the old entry if the change is expected.",
+                info.address(),
+                info.key_type,
+                info.fingerprint
+            ),
+        });
+        self.set_status(format!("Host key changed for {}", info.address()));
+        true
+    }
+
+    pub(super) fn handle_host_key_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.host_key_prompt.clone() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('o') => {
+                self.host_key_prompt = None;
+                trust_host_key_once(&prompt.info);
+                self.set_status(format!("Host key accepted for {}", prompt.info.address()));
+                self.retry_after_host_key(prompt.config, prompt.retry);
+            }
+            KeyCode::Char('s') => {
+                self.host_key_prompt = None;
+                trust_host_key_once(&prompt.info);
+                match save_known_host(&prompt.info) {
+                    Ok(()) => self.set_status(format!(
+                        "Host key for {} saved to known_hosts",
+                        prompt.info.address()
+                    )),
+                    Err(err) => self.set_status(format!("Failed to save host key: {err}")),
+                }
+                self.retry_after_host_key(prompt.config, prompt.retry);
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('r') => {
+                self.host_key_prompt = None;
+                self.reject_host_key(&prompt.config, prompt.retry);
+            }
+            _ => {}
+        }
+    }
+
+    fn retry_after_host_key(&mut self, config: ConnectionConfig, retry: ConnectIntent) {
+        match retry {
+            ConnectIntent::Reconnect => {
+                self.retry_reconnect_now(&crate::model::connection_key(&config));
+            }
+            _ => self.start_connect(config, retry),
+        }
+    }
+
+    fn reject_host_key(&mut self, config: &ConnectionConfig, retry: ConnectIntent) {
+        let err = anyhow::anyhow!("Host key rejected");
+        match retry {
+            ConnectIntent::Open(_) => self.record_connect_error(config, &err),
+            ConnectIntent::Test => {
+                self.try_result = Some(TryResult {
+                    success: false,
+                    message: format!("Connection failed: {err}"),
+                });
+            }
+            ConnectIntent::Save => {
+                self.new_connection_feedback = Some(format!("Connection failed: {err}"));
+            }
+            ConnectIntent::Terminal { .. } | ConnectIntent::ReopenTerminal { .. } => {}
+            ConnectIntent::Reconnect => {
+                self.cancel_reconnect(&crate::model::connection_key(config));
+            }
+        }
+        self.set_status(err.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+    use crate::known_hosts::HostKeyInfo;
+
+    fn unknown_key_error() -> anyhow::Error {
+        let info = HostKeyInfo {
+            host: "host".to_string(),
+            port: 22,
+            key_type: "ssh-ed25519".to_string(),
+            fingerprint: "SHA256:abc".to_string(),
+            key: vec![1, 2, 3],
+        };
+        HostKeyError::Unknown(info).into()
+    }
+
+    fn config(name: &str) -> ConnectionConfig {
+        ConnectionConfig {
+            name: name.to_string(),
+            user: "user".to_string(),
+            host: format!("{name}.invalid"),
+            port: 22,
+            auth: crate::model::AuthConfig::KeyboardInteractive,
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    #[test]
+    fn unknown_host_key_opens_prompt() {
+        let mut app = App::for_test();
+        let err = unknown_key_error().context("connect");
+        assert!(app.prompt_host_key(&err, &config("a"), ConnectIntent::Test));
+        assert!(app.host_key_prompt.is_some());
+        let other = anyhow::anyhow!("other");
+        assert!(!app.prompt_host_key(&other, &config("a"), ConnectIntent::Test));
+    }
+
+    #[test]
+    fn rejecting_host_key_reports_failure() {
+        let mut app = App::for_test();
+        app.prompt_host_key(&unknown_key_error(), &config("a"), ConnectIntent::Test);
+        app.handle_host_key_prompt_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert!(app.host_key_prompt.is_none());
+        let result = app.try_result.as_ref().unwrap();
+        assert!(!result.success);
+        assert!(result.message.contains("rejected"));
+    }
+
+    #[test]
+    fn host_key_decision_applies_to_the_host_that_asked() {
+        let mut app = App::for_test();
+        app.connections = vec![config("a"), config("b")];
+        app.selected_saved = 0;
+        app.prompt_host_key(
+            &unknown_key_error(),
+            &config("a"),
+            ConnectIntent::Open(None),
+        );
+        app.selected_saved = 1;
+        app.handle_host_key_prompt_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(
+            app.connections
+                .iter()
+                .find(|c| c.name == "a")
+                .unwrap()
+                .history
+                .len(),
+            1
+        );
+        assert!(
+            app.connections
+                .iter()
+                .find(|c| c.name == "b")
+                .unwrap()
+                .history
+                .is_empty()
+        );
+
+        app.prompt_host_key(
+            &unknown_key_error(),
+            &config("a"),
+            ConnectIntent::Open(None),
+        );
+        app.handle_host_key_prompt_key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE));
+        let key = crate::model::connection_key(&config("a"));
+        assert!(app.pending_connects.contains_key(&key));
+        app.abort_pending_connect(&key);
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::{Duration, Instant};
+
+use crate::app::App;
+use crate::model::{ConnectionHealth, OpenConnection, connection_key};
+use crate::ssh::probe_session;
+
+impl App {
+    pub(crate) fn poll_keepalives(&mut self) {
+        let mut finished = Vec::new();
+        for (key, rx) in &self.keepalive_probes {
+            match rx.try_recv() {
+                Ok(alive) => finished.push((key.clone(), alive)),
+                Err(TryRecvError::Disconnected) => finished.push((key.clone(), false)),
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        for (key, alive) in finished {
+            self.keepalive_probes.remove(&key);
+            self.record_keepalive(&key, alive);
+        }
+
+        let now = Instant::now();
+        for conn in &mut self.open_connections {
+            if !probe_due(conn, now) {
+                continue;
+            }
+            let key = connection_key(&conn.config);
+            if self.keepalive_probes.contains_key(&key) {
+                continue;
+            }
+            conn.keepalive.last_probe = now;
+            let session = conn.session.clone();
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(probe_session(&session.lock()));
+            });
+            self.keepalive_probes.insert(key, rx);
+        }
+    }
+
+    pub(super) fn record_keepalive(&mut self, key: &str, alive: bool) {
+        let Some(conn) = self
+            .open_connections
+            .iter_mut()
+            .find(|conn| connection_key(&conn.config) == key)
+        else {
+            return;
+        };
+        let status = &mut conn.keepalive;
+        if alive {
+            status.missed = 0;
+            status.health = ConnectionHealth::Alive;
+            return;
+        }
+        status.missed += 1;
+        let max_missed = conn.config.keepalive.max_missed.max(1);
+        let label = conn.config.label();
+        if status.missed < max_missed {
+            status.health = ConnectionHealth::Stale;
+            let missed = status.missed;
+            self.set_status(format!(
+                "No keepalive reply from {label} ({missed}/{max_missed})"
+            ));
+            return;
+        }
+        status.health = ConnectionHealth::Dead;
+        let config = conn.config.clone();
+        self.disconnect_terminal_tabs(key);
+        self.stop_forwards(key);
+        if self.reconnect_needs_user(&config) {
+            self.last_error.insert(
+                key.to_string(),
+                format!(
+                    "Connection lost: {max_missed} keepalives unanswered, press r to reconnect"
+                ),
+            );
+            self.set_status(format!("Connection to {label} lost, press r to reconnect"));
+            return;
+        }
+        self.last_error.insert(
+            key.to_string(),
+            format!("Connection lost: {max_missed} keepalives unanswered"),
+        );
+        self.schedule_reconnect(config);
+        self.set_status(format!("Connection to {label} lost, reconnecting"));
+    }
+}
+
+fn probe_due(conn: &OpenConnection, now: Instant) -> bool {
+    let interval = Duration::from_secs(conn.config.keepalive.interval_secs.into());
+    !interval.is_zero()
+        && conn.keepalive.health != ConnectionHealth::Dead
+        && now.duration_since(conn.keepalive.last_probe) >= interval
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AuthConfig, ConnectionConfig, KeepaliveConfig};
+    use crate::secret::{AskPolicy, SecretSource};
+
+    fn open(app: &mut App, max_missed: u32, auth: AuthConfig) -> String {
+        let config = ConnectionConfig {
+            name: "box".to_string(),
+            user: "u".to_string(),
+            host: "box.example".to_string(),
+            port: 22,
+            auth,
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig {
+                interval_secs: 30,
+                max_missed,
+            },
+            forwards: vec![],
+        };
+        app.connections.push(config.clone());
+        app.open_connections.push(OpenConnection {
+            config: config.clone(),
+            session: crate::ssh::SharedSession::new(ssh2::Session::new().unwrap()),
+            connected_at: std::time::SystemTime::now(),
+            keepalive: Default::default(),
+            asked: Default::default(),
+        });
+        connection_key(&config)
+    }
+
+    #[test]
+    fn missed_keepalives_go_stale_then_dead() {
+        let mut app = App::for_test();
+        let key = open(&mut app, 2, AuthConfig::Agent { identity: None });
+        app.record_keepalive(&key, false);
+        assert_eq!(
+            app.open_connections[0].keepalive.health,
+            ConnectionHealth::Stale
+        );
+        app.record_keepalive(&key, true);
+        assert_eq!(
+            app.open_connections[0].keepalive.health,
+            ConnectionHealth::Alive
+        );
+        app.record_keepalive(&key, false);
+        app.record_keepalive(&key, false);
+        assert_eq!(
+            app.open_connections[0].keepalive.health,
+            ConnectionHealth::Dead
+        );
+        assert!(app.last_error.contains_key(&key));
+        assert!(app.selected_connected_connection().is_none());
+        assert!(app.reconnect_label(&key).is_some());
+    }
+
+    #[test]
+    fn lost_connections_that_would_prompt_wait_for_the_user() {
+        let asked = |ask| AuthConfig::Password {
+            password: SecretSource::Ask { ask },
+        };
+        let mut app = App::for_test();
+        let key = open(&mut app, 1, asked(AskPolicy::EveryTime));
+        app.record_keepalive(&key, false);
+        assert!(app.reconnects.is_empty());
+        assert!(app.status.contains("press r"));
+
+        let mut app = App::for_test();
+        let key = open(&mut app, 1, AuthConfig::KeyboardInteractive);
+        app.record_keepalive(&key, false);
+        assert!(app.reconnects.is_empty());
+        let r = crossterm::event::KeyEvent::new(
+            crossterm::event::KeyCode::Char('r'),
+            crossterm::event::KeyModifiers::NONE,
+        );
+        app.handle_key(r).unwrap();
+        assert!(app.pending_connects.contains_key(&key));
+        app.abort_pending_connect(&key);
+
+        let mut app = App::for_test();
+        let key = open(&mut app, 1, asked(AskPolicy::WhileConnected));
+        app.open_connections[0]
+            .asked
+            .insert(key.clone(), "typed".into());
+        app.record_keepalive(&key, false);
+        assert_eq!(app.reconnects.len(), 1);
+    }
+
+    #[test]
+    fn probes_are_due_after_interval_unless_off_or_dead() {
+        let mut app = App::for_test();
+        open(&mut app, 3, AuthConfig::Agent { identity: None });
+        let conn = &mut app.open_connections[0];
+        let start = conn.keepalive.last_probe;
+        assert!(!probe_due(conn, start + Duration::from_secs(29)));
+        assert!(probe_due(conn, start + Duration::from_secs(30)));
+        conn.keepalive.health = ConnectionHealth::Dead;
+        assert!(!probe_due(conn, start + Duration::from_secs(60)));
+        conn.keepalive.health = ConnectionHealth::Alive;
+        conn.config.keepalive.interval_secs = 0;
+        assert!(!probe_due(conn, start + Duration::from_secs(60)));
+    }
+}
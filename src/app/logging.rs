@@ -4,11 +4,11 @@ use std::path::Path;
 
 use chrono::Datelike;
 
+use crate::app::App;
 use crate::app::constants::{
-    LOG_MAX_ENTRIES, LOG_MAX_IN_MEMORY, LOG_PARSE_FORMAT, LOG_RETENTION_DAYS,
-    LOG_SEPARATOR, LOG_TIMESTAMP_FORMAT,
+    LOG_MAX_ENTRIES, LOG_MAX_IN_MEMORY, LOG_PARSE_FORMAT, LOG_RETENTION_DAYS, LOG_SEPARATOR,
+    LOG_TIMESTAMP_FORMAT,
 };
-use crate::app::App;
 
 impl App {
     pub(crate) fn set_status(&mut self, message: impl Into<String>) {
@@ -20,15 +20,17 @@ impl App {
     pub(super) fn log_line(&mut self, message: &str) {
         let timestamp = chrono::Local::now().format(LOG_TIMESTAMP_FORMAT);
         let line = format!("{timestamp}{LOG_SEPARATOR}{message}");
-        if let Some(parent) = self.log_path.parent() {
-            let _ = fs::create_dir_all(parent);
-        }
-        if let Ok(mut file) = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.log_path)
-        {
-            let _ = writeln!(file, "{line}");
+        if !self.sealed_vault {
+            if let Some(parent) = self.log_path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            if let Ok(mut file) = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.log_path)
+            {
+                let _ = writeln!(file, "{line}");
+            }
         }
         self.last_log = line.clone();
         self.log_lines.push_back(line);
@@ -48,8 +50,7 @@ pub(crate) fn prune_log_file(path: &Path) {
     for line in content.lines() {
         if let Some((timestamp, _)) = line.split_once(LOG_SEPARATOR) {
             let with_year = format!("{current_year}-{timestamp}");
-            if let Ok(parsed) =
-                chrono::NaiveDateTime::parse_from_str(&with_year, LOG_PARSE_FORMAT)
+            if let Ok(parsed) = chrono::NaiveDateTime::parse_from_str(&with_year, LOG_PARSE_FORMAT)
             {
                 if parsed >= cutoff {
                     kept.push(line.to_string());
@@ -90,7 +91,11 @@ mod tests {
         let old = now - chrono::Duration::days(LOG_RETENTION_DAYS + 1);
         let recent = now - chrono::Duration::days(1);
         let old_line = format!("{}{}old", old.format(LOG_TIMESTAMP_FORMAT), LOG_SEPARATOR);
-        let recent_line = format!("{}{}recent", recent.format(LOG_TIMESTAMP_FORMAT), LOG_SEPARATOR);
+        let recent_line = format!(
+            "{}{}recent",
+            recent.format(LOG_TIMESTAMP_FORMAT),
+            LOG_SEPARATOR
+        );
         fs::write(&path, format!("{old_line}\n{recent_line}\n")).unwrap();
         prune_log_file(&path);
         let content = fs::read_to_string(&path).unwrap();
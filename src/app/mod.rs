@@ -6,22 +6,36 @@ use anyhow::Result;
 
 use crate::app::constants::{LOG_NO_LOGS_MESSAGE, STATUS_READY};
 use crate::app::logging::prune_log_file;
+use crate::auth_prompt::AuthPromptRequest;
 use crate::model::{
-    AppAction, ConnectionConfig, FilePickerState, KeyPickerState, MasterPasswordState, Mode,
-    NewConnectionState, Notice, OpenConnection, RemoteEntry, RemotePickerState, TransferState,
-    TransferUpdate, TryResult,
+    AppAction, BundleExportState, BundleImportState, ConnectionConfig, FilePickerState,
+    KeyPickerState, MasterPasswordState, Mode, NewConnectionState, Notice, OpenConnection,
+    RemoteEntry, RemotePickerState, SshExportState, SshImportState, TransferState, TransferUpdate,
+    TryResult,
 };
-use crate::storage::{config_path, load_or_init_store, log_path};
+use crate::profile::Profile;
+use crate::secret::SecretKey;
+use crate::storage::open_store;
 
-mod constants;
+mod auth_prompts;
+mod bundle;
 mod connections;
+mod constants;
+mod forwards;
 mod handlers;
 mod helpers;
+mod host_keys;
+mod keepalive;
 mod logging;
 mod pickers;
+mod reconnect;
 mod ssh_backend;
+mod ssh_export;
+mod ssh_import;
+mod sync;
 pub(crate) mod terminal;
 mod transfer;
+mod unlock;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum HeaderMode {
@@ -37,15 +51,77 @@ pub(super) enum NoticeAction {
     ConnectDownload,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub(super) enum ConnectIntent {
+    Open(Option<NoticeAction>),
+    Test,
+    Save,
+    Terminal { cols: u16, rows: u16 },
+    Reconnect,
+    ReopenTerminal { id: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct HostKeyPrompt {
+    pub(crate) info: crate::known_hosts::HostKeyInfo,
+    pub(super) config: ConnectionConfig,
+    pub(super) retry: ConnectIntent,
+}
+
+pub(crate) struct PendingConnect {
+    pub(crate) config: ConnectionConfig,
+    pub(super) intent: ConnectIntent,
+    pub(super) rx: mpsc::Receiver<Result<ssh2::Session>>,
+    pub(crate) phase: crate::ssh::ConnectPhase,
+    pub(super) phase_rx: mpsc::Receiver<crate::ssh::ConnectPhase>,
+    pub(super) cancel: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    pub(super) answers_rx: mpsc::Receiver<(String, crate::secret::Secret)>,
+    pub(super) prompts_rx: mpsc::Receiver<AuthPromptRequest>,
+    pub(crate) started: std::time::Instant,
+}
+
+pub(crate) struct Reconnect {
+    pub(crate) config: ConnectionConfig,
+    pub(crate) attempt: u32,
+    pub(crate) next_at: std::time::Instant,
+    pub(crate) in_flight: bool,
+}
+
+pub(crate) enum RemoteFetch {
+    Listed {
+        cwd: String,
+        entries: Result<Vec<RemoteEntry>>,
+    },
+    NoSubfolders,
+}
+
+pub(crate) struct AuthPromptState {
+    pub(crate) request: AuthPromptRequest,
+    pub(crate) answers: Vec<String>,
+    pub(crate) active: usize,
+    pub(crate) connect_key: Option<String>,
+}
+
 pub(crate) struct App {
+    pub(crate) profile: Profile,
     pub(crate) config_path: PathBuf,
     pub(crate) log_path: PathBuf,
     pub(crate) last_log: String,
     pub(crate) log_lines: VecDeque<String>,
     pub(crate) last_local_dir: Option<PathBuf>,
+    pub(crate) sealed_vault: bool,
     pub(crate) master: crate::model::MasterConfig,
-    pub(crate) master_key: Vec<u8>,
+    pub(crate) master_key: SecretKey,
     pub(crate) connections: Vec<ConnectionConfig>,
+    pub(crate) store_base: Vec<ConnectionConfig>,
+    pub(crate) store_fingerprint: Option<[u8; 32]>,
+    pub(crate) save_conflicts: Vec<crate::merge::MergeConflict>,
+    pub(crate) unlock: Option<crate::model::UnlockState>,
+    pub(crate) startup_store: Option<crate::model::StoreFile>,
+    pub(crate) locked_vault: Vec<crate::model::StoredConnection>,
+    pub(crate) save_deferred: bool,
+    pub(crate) auto_lock: Option<std::time::Duration>,
+    pub(crate) last_activity: std::time::Instant,
     pub(crate) selected_saved: usize,
     pub(crate) selected_tab: usize,
     pub(crate) open_connections: Vec<OpenConnection>,
@@ -57,18 +133,30 @@ pub(crate) struct App {
     pub(crate) key_picker: Option<KeyPickerState>,
     pub(crate) pending_action: Option<AppAction>,
     pub(crate) last_error: HashMap<String, String>,
-    pub(crate) edit_index: Option<usize>,
-    pub(crate) delete_index: Option<usize>,
+    pub(crate) edit_key: Option<String>,
+    pub(crate) delete_key: Option<String>,
     pub(crate) try_result: Option<TryResult>,
     pub(crate) new_connection_feedback: Option<String>,
     pub(crate) notice: Option<Notice>,
     pub(super) notice_action: Option<NoticeAction>,
+    pub(crate) host_key_prompt: Option<HostKeyPrompt>,
+    pub(crate) auth_prompt: Option<AuthPromptState>,
+    pub(crate) auth_prompt_tx: mpsc::Sender<AuthPromptRequest>,
+    pub(crate) auth_prompt_rx: mpsc::Receiver<AuthPromptRequest>,
+    pub(crate) pending_connects: HashMap<String, PendingConnect>,
+    pub(crate) keepalive_probes: HashMap<String, mpsc::Receiver<bool>>,
+    pub(crate) forwarders: HashMap<String, crate::forward::Forwarder>,
+    pub(crate) reconnects: Vec<Reconnect>,
     pub(crate) header_mode: HeaderMode,
     pub(crate) history_page: usize,
     pub(crate) details_height: u16,
     pub(crate) transfer: Option<TransferState>,
     pub(crate) remote_picker: Option<RemotePickerState>,
-    pub(crate) remote_fetch: Option<mpsc::Receiver<Result<Vec<RemoteEntry>>>>,
+    pub(crate) ssh_import: Option<SshImportState>,
+    pub(crate) ssh_export: Option<SshExportState>,
+    pub(crate) bundle_export: Option<BundleExportState>,
+    pub(crate) bundle_import: Option<BundleImportState>,
+    pub(crate) remote_fetch: Option<mpsc::Receiver<RemoteFetch>>,
     pub(crate) transfer_progress: Option<mpsc::Receiver<TransferUpdate>>,
     pub(crate) transfer_cancel: Option<mpsc::Sender<()>>,
     pub(crate) transfer_hidden: bool,
@@ -77,27 +165,40 @@ pub(crate) struct App {
     pub(crate) size_calc_generation: u64,
     pub(crate) terminal_tabs: Vec<crate::app::terminal::TerminalTab>,
     pub(crate) active_terminal_tab: usize,
+    pub(crate) next_terminal_id: u64,
     pub(crate) ssh_backend: Arc<dyn crate::app::ssh_backend::SshBackend>,
     pub(crate) clipboard: Option<arboard::Clipboard>,
 }
 
 impl App {
-    pub(crate) fn load_with_master() -> Result<Self> {
-        let config_path = config_path()?;
-        let (master, master_key, connections, last_local_dir) = load_or_init_store(&config_path)?;
-        let log_path = log_path()?;
+    pub(crate) fn load(profile: Profile) -> Result<Self> {
+        let config_path = profile.config_path()?;
+        let opened = open_store(&config_path);
+        let log_path = profile.log_path()?;
         prune_log_file(&log_path);
         let log_lines = VecDeque::new();
         let last_log = String::from(LOG_NO_LOGS_MESSAGE);
+        let (auth_prompt_tx, auth_prompt_rx) = mpsc::channel();
         let mut app = Self {
+            profile,
             config_path,
             log_path,
             last_log,
             log_lines,
-            last_local_dir,
-            master,
-            master_key,
-            connections,
+            last_local_dir: None,
+            sealed_vault: false,
+            master: crate::model::MasterConfig::default(),
+            master_key: SecretKey::default(),
+            store_base: vec![],
+            store_fingerprint: None,
+            save_conflicts: vec![],
+            unlock: None,
+            startup_store: None,
+            locked_vault: vec![],
+            save_deferred: false,
+            auto_lock: unlock::auto_lock_timeout(),
+            last_activity: std::time::Instant::now(),
+            connections: vec![],
             selected_saved: 0,
             selected_tab: 0,
             open_connections: vec![],
@@ -109,17 +210,29 @@ impl App {
             key_picker: None,
             pending_action: None,
             last_error: HashMap::new(),
-            edit_index: None,
-            delete_index: None,
+            edit_key: None,
+            delete_key: None,
             try_result: None,
             new_connection_feedback: None,
             notice: None,
             notice_action: None,
+            host_key_prompt: None,
+            auth_prompt: None,
+            auth_prompt_tx,
+            auth_prompt_rx,
+            pending_connects: HashMap::new(),
+            keepalive_probes: HashMap::new(),
+            forwarders: HashMap::new(),
+            reconnects: vec![],
             header_mode: HeaderMode::Help,
             history_page: 0,
             details_height: 0,
             transfer: None,
             remote_picker: None,
+            ssh_import: None,
+            ssh_export: None,
+            bundle_export: None,
+            bundle_import: None,
             remote_fetch: None,
             transfer_progress: None,
             transfer_cancel: None,
@@ -129,10 +242,11 @@ impl App {
             size_calc_generation: 0,
             terminal_tabs: vec![],
             active_terminal_tab: 0,
+            next_terminal_id: 0,
             ssh_backend: Arc::new(crate::app::ssh_backend::RealSshBackend::default()),
             clipboard: None,
         };
-        app.sort_connections_by_recent(None);
+        app.begin_startup(opened);
         app.set_status(STATUS_READY);
         Ok(app)
     }
@@ -154,11 +268,18 @@ impl App {
     ) -> Self {
         let (master, master_key) = crate::storage::create_master_from_password("test-password")
             .expect("create master key");
+        static NEXT_STORE: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        let store_id = NEXT_STORE.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         let mut config_path = std::env::temp_dir();
-        config_path.push("ssh-client-test-config.json");
+        config_path.push(format!(
+            "ssh-client-test-{}-{store_id}/config.json",
+            std::process::id()
+        ));
         let mut log_path = std::env::temp_dir();
         log_path.push("ssh-client-test.log");
+        let (auth_prompt_tx, auth_prompt_rx) = mpsc::channel();
         Self {
+            profile: Profile::default(),
             config_path,
             log_path,
             last_log: crate::app::constants::LOG_NO_LOGS_MESSAGE.to_string(),
@@ -166,6 +287,15 @@ impl App {
             master,
             master_key,
             connections: vec![],
+            store_base: vec![],
+            store_fingerprint: None,
+            save_conflicts: vec![],
+            unlock: None,
+            startup_store: None,
+            locked_vault: vec![],
+            save_deferred: false,
+            auto_lock: None,
+            last_activity: std::time::Instant::now(),
             selected_saved: 0,
             selected_tab: 0,
             open_connections: vec![],
@@ -177,17 +307,29 @@ impl App {
             key_picker: None,
             pending_action: None,
             last_error: std::collections::HashMap::new(),
-            edit_index: None,
-            delete_index: None,
+            edit_key: None,
+            delete_key: None,
             try_result: None,
             new_connection_feedback: None,
             notice: None,
             notice_action: None,
+            host_key_prompt: None,
+            auth_prompt: None,
+            auth_prompt_tx,
+            auth_prompt_rx,
+            pending_connects: HashMap::new(),
+            keepalive_probes: HashMap::new(),
+            forwarders: HashMap::new(),
+            reconnects: vec![],
             header_mode: HeaderMode::Help,
             history_page: 0,
             details_height: 0,
             transfer: None,
             remote_picker: None,
+            ssh_import: None,
+            ssh_export: None,
+            bundle_export: None,
+            bundle_import: None,
             remote_fetch: None,
             transfer_progress: None,
             transfer_cancel: None,
@@ -197,7 +339,9 @@ impl App {
             size_calc_generation: 0,
             terminal_tabs: vec![],
             active_terminal_tab: 0,
+            next_terminal_id: 0,
             last_local_dir: None,
+            sealed_vault: false,
             ssh_backend,
             clipboard: None,
         }
@@ -3,9 +3,12 @@ use std::sync::mpsc;
 
 use anyhow::Result;
 
+use crate::app::constants::{NOTICE_NO_SUBFOLDERS_MESSAGE, NOTICE_NO_SUBFOLDERS_TITLE};
 use crate::app::helpers::{read_dir_entries_filtered, resolve_picker_start};
-use crate::app::App;
-use crate::model::{AuthConfig, FilePickerState, KeyPickerState, RemoteEntry, RemotePickerState};
+use crate::app::ssh_backend::SshBackend;
+use crate::app::{App, RemoteFetch};
+use crate::model::{AuthConfig, FilePickerState, KeyPickerState, Notice, RemotePickerState};
+use crate::ssh::SharedSession;
 
 impl App {
     pub(crate) fn open_file_picker(&mut self) -> Result<()> {
@@ -20,7 +23,11 @@ impl App {
         Ok(())
     }
 
-    pub(crate) fn open_local_picker(&mut self, start: Option<PathBuf>, only_dirs: bool) -> Result<()> {
+    pub(crate) fn open_local_picker(
+        &mut self,
+        start: Option<PathBuf>,
+        only_dirs: bool,
+    ) -> Result<()> {
         let start_dir = match start {
             Some(dir) => dir,
             None => self
@@ -49,11 +56,12 @@ impl App {
     }
 
     pub(crate) fn open_remote_picker(&mut self) -> Result<()> {
-        let cwd = if let Some(conn) = self.selected_connected_connection() {
-            self.connections
-                .iter()
-                .find(|candidate| crate::model::same_identity(candidate, &conn))
-                .and_then(|candidate| candidate.last_remote_dir.clone())
+        let connected = self.selected_connected_connection().is_some();
+        let cwd = if let Some(conn) = self.connections.get(self.selected_saved)
+            && connected
+        {
+            conn.last_remote_dir
+                .clone()
                 .unwrap_or_else(|| format!("/home/{}", conn.user))
         } else {
             "/".to_string()
@@ -75,24 +83,7 @@ impl App {
             only_dirs,
             show_hidden: false,
         });
-        if self.try_load_remote_dir(cwd.clone(), only_dirs).is_ok() {
-            return Ok(());
-        }
-        if let Some(next) = self.remote_home_fallback() {
-            if self.try_load_remote_dir(next.clone(), only_dirs).is_ok() {
-                if let Some(picker) = &mut self.remote_picker {
-                    picker.cwd = next;
-                }
-                return Ok(());
-            }
-        }
-        if let Err(err) = self.start_remote_fetch("/".to_string(), only_dirs) {
-            return Err(err);
-        }
-        if let Some(picker) = &mut self.remote_picker {
-            picker.cwd = "/".to_string();
-        }
-        Ok(())
+        self.start_remote_fetch(cwd, only_dirs, false)
     }
 
     pub(crate) fn load_remote_dir(&mut self, cwd: String, only_dirs: bool) -> Result<()> {
@@ -103,24 +94,15 @@ impl App {
             picker.loading = true;
             picker.error = None;
         }
-        if self.try_load_remote_dir(cwd.clone(), only_dirs).is_ok() {
-            return Ok(());
-        }
-        if let Some(next) = self.remote_home_fallback() {
-            if self.try_load_remote_dir(next.clone(), only_dirs).is_ok() {
-                if let Some(picker) = &mut self.remote_picker {
-                    picker.cwd = next;
-                }
-                return Ok(());
-            }
-        }
-        if let Err(err) = self.start_remote_fetch("/".to_string(), only_dirs) {
-            return Err(err);
-        }
+        self.start_remote_fetch(cwd, only_dirs, false)
+    }
+
+    pub(crate) fn descend_remote_dir(&mut self, cwd: String) -> Result<()> {
         if let Some(picker) = &mut self.remote_picker {
-            picker.cwd = "/".to_string();
+            picker.loading = true;
+            picker.error = None;
         }
-        Ok(())
+        self.start_remote_fetch(cwd, true, true)
     }
 
     pub(crate) fn open_local_target_picker(&mut self) -> Result<()> {
@@ -131,8 +113,13 @@ impl App {
         self.open_local_picker(start, true)
     }
 
-    pub(crate) fn start_remote_fetch(&mut self, cwd: String, only_dirs: bool) -> Result<()> {
-        let Some(conn) = self.selected_connected_connection() else {
+    fn start_remote_fetch(&mut self, cwd: String, only_dirs: bool, descend: bool) -> Result<()> {
+        // Every session call runs in the worker: the session lock can be held
+        // for seconds by a forward waiting on a channel open.
+        let Some(session) = self
+            .selected_connected_connection()
+            .map(|open| open.session.clone())
+        else {
             anyhow::bail!("Selected connection is not connected");
         };
         let show_hidden = self
@@ -143,10 +130,25 @@ impl App {
         let (tx, rx) = mpsc::channel();
         let backend = self.ssh_backend.clone();
         std::thread::spawn(move || {
-            let result = (|| -> Result<Vec<RemoteEntry>> {
-                backend.list_remote_dir(None, &conn, &cwd, only_dirs, show_hidden)
-            })();
-            let _ = tx.send(result);
+            let fetch = if descend {
+                match backend.remote_has_subdirectories(&session, &cwd) {
+                    Ok(false) => RemoteFetch::NoSubfolders,
+                    Ok(true) => list_remote_with_fallback(
+                        backend.as_ref(),
+                        &session,
+                        cwd,
+                        only_dirs,
+                        show_hidden,
+                    ),
+                    Err(err) => RemoteFetch::Listed {
+                        cwd,
+                        entries: Err(err),
+                    },
+                }
+            } else {
+                list_remote_with_fallback(backend.as_ref(), &session, cwd, only_dirs, show_hidden)
+            };
+            let _ = tx.send(fetch);
         });
         self.remote_fetch = Some(rx);
         Ok(())
@@ -156,10 +158,28 @@ impl App {
         let Some(rx) = &self.remote_fetch else {
             return;
         };
-        if let Ok(result) = rx.try_recv() {
-            if let Some(picker) = &mut self.remote_picker {
-                picker.loading = false;
-                match result {
+        if let Ok(fetch) = rx.try_recv() {
+            self.remote_fetch = None;
+            self.apply_remote_fetch(fetch);
+        }
+    }
+
+    pub(crate) fn apply_remote_fetch(&mut self, fetch: RemoteFetch) {
+        let Some(picker) = &mut self.remote_picker else {
+            return;
+        };
+        picker.loading = false;
+        match fetch {
+            RemoteFetch::NoSubfolders => {
+                self.notice = Some(Notice {
+                    title: NOTICE_NO_SUBFOLDERS_TITLE.to_string(),
+                    message: NOTICE_NO_SUBFOLDERS_MESSAGE.to_string(),
+                });
+            }
+            RemoteFetch::Listed { cwd, entries } => {
+                picker.cwd = cwd;
+                picker.selected = 0;
+                match entries {
                     Ok(entries) => {
                         if picker.only_dirs {
                             picker.entries = entries.into_iter().filter(|e| e.is_dir).collect();
@@ -169,11 +189,11 @@ impl App {
                         picker.error = None;
                     }
                     Err(err) => {
+                        picker.entries.clear();
                         picker.error = Some(err.to_string());
                     }
                 }
             }
-            self.remote_fetch = None;
         }
     }
 
@@ -183,56 +203,54 @@ impl App {
         for conn in &self.connections {
             if let AuthConfig::PrivateKey { path, password } = &conn.auth {
                 if seen.insert(path.clone()) {
-                    candidates.push(crate::model::KeyCandidate {
+                    candidates.push(crate::model::KeyCandidate::File {
                         path: path.clone(),
                         password: password.clone(),
                     });
                 }
             }
         }
+        if let Ok(identities) = crate::ssh::list_agent_identities() {
+            for (comment, fingerprint) in identities {
+                candidates.push(crate::model::KeyCandidate::Agent {
+                    comment,
+                    fingerprint,
+                });
+            }
+        }
         candidates
     }
+}
 
-    fn try_load_remote_dir(&mut self, cwd: String, only_dirs: bool) -> Result<()> {
-        let Some(conn) = self.selected_connected_connection() else {
-            anyhow::bail!("Selected connection is not connected");
+fn list_remote_with_fallback(
+    backend: &dyn SshBackend,
+    session: &SharedSession,
+    cwd: String,
+    only_dirs: bool,
+    show_hidden: bool,
+) -> RemoteFetch {
+    if let Ok(entries) = backend.list_remote_dir(session, &cwd, only_dirs, show_hidden) {
+        return RemoteFetch::Listed {
+            cwd,
+            entries: Ok(entries),
         };
-        let show_hidden = self
-            .remote_picker
-            .as_ref()
-            .map(|picker| picker.show_hidden)
-            .unwrap_or(false);
-        let entries = self
-            .ssh_backend
-            .list_remote_dir(
-                Some(&self.open_connections),
-                &conn,
-                &cwd,
-                only_dirs,
-                show_hidden,
-            )?;
-        if let Some(picker) = &mut self.remote_picker {
-            picker.entries = entries;
-            picker.loading = false;
-            picker.error = None;
-        }
-        Ok(())
     }
-
-    fn remote_home_fallback(&self) -> Option<String> {
-        let conn = self.selected_connected_connection()?;
-        let home = self
-            .ssh_backend
-            .remote_home_dir(Some(&self.open_connections), &conn)
-            .ok()
-            .flatten()?;
-        let trimmed = home.trim();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed.to_string())
-        }
+    if let Some(home) = backend
+        .remote_home_dir(session)
+        .ok()
+        .flatten()
+        .map(|home| home.trim().to_string())
+        .filter(|home| !home.is_empty())
+        && let Ok(entries) = backend.list_remote_dir(session, &home, only_dirs, show_hidden)
+    {
+        return RemoteFetch::Listed {
+            cwd: home,
+            entries: Ok(entries),
+        };
     }
+    let cwd = "/".to_string();
+    let entries = backend.list_remote_dir(session, &cwd, only_dirs, show_hidden);
+    RemoteFetch::Listed { cwd, entries }
 }
 
 #[cfg(test)]
@@ -272,21 +290,30 @@ mod tests {
             name: "test".to_string(),
             user: "root".to_string(),
             host: "host".to_string(),
+            port: 22,
             auth: AuthConfig::Password {
-                password: "pw".to_string(),
+                password: "pw".into(),
             },
             history: vec![],
             last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
         };
         app.connections.push(connection.clone());
         app.open_connections.push(OpenConnection {
             config: connection,
-            session: ssh2::Session::new().unwrap(),
+            session: crate::ssh::SharedSession::new(ssh2::Session::new().unwrap()),
             connected_at: SystemTime::now(),
+            keepalive: crate::model::KeepaliveStatus::default(),
+            asked: Default::default(),
         });
         app.selected_saved = 0;
-        app.open_remote_picker_at("/home/root".to_string(), false).unwrap();
-        app.poll_remote_fetch();
+        app.open_remote_picker_at("/home/root".to_string(), false)
+            .unwrap();
+        let fetch = app.remote_fetch.take().unwrap().recv().unwrap();
+        app.apply_remote_fetch(fetch);
         let picker = app.remote_picker.as_ref().unwrap();
         assert_eq!(picker.cwd, "/home/user");
     }
@@ -0,0 +1,239 @@
+use std::time::{Duration, Instant};
+
+use ssh2::Session;
+
+use crate::app::{App, ConnectIntent, Reconnect};
+use crate::model::{AskedSecrets, ConnectionConfig, connection_key};
+
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(2);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+const RECONNECT_MAX_ATTEMPTS: u32 = 8;
+
+impl App {
+    pub(super) fn schedule_reconnect(&mut self, config: ConnectionConfig) {
+        let key = connection_key(&config);
+        if self
+            .reconnects
+            .iter()
+            .any(|entry| connection_key(&entry.config) == key)
+        {
+            return;
+        }
+        self.reconnects.push(Reconnect {
+            config,
+            attempt: 0,
+            next_at: Instant::now() + backoff(0),
+            in_flight: false,
+        });
+    }
+
+    pub(super) fn reconnect_needs_user(&self, config: &ConnectionConfig) -> bool {
+        let mut config = config.clone();
+        if let Ok(via) = self.resolve_jump_chain(&config) {
+            config.via = via;
+        }
+        let config = self.with_remembered_answers(&config);
+        config.auth.asks_user() || config.via.iter().any(|hop| hop.auth.asks_user())
+    }
+
+    pub(crate) fn poll_reconnects(&mut self) {
+        if self.is_locked() {
+            return;
+        }
+        let now = Instant::now();
+        let due: Vec<ConnectionConfig> = self
+            .reconnects
+            .iter_mut()
+            .filter(|entry| !entry.in_flight && entry.next_at <= now)
+            .filter(|entry| {
+                !self
+                    .pending_connects
+                    .contains_key(&connection_key(&entry.config))
+            })
+            .map(|entry| {
+                entry.in_flight = true;
+                entry.config.clone()
+            })
+            .collect();
+        for config in due {
+            let key = connection_key(&config);
+            let config = self
+                .connections
+                .iter()
+                .find(|conn| connection_key(conn) == key)
+                .cloned()
+                .unwrap_or(config);
+            self.start_connect(config, ConnectIntent::Reconnect);
+        }
+    }
+
+    pub(super) fn finish_reconnect(
+        &mut self,
+        config: ConnectionConfig,
+        session: Session,
+        answers: AskedSecrets,
+    ) {
+        let key = connection_key(&config);
+        self.reconnects
+            .retain(|entry| connection_key(&entry.config) != key);
+        let label = config.label();
+        match self.open_session(config, session, answers) {
+            Ok(()) => self.set_status(format!("Reconnected to {label}")),
+            Err(err) => self.set_status(format!("Reconnect failed: {err}")),
+        }
+    }
+
+    pub(super) fn retry_reconnect(&mut self, config: &ConnectionConfig, err: &anyhow::Error) {
+        let key = connection_key(config);
+        let Some(index) = self
+            .reconnects
+            .iter()
+            .position(|entry| connection_key(&entry.config) == key)
+        else {
+            return;
+        };
+        let entry = &mut self.reconnects[index];
+        entry.in_flight = false;
+        entry.attempt += 1;
+        let label = config.label();
+        if entry.attempt >= RECONNECT_MAX_ATTEMPTS {
+            self.reconnects.remove(index);
+            self.last_error.insert(
+                key,
+                format!("Reconnect gave up after {RECONNECT_MAX_ATTEMPTS} attempts: {err}"),
+            );
+            self.set_status(format!("Gave up reconnecting to {label}"));
+            return;
+        }
+        let delay = backoff(entry.attempt);
+        entry.next_at = Instant::now() + delay;
+        self.set_status(format!(
+            "Reconnect to {label} failed, retrying in {}s",
+            delay.as_secs()
+        ));
+    }
+
+    pub(super) fn retry_reconnect_now(&mut self, key: &str) {
+        if let Some(entry) = self
+            .reconnects
+            .iter_mut()
+            .find(|entry| connection_key(&entry.config) == key)
+        {
+            entry.in_flight = false;
+            entry.next_at = Instant::now();
+        }
+    }
+
+    pub(crate) fn cancel_reconnect(&mut self, key: &str) -> bool {
+        let before = self.reconnects.len();
+        self.reconnects
+            .retain(|entry| connection_key(&entry.config) != key);
+        before != self.reconnects.len()
+    }
+
+    pub(crate) fn reconnect_label(&self, key: &str) -> Option<String> {
+        let entry = self
+            .reconnects
+            .iter()
+            .find(|entry| connection_key(&entry.config) == key)?;
+        if entry.in_flight {
+            return Some("reconnecting…".to_string());
+        }
+        let remaining = entry.next_at.saturating_duration_since(Instant::now());
+        Some(format!("retry in {}s", remaining.as_secs() + 1))
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AuthConfig, KeepaliveConfig};
+
+    fn config() -> ConnectionConfig {
+        ConnectionConfig {
+            name: "box".to_string(),
+            user: "u".to_string(),
+            host: "box.example".to_string(),
+            port: 22,
+            auth: AuthConfig::Agent { identity: None },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_cap() {
+        assert_eq!(backoff(0), Duration::from_secs(2));
+        assert_eq!(backoff(1), Duration::from_secs(4));
+        assert_eq!(backoff(4), Duration::from_secs(32));
+        assert_eq!(backoff(5), Duration::from_secs(60));
+        assert_eq!(backoff(40), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn failed_reconnects_back_off_then_give_up() {
+        let mut app = App::for_test();
+        let config = config();
+        let key = connection_key(&config);
+        app.schedule_reconnect(config.clone());
+        app.schedule_reconnect(config.clone());
+        assert_eq!(app.reconnects.len(), 1);
+        assert!(app.reconnect_label(&key).unwrap().starts_with("retry in"));
+
+        let err = anyhow::anyhow!("refused");
+        for attempt in 1..RECONNECT_MAX_ATTEMPTS {
+            app.reconnects[0].in_flight = true;
+            app.retry_reconnect(&config, &err);
+            assert_eq!(app.reconnects[0].attempt, attempt);
+            assert!(!app.reconnects[0].in_flight);
+        }
+        app.retry_reconnect(&config, &err);
+        assert!(app.reconnects.is_empty());
+        assert!(app.last_error[&key].contains("gave up"));
+    }
+
+    #[test]
+    fn reconnects_do_not_wait_for_other_connects() {
+        let mut app = App::for_test();
+        let dropped = ConnectionConfig {
+            host: "127.0.0.1".to_string(),
+            port: 1,
+            ..config()
+        };
+        let other = ConnectionConfig {
+            name: "other".to_string(),
+            port: 2,
+            ..dropped.clone()
+        };
+        app.start_connect(other.clone(), ConnectIntent::Open(None));
+        app.schedule_reconnect(dropped.clone());
+        app.reconnects[0].next_at = Instant::now();
+        app.poll_reconnects();
+        assert!(app.reconnects[0].in_flight);
+        assert_eq!(app.pending_connects.len(), 2);
+        app.abort_pending_connect(&connection_key(&other));
+        app.abort_pending_connect(&connection_key(&dropped));
+        assert!(app.reconnects.is_empty());
+    }
+
+    #[test]
+    fn cancel_drops_waiting_reconnect() {
+        let mut app = App::for_test();
+        let config = config();
+        let key = connection_key(&config);
+        app.schedule_reconnect(config);
+        assert!(app.cancel_reconnect(&key));
+        assert!(!app.cancel_reconnect(&key));
+        assert!(app.reconnect_label(&key).is_none());
+    }
+}
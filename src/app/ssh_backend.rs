@@ -2,8 +2,8 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 
-use crate::model::{ConnectionConfig, OpenConnection, RemoteEntry};
-use crate::ssh::connect_ssh;
+use crate::model::RemoteEntry;
+use crate::ssh::SharedSession;
 
 #[cfg(test)]
 use std::collections::HashMap;
@@ -11,97 +11,45 @@ use std::collections::HashMap;
 pub(crate) trait SshBackend: Send + Sync {
     fn list_remote_dir(
         &self,
-        open_connections: Option<&[OpenConnection]>,
-        conn: &ConnectionConfig,
+        session: &SharedSession,
         cwd: &str,
         only_dirs: bool,
         show_hidden: bool,
     ) -> Result<Vec<RemoteEntry>>;
-    fn remote_home_dir(
-        &self,
-        open_connections: Option<&[OpenConnection]>,
-        conn: &ConnectionConfig,
-    ) -> Result<Option<String>>;
-    fn remote_has_subdirectories(
-        &self,
-        open_connections: Option<&[OpenConnection]>,
-        conn: &ConnectionConfig,
-        path: &str,
-    ) -> Result<bool>;
-    fn remote_size(&self, conn: &ConnectionConfig, path: &str, is_dir: bool) -> Result<u64>;
+    fn remote_home_dir(&self, session: &SharedSession) -> Result<Option<String>>;
+    fn remote_has_subdirectories(&self, session: &SharedSession, path: &str) -> Result<bool>;
+    fn remote_size(&self, session: &SharedSession, path: &str, is_dir: bool) -> Result<u64>;
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct RealSshBackend;
 
-impl RealSshBackend {
-    fn find_session<'a>(
-        &self,
-        open_connections: Option<&'a [OpenConnection]>,
-        conn: &ConnectionConfig,
-    ) -> Option<&'a ssh2::Session> {
-        let open_connections = open_connections?;
-        open_connections
-            .iter()
-            .find(|candidate| crate::model::same_identity(&candidate.config, conn))
-            .map(|conn| &conn.session)
-    }
-}
-
 impl SshBackend for RealSshBackend {
     fn list_remote_dir(
         &self,
-        open_connections: Option<&[OpenConnection]>,
-        conn: &ConnectionConfig,
+        session: &SharedSession,
         cwd: &str,
         only_dirs: bool,
         show_hidden: bool,
     ) -> Result<Vec<RemoteEntry>> {
-        let session = if let Some(session) = self.find_session(open_connections, conn) {
-            session
-        } else {
-            let session = connect_ssh(conn)?;
-            return list_remote_dir_with_session(&session, cwd, only_dirs, show_hidden);
-        };
-        list_remote_dir_with_session(session, cwd, only_dirs, show_hidden)
+        list_remote_dir_with_session(&session.lock(), cwd, only_dirs, show_hidden)
     }
 
-    fn remote_home_dir(
-        &self,
-        open_connections: Option<&[OpenConnection]>,
-        conn: &ConnectionConfig,
-    ) -> Result<Option<String>> {
-        if let Some(session) = self.find_session(open_connections, conn) {
-            let home = crate::ssh::remote_home_dir(session)?;
-            return Ok(if home.trim().is_empty() {
-                None
-            } else {
-                Some(home)
-            });
-        }
-        let session = connect_ssh(conn)?;
-        let home = crate::ssh::remote_home_dir(&session)?;
-        Ok(if home.trim().is_empty() { None } else { Some(home) })
+    fn remote_home_dir(&self, session: &SharedSession) -> Result<Option<String>> {
+        let home = crate::ssh::remote_home_dir(&session.lock())?;
+        Ok(if home.trim().is_empty() {
+            None
+        } else {
+            Some(home)
+        })
     }
 
-    fn remote_has_subdirectories(
-        &self,
-        open_connections: Option<&[OpenConnection]>,
-        conn: &ConnectionConfig,
-        path: &str,
-    ) -> Result<bool> {
-        let session = if let Some(session) = self.find_session(open_connections, conn) {
-            session
-        } else {
-            let session = connect_ssh(conn)?;
-            return crate::ssh::remote_has_subdirectories(&session, path);
-        };
-        crate::ssh::remote_has_subdirectories(session, path)
+    fn remote_has_subdirectories(&self, session: &SharedSession, path: &str) -> Result<bool> {
+        crate::ssh::remote_has_subdirectories(&session.lock(), path)
     }
 
-    fn remote_size(&self, conn: &ConnectionConfig, path: &str, is_dir: bool) -> Result<u64> {
-        let session = connect_ssh(conn)?;
-        crate::ssh::remote_size(&session, path, is_dir)
+    fn remote_size(&self, session: &SharedSession, path: &str, is_dir: bool) -> Result<u64> {
+        crate::ssh::remote_size(session, path, is_dir)
     }
 }
 
@@ -195,8 +143,7 @@ impl MockSshBackend {
 impl SshBackend for MockSshBackend {
     fn list_remote_dir(
         &self,
-        _open_connections: Option<&[OpenConnection]>,
-        _conn: &ConnectionConfig,
+        _session: &SharedSession,
         cwd: &str,
         _only_dirs: bool,
         _show_hidden: bool,
@@ -213,24 +160,15 @@ impl SshBackend for MockSshBackend {
             .unwrap_or_default())
     }
 
-    fn remote_home_dir(
-        &self,
-        _open_connections: Option<&[OpenConnection]>,
-        _conn: &ConnectionConfig,
-    ) -> Result<Option<String>> {
+    fn remote_home_dir(&self, _session: &SharedSession) -> Result<Option<String>> {
         Ok(self.home.lock().unwrap().clone())
     }
 
-    fn remote_has_subdirectories(
-        &self,
-        _open_connections: Option<&[OpenConnection]>,
-        _conn: &ConnectionConfig,
-        _path: &str,
-    ) -> Result<bool> {
+    fn remote_has_subdirectories(&self, _session: &SharedSession, _path: &str) -> Result<bool> {
         Ok(*self.has_subdirs.lock().unwrap())
     }
 
-    fn remote_size(&self, _conn: &ConnectionConfig, _path: &str, _is_dir: bool) -> Result<u64> {
+    fn remote_size(&self, _session: &SharedSession, _path: &str, _is_dir: bool) -> Result<u64> {
         if let Some(err) = self.size_error.lock().unwrap().as_ref() {
             return Err(anyhow::anyhow!(err.clone()));
         }
@@ -0,0 +1,126 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::App;
+use crate::model::SshExportState;
+use crate::ssh::expand_tilde;
+use crate::ssh_config::{default_export_path, export_ssh_config, ssh_config_path};
+
+impl App {
+    pub(crate) fn open_ssh_export(&mut self) {
+        if self.connections.is_empty() {
+            self.set_status("No saved connections");
+            return;
+        }
+        let path = default_export_path()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.ssh_export = Some(SshExportState { path });
+        self.set_status("Choose where to write the ssh config snippet");
+    }
+
+    pub(super) fn handle_ssh_export_key(&mut self, key: KeyEvent) {
+        let Some(export) = &mut self.ssh_export else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.ssh_export = None;
+                self.set_status("Export cancelled");
+            }
+            KeyCode::Enter => self.run_ssh_export(),
+            KeyCode::Backspace => {
+                export.path.pop();
+            }
+            KeyCode::Char(ch) => export.path.push(ch),
+            _ => {}
+        }
+    }
+
+    fn run_ssh_export(&mut self) {
+        let Some(export) = &self.ssh_export else {
+            return;
+        };
+        let raw = export.path.trim();
+        if raw.is_empty() {
+            self.set_status("Export path is empty");
+            return;
+        }
+        let path = expand_tilde(raw);
+        if let Err(err) = export_ssh_config(&path, &self.connections) {
+            self.set_status(format!("Export failed: {err:#}"));
+            return;
+        }
+        self.ssh_export = None;
+        let mut status = format!(
+            "Exported {} connection(s) to {}",
+            self.connections.len(),
+            path.display()
+        );
+        if !is_included_by_ssh_config(&path) {
+            status.push_str(" (add an Include for it to ~/.ssh/config)");
+        }
+        self.set_status(status);
+    }
+}
+
+fn is_included_by_ssh_config(path: &std::path::Path) -> bool {
+    let Some(config) = ssh_config_path() else {
+        return false;
+    };
+    if path == config {
+        return true;
+    }
+    let Ok(content) = std::fs::read_to_string(&config) else {
+        return false;
+    };
+    let dir_name = path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    content.lines().any(|line| {
+        let line = line.trim();
+        line.get(..7)
+            .is_some_and(|word| word.eq_ignore_ascii_case("include"))
+            && (line.contains(dir_name) || line.contains(&*path.to_string_lossy()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    #[test]
+    fn export_writes_managed_block_to_typed_path() {
+        let mut app = App::for_test();
+        app.connections.push(crate::model::ConnectionConfig {
+            name: "web".to_string(),
+            user: "dev".to_string(),
+            host: "web.example".to_string(),
+            port: 22,
+            auth: crate::model::AuthConfig::Password {
+                password: "secret".into(),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        });
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("ssh-client-export-{nanos}/ss-ssh"));
+        app.open_ssh_export();
+        app.ssh_export.as_mut().unwrap().path = path.to_string_lossy().into_owned();
+        app.handle_ssh_export_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.ssh_export.is_none());
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("Host web\n"));
+        assert!(!content.contains("secret"));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::App;
+use crate::model::{
+    AuthConfig, ConnectionConfig, DEFAULT_SSH_PORT, ImportCandidate, ImportSource, KeepaliveConfig,
+    SshImportState, connection_key, jump_reference, parse_host_port, same_identity,
+};
+use crate::ssh_config::{SshHostEntry, load_ssh_config, ssh_config_path};
+
+impl App {
+    pub(crate) fn open_ssh_import(&mut self) {
+        let Some(source) = ssh_config_path() else {
+            self.set_status("Home directory not found");
+            return;
+        };
+        if !source.exists() {
+            self.set_status(format!("No ssh config found at {}", source.display()));
+            return;
+        }
+        let entries = match load_ssh_config(&source) {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.set_status(format!("Failed to read ssh config: {err}"));
+                return;
+            }
+        };
+        let candidates = self.import_candidates(entries);
+        if candidates.is_empty() {
+            self.set_status("No hosts found in ssh config");
+            return;
+        }
+        self.set_status(format!("Select hosts to import from {}", source.display()));
+        self.ssh_import = Some(SshImportState {
+            source,
+            origin: ImportSource::SshConfig,
+            candidates,
+            selected: 0,
+        });
+    }
+
+    pub(super) fn handle_ssh_import_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let Some(import) = &mut self.ssh_import else {
+            return Ok(false);
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.ssh_import = None;
+                self.set_status("Import cancelled");
+            }
+            KeyCode::Up => import.selected = import.selected.saturating_sub(1),
+            KeyCode::Down if import.selected + 1 < import.candidates.len() => {
+                import.selected += 1;
+            }
+            KeyCode::Char(' ') => {
+                if let Some(candidate) = import.candidates.get_mut(import.selected) {
+                    candidate.checked = !candidate.checked;
+                }
+            }
+            KeyCode::Char('a') => {
+                let check = import
+                    .candidates
+                    .iter()
+                    .any(|candidate| !candidate.checked && !candidate.duplicate);
+                for candidate in &mut import.candidates {
+                    candidate.checked = check && !candidate.duplicate;
+                }
+            }
+            KeyCode::Enter => self.import_checked_connections(),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn import_candidates(&self, entries: Vec<SshHostEntry>) -> Vec<ImportCandidate> {
+        let default_user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "root".to_string());
+        let mut configs: Vec<ConnectionConfig> = entries
+            .iter()
+            .map(|entry| ConnectionConfig {
+                name: entry.alias.clone(),
+                user: entry.user.clone().unwrap_or_else(|| default_user.clone()),
+                host: entry.host_name.clone(),
+                port: entry.port.unwrap_or(DEFAULT_SSH_PORT),
+                auth: match &entry.identity_file {
+                    Some(path) => AuthConfig::PrivateKey {
+                        path: path.clone(),
+                        password: None,
+                    },
+                    None => AuthConfig::Agent { identity: None },
+                },
+                history: vec![],
+                last_remote_dir: None,
+                jump_host: None,
+                via: vec![],
+                keepalive: KeepaliveConfig::default(),
+                forwards: vec![],
+            })
+            .collect();
+
+        for (index, entry) in entries.iter().enumerate() {
+            let Some(proxy_jump) = &entry.proxy_jump else {
+                continue;
+            };
+            let hops: Vec<usize> = proxy_jump
+                .split(',')
+                .filter_map(|spec| self.jump_hop_index(spec.trim(), &mut configs, &default_user))
+                .filter(|hop| *hop != index)
+                .collect();
+            for pair in hops.windows(2) {
+                if configs[pair[1]].jump_host.is_none() && pair[0] != pair[1] {
+                    configs[pair[1]].jump_host = Some(jump_reference(&configs[pair[0]]));
+                }
+            }
+            if let Some(last) = hops.last() {
+                configs[index].jump_host = Some(jump_reference(&configs[*last]));
+            }
+        }
+
+        configs
+            .into_iter()
+            .map(|config| {
+                let duplicate = self
+                    .connections
+                    .iter()
+                    .any(|existing| same_identity(existing, &config));
+                ImportCandidate {
+                    config,
+                    checked: !duplicate,
+                    duplicate,
+                }
+            })
+            .collect()
+    }
+
+    fn jump_hop_index(
+        &self,
+        spec: &str,
+        configs: &mut Vec<ConnectionConfig>,
+        default_user: &str,
+    ) -> Option<usize> {
+        if spec.is_empty() {
+            return None;
+        }
+        if let Some(index) = configs.iter().position(|config| config.name == spec) {
+            return Some(index);
+        }
+        if let Some(saved) = self.connections.iter().find(|conn| conn.name == spec) {
+            configs.push(saved.clone());
+            return Some(configs.len() - 1);
+        }
+        let (user, address) = match spec.rsplit_once('@') {
+            Some((user, address)) => (user.to_string(), address),
+            None => (default_user.to_string(), spec),
+        };
+        let (host, port) = parse_host_port(address).ok()?;
+        let port = port.unwrap_or(DEFAULT_SSH_PORT);
+        if let Some(index) = configs
+            .iter()
+            .position(|config| config.user == user && config.host == host && config.port == port)
+        {
+            return Some(index);
+        }
+        configs.push(ConnectionConfig {
+            name: spec.to_string(),
+            user,
+            host,
+            port,
+            auth: AuthConfig::Agent { identity: None },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        });
+        Some(configs.len() - 1)
+    }
+
+    fn import_checked_connections(&mut self) {
+        let Some(import) = self.ssh_import.take() else {
+            return;
+        };
+        let mut wanted: HashSet<String> = import
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.checked)
+            .map(|candidate| connection_key(&candidate.config))
+            .collect();
+        loop {
+            let missing: Vec<String> = import
+                .candidates
+                .iter()
+                .filter(|candidate| wanted.contains(&connection_key(&candidate.config)))
+                .filter_map(|candidate| candidate.config.jump_host.as_deref())
+                .filter_map(|reference| {
+                    import
+                        .candidates
+                        .iter()
+                        .find(|hop| jump_reference(&hop.config) == reference)
+                })
+                .map(|hop| connection_key(&hop.config))
+                .filter(|key| !wanted.contains(key))
+                .collect();
+            if missing.is_empty() {
+                break;
+            }
+            wanted.extend(missing);
+        }
+        let mut imported = 0;
+        for candidate in import.candidates {
+            let replace = import.origin == ImportSource::Bundle && candidate.checked;
+            if (candidate.duplicate && !replace)
+                || !wanted.contains(&connection_key(&candidate.config))
+            {
+                continue;
+            }
+            self.upsert_connection(candidate.config);
+            imported += 1;
+        }
+        if imported == 0 {
+            self.set_status("Nothing imported");
+            return;
+        }
+        let direct = self.clear_dangling_jump_hosts();
+        if let Err(err) = self.save_store() {
+            self.set_status(format!("Failed to save imported connections: {err}"));
+            return;
+        }
+        self.sort_connections_by_recent(None);
+        let mut status = format!(
+            "Imported {imported} connection(s) from {}",
+            import.source.display()
+        );
+        if !direct.is_empty() {
+            status.push_str(&format!(
+                "; jump host not found for {}, connecting directly",
+                direct.join(", ")
+            ));
+        }
+        self.set_status(status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(alias: &str, proxy_jump: Option<&str>) -> SshHostEntry {
+        SshHostEntry {
+            alias: alias.to_string(),
+            host_name: format!("{alias}.example"),
+            user: Some("dev".to_string()),
+            port: None,
+            identity_file: None,
+            proxy_jump: proxy_jump.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn import_candidates_link_jump_hosts_and_flag_duplicates() {
+        let mut app = App::for_test();
+        let entries = vec![
+            entry("bastion", None),
+            entry("app", Some("bastion")),
+            entry("db", Some("ops@gate.example:2200")),
+        ];
+        let first = app.import_candidates(entries.clone());
+        app.connections.push(first[0].config.clone());
+        let candidates = app.import_candidates(entries);
+
+        assert_eq!(candidates.len(), 4);
+        assert!(candidates[0].duplicate && !candidates[0].checked);
+        assert_eq!(
+            candidates[1].config.jump_host,
+            Some(jump_reference(&candidates[0].config))
+        );
+        let gate = &candidates[3].config;
+        assert_eq!(
+            (gate.user.as_str(), gate.host.as_str(), gate.port),
+            ("ops", "gate.example", 2200)
+        );
+        assert_eq!(candidates[2].config.jump_host, Some(jump_reference(gate)));
+    }
+
+    #[test]
+    fn import_pulls_in_required_jump_hosts() {
+        let mut app = App::for_test();
+        let mut candidates =
+            app.import_candidates(vec![entry("bastion", None), entry("app", Some("bastion"))]);
+        candidates[0].checked = false;
+        app.ssh_import = Some(SshImportState {
+            source: std::env::temp_dir(),
+            origin: ImportSource::SshConfig,
+            candidates,
+            selected: 0,
+        });
+        app.import_checked_connections();
+        assert!(app.ssh_import.is_none());
+        assert_eq!(app.connections.len(), 2);
+        let target = app
+            .connections
+            .iter()
+            .find(|conn| conn.name == "app")
+            .unwrap();
+        assert!(app.resolve_jump_chain(target).is_ok());
+    }
+
+    #[test]
+    fn import_drops_jump_hosts_it_cannot_find() {
+        let mut app = App::for_test();
+        let mut candidates = app.import_candidates(vec![entry("app", None)]);
+        candidates[0].config.jump_host = Some("ops@gone.example:22".to_string());
+        app.ssh_import = Some(SshImportState {
+            source: std::env::temp_dir(),
+            origin: ImportSource::Bundle,
+            candidates,
+            selected: 0,
+        });
+        app.import_checked_connections();
+        assert_eq!(app.connections[0].jump_host, None);
+        assert!(app.status.contains("jump host not found for app"));
+    }
+}
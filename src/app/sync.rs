@@ -0,0 +1,174 @@
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::app::App;
+use crate::merge::merge_connections;
+use crate::model::{connection_key, same_identity};
+use crate::storage::{decrypt_connection, load_store, store_fingerprint, unseal_store};
+
+impl App {
+    pub(super) fn merge_external_changes(&mut self) -> Result<()> {
+        let on_disk = store_fingerprint(&self.config_path);
+        if on_disk.is_none() || on_disk == self.store_fingerprint {
+            return Ok(());
+        }
+        let mut store = load_store(&self.config_path)?;
+        if store.master.salt_b64 != self.master.salt_b64 {
+            anyhow::bail!(
+                "Master password was changed by another ss-ssh instance; restart to unlock with it"
+            );
+        }
+        self.sealed_vault = store.sealed.is_some();
+        unseal_store(&mut store, &self.master_key)?;
+        let theirs = store
+            .connections
+            .into_iter()
+            .map(|conn| decrypt_connection(conn, &self.master_key))
+            .collect::<Result<Vec<_>>>()?;
+        let selected = self
+            .connections
+            .get(self.selected_saved)
+            .map(connection_key);
+        let merge = merge_connections(&self.store_base, &self.connections, &theirs);
+        self.connections = merge.connections;
+        self.store_base = theirs;
+        self.store_fingerprint = on_disk;
+        self.sort_connections_by_recent(selected);
+        if merge.conflicts.is_empty() {
+            self.log_line("Merged changes saved by another ss-ssh instance");
+        } else {
+            self.set_status("Connections were also edited in another ss-ssh instance");
+            self.save_conflicts.extend(merge.conflicts);
+        }
+        Ok(())
+    }
+
+    pub(super) fn mark_store_synced(&mut self) {
+        self.store_fingerprint = store_fingerprint(&self.config_path);
+        self.store_base = self.connections.clone();
+    }
+
+    pub(super) fn handle_save_conflict_key(&mut self, key: KeyEvent) {
+        let keep_theirs = match key.code {
+            KeyCode::Char('t') => true,
+            KeyCode::Char('m') | KeyCode::Esc => false,
+            _ => return,
+        };
+        if self.save_conflicts.is_empty() {
+            return;
+        }
+        let conflict = self.save_conflicts.remove(0);
+        if !keep_theirs {
+            self.set_status(format!("Kept your version of {}", conflict.ours.label()));
+            return;
+        }
+        if let Some(existing) = self
+            .connections
+            .iter_mut()
+            .find(|conn| same_identity(conn, &conflict.ours))
+        {
+            *existing = conflict.theirs.clone();
+        }
+        match self.save_store() {
+            Ok(()) => self.set_status(format!(
+                "Took the other instance's version of {}",
+                conflict.theirs.label()
+            )),
+            Err(err) => self.set_status(format!("Failed to save connections: {err}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+    use crate::model::{AuthConfig, ConnectionConfig, KeepaliveConfig};
+
+    fn conn(host: &str) -> ConnectionConfig {
+        ConnectionConfig {
+            name: host.to_string(),
+            user: "u".to_string(),
+            host: host.to_string(),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: "pw".into(),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    fn second_instance(first: &App) -> App {
+        let mut other = App::for_test();
+        other.config_path = first.config_path.clone();
+        other.master = first.master.clone();
+        other.master_key = first.master_key.clone();
+        other.connections = first.connections.clone();
+        other.mark_store_synced();
+        other
+    }
+
+    #[test]
+    fn saving_keeps_connections_added_by_another_instance() {
+        let mut first = App::for_test();
+        first.connections = vec![conn("a")];
+        first.save_store().unwrap();
+        let mut second = second_instance(&first);
+
+        second.connections.push(conn("b"));
+        second.save_store().unwrap();
+        first.connections.push(conn("c"));
+        first.save_store().unwrap();
+
+        let mut hosts: Vec<String> = first.connections.iter().map(|c| c.host.clone()).collect();
+        hosts.sort();
+        assert_eq!(hosts, ["a", "b", "c"]);
+        assert!(first.save_conflicts.is_empty());
+    }
+
+    #[test]
+    fn conflicting_edit_asks_and_can_take_theirs() {
+        let mut first = App::for_test();
+        first.connections = vec![conn("a")];
+        first.save_store().unwrap();
+        let mut second = second_instance(&first);
+
+        second.connections[0].name = "theirs".to_string();
+        second.save_store().unwrap();
+        first.connections[0].name = "mine".to_string();
+        first.save_store().unwrap();
+        assert_eq!(first.save_conflicts.len(), 1);
+        assert_eq!(first.connections[0].name, "mine");
+
+        first.handle_save_conflict_key(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE));
+        assert!(first.save_conflicts.is_empty());
+        assert_eq!(first.connections[0].name, "theirs");
+        let saved = load_store(&first.config_path).unwrap();
+        assert_eq!(saved.connections[0].name, "theirs");
+    }
+
+    #[test]
+    fn merging_reads_and_keeps_a_vault_sealed_elsewhere() {
+        let mut first = App::for_test();
+        first.connections = vec![conn("a")];
+        first.save_store().unwrap();
+        let mut second = second_instance(&first);
+
+        second.sealed_vault = true;
+        second.connections.push(conn("b"));
+        second.save_store().unwrap();
+        first.connections.push(conn("c"));
+        first.save_store().unwrap();
+
+        assert!(first.sealed_vault);
+        assert_eq!(first.connections.len(), 3);
+        let saved = load_store(&first.config_path).unwrap();
+        assert!(saved.sealed.is_some() && saved.connections.is_empty());
+    }
+}
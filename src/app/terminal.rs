@@ -1,18 +1,18 @@
 use std::io::{Read, Write};
 
 use anyhow::Result;
-use crossterm::event::{
-    KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
-};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ssh2::Session;
 
 use crate::app::constants::NOT_CONNECTED_MESSAGE;
-use crate::app::App;
+use crate::app::{App, ConnectIntent};
+use crate::model::ConnectionConfig;
+use crate::ssh::terminal_key_bytes;
 use crate::ui::constants::{HEADER_HEIGHT, TERMINAL_FOOTER_HEIGHT};
-use crate::ssh::{connect_ssh, terminal_key_bytes};
 
 const TERMINAL_SCROLLBACK_LINES: u16 = 2000;
 const TERMINAL_SCROLL_STEP: u16 = 3;
+const LIBSSH2_ERROR_EAGAIN: i32 = -37;
 
 pub(crate) struct SelectionRange {
     pub(crate) start_row: u16,
@@ -22,8 +22,11 @@ pub(crate) struct SelectionRange {
 }
 
 pub(crate) struct TerminalTab {
+    pub(crate) id: u64,
     pub(crate) title: String,
-    pub(crate) _session: Session,
+    pub(crate) config: ConnectionConfig,
+    pub(crate) disconnected: bool,
+    pub(crate) session: Session,
     pub(crate) channel: ssh2::Channel,
     pub(crate) parser: vt100::Parser,
     pub(crate) cols: u16,
@@ -35,27 +38,36 @@ pub(crate) struct TerminalTab {
 }
 
 impl App {
-    pub(crate) fn open_terminal_tab(&mut self, cols: u16, rows: u16) -> Result<()> {
-        let Some(conn) = self.selected_connected_connection() else {
+    pub(crate) fn open_terminal_tab(&mut self, cols: u16, rows: u16) {
+        let Some(conn) = self
+            .selected_connected_connection()
+            .map(|open| open.config.clone())
+        else {
             self.set_status(NOT_CONNECTED_MESSAGE);
-            return Ok(());
+            return;
         };
-        let session = connect_ssh(&conn)?;
-        session.set_blocking(true);
+        self.start_connect(conn, ConnectIntent::Terminal { cols, rows });
+    }
+
+    pub(super) fn open_terminal_with_session(
+        &mut self,
+        conn: &ConnectionConfig,
+        session: Session,
+        cols: u16,
+        rows: u16,
+    ) -> Result<()> {
         let view_cols = cols.max(1);
         let view_rows = rows.max(1);
-        let mut channel = session.channel_session()?;
-        channel.request_pty(
-            "xterm-256color",
-            None,
-            Some((u32::from(view_cols), u32::from(view_rows), 0, 0)),
-        )?;
-        channel.shell()?;
-        session.set_blocking(false);
+        let channel = open_shell(&session, view_cols, view_rows)?;
         let parser = vt100::Parser::new(view_rows, view_cols, TERMINAL_SCROLLBACK_LINES.into());
+        let id = self.next_terminal_id;
+        self.next_terminal_id += 1;
         let tab = TerminalTab {
+            id,
             title: conn.label(),
-            _session: session,
+            config: conn.clone(),
+            disconnected: false,
+            session,
             channel,
             parser,
             cols: view_cols,
@@ -70,6 +82,37 @@ impl App {
         Ok(())
     }
 
+    pub(super) fn reopen_terminal(&mut self, id: u64) {
+        let Some(tab) = self.terminal_tabs.iter().find(|tab| tab.id == id) else {
+            return;
+        };
+        self.start_connect(tab.config.clone(), ConnectIntent::ReopenTerminal { id });
+    }
+
+    pub(super) fn reattach_terminal(&mut self, id: u64, session: Session) -> Result<()> {
+        let Some(tab) = self.terminal_tabs.iter_mut().find(|tab| tab.id == id) else {
+            anyhow::bail!("terminal tab was closed");
+        };
+        let channel = open_shell(&session, tab.cols, tab.rows)?;
+        tab.parser.process(b"\r\n\x1b[0m--- reconnected ---\r\n");
+        tab.parser.screen_mut().set_scrollback(0);
+        tab.session = session;
+        tab.channel = channel;
+        tab.pending_write.clear();
+        tab.disconnected = false;
+        let title = tab.title.clone();
+        self.set_status(format!("Reconnected terminal to {title}"));
+        Ok(())
+    }
+
+    pub(super) fn disconnect_terminal_tabs(&mut self, key: &str) {
+        for tab in &mut self.terminal_tabs {
+            if crate::model::connection_key(&tab.config) == key {
+                tab.disconnected = true;
+            }
+        }
+    }
+
     pub(crate) fn terminal_tabs_open(&self) -> bool {
         !self.terminal_tabs.is_empty()
     }
@@ -123,6 +166,15 @@ impl App {
         if self.active_terminal_tab == 0 {
             return Ok(false);
         }
+        let index = self.active_terminal_tab - 1;
+        if let Some(tab) = self.terminal_tabs.get(index)
+            && tab.disconnected
+        {
+            if matches!(key.code, KeyCode::Char('r') | KeyCode::Char('R')) {
+                self.reopen_terminal(tab.id);
+            }
+            return Ok(true);
+        }
         if let Some(bytes) = terminal_key_bytes(key) {
             if let Some(tab) = self
                 .terminal_tabs
@@ -226,6 +278,15 @@ impl App {
         let mut err_buffer = [0u8; 1024];
         let mut closed = Vec::new();
         for (index, tab) in self.terminal_tabs.iter_mut().enumerate() {
+            if tab.disconnected {
+                continue;
+            }
+            if let Err(err) = tab.session.keepalive_send()
+                && err.code() != ssh2::ErrorCode::Session(LIBSSH2_ERROR_EAGAIN)
+            {
+                tab.disconnected = true;
+                continue;
+            }
             if !tab.pending_write.is_empty() {
                 match tab.channel.write(&tab.pending_write) {
                     Ok(0) => {}
@@ -234,7 +295,8 @@ impl App {
                     }
                     Err(err) => {
                         if err.kind() != std::io::ErrorKind::WouldBlock {
-                            closed.push(index);
+                            tab.disconnected = true;
+                            continue;
                         }
                     }
                 }
@@ -246,13 +308,16 @@ impl App {
                         tab.parser.process(&buffer[..count]);
                     }
                     Err(err) => {
-                        if err.kind() == std::io::ErrorKind::WouldBlock {
-                            break;
+                        if err.kind() != std::io::ErrorKind::WouldBlock {
+                            tab.disconnected = true;
                         }
                         break;
                     }
                 }
             }
+            if tab.disconnected {
+                continue;
+            }
             loop {
                 match tab.channel.stderr().read(&mut err_buffer) {
                     Ok(0) => break,
@@ -414,6 +479,19 @@ impl App {
     }
 }
 
+fn open_shell(session: &Session, cols: u16, rows: u16) -> Result<ssh2::Channel> {
+    session.set_blocking(true);
+    let mut channel = session.channel_session()?;
+    channel.request_pty(
+        "xterm-256color",
+        None,
+        Some((u32::from(cols), u32::from(rows), 0, 0)),
+    )?;
+    channel.shell()?;
+    session.set_blocking(false);
+    Ok(channel)
+}
+
 impl TerminalTab {
     pub(crate) fn selection_range(&self) -> Option<SelectionRange> {
         let (start_row, start_col) = self.selection_start?;
@@ -454,10 +532,6 @@ impl TerminalTab {
             range.end_row,
             range.end_col,
         );
-        if text.is_empty() {
-            None
-        } else {
-            Some(text)
-        }
+        if text.is_empty() { None } else { Some(text) }
     }
 }
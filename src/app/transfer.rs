@@ -3,29 +3,27 @@ use std::sync::mpsc::{self, TryRecvError};
 
 use anyhow::Result;
 
+use crate::app::App;
 use crate::app::constants::{MB_BYTES, TRANSFER_LOG_THRESHOLD_BYTES};
 use crate::app::helpers::compute_local_size;
-use crate::app::App;
-use crate::model::{
-    ConnectionConfig, Notice, TransferDirection, TransferState, TransferStep, TransferUpdate,
-};
-use crate::ssh::connect_ssh;
+use crate::model::{Notice, TransferDirection, TransferState, TransferStep, TransferUpdate};
+use crate::ssh::{ConnectHooks, connect_ssh_with_hooks};
 
 impl App {
-    pub(crate) fn start_upload(&mut self, conn: ConnectionConfig) {
+    pub(crate) fn start_upload(&mut self, label: &str) {
         self.start_transfer(TransferDirection::Upload);
         if let Err(err) = self.open_local_picker(None, false) {
             self.set_status(format!("Failed to open local picker: {err}"));
         }
-        self.set_status(format!("Select source for {}", conn.label()));
+        self.set_status(format!("Select source for {label}"));
     }
 
-    pub(crate) fn start_download(&mut self, conn: ConnectionConfig) {
+    pub(crate) fn start_download(&mut self, label: &str) {
         self.start_transfer(TransferDirection::Download);
         if let Err(err) = self.open_remote_picker() {
             self.set_status(format!("Failed to open remote picker: {err}"));
         }
-        self.set_status(format!("Select remote source for {}", conn.label()));
+        self.set_status(format!("Select remote source for {label}"));
     }
 
     fn start_transfer(&mut self, direction: TransferDirection) {
@@ -61,8 +59,7 @@ impl App {
     }
 
     pub(crate) fn select_target_dir(&mut self, path: String) {
-        let (source_path, source_is_dir, should_calc) = if let Some(transfer) = &mut self.transfer
-        {
+        let (source_path, source_is_dir, should_calc) = if let Some(transfer) = &mut self.transfer {
             transfer.target_dir = Some(path);
             transfer.step = TransferStep::Confirm;
             (
@@ -92,7 +89,10 @@ impl App {
             return;
         };
         if should_calc {
-            let Some(conn) = self.selected_connected_connection() else {
+            let Some(session) = self
+                .selected_connected_connection()
+                .map(|open| open.session.clone())
+            else {
                 self.set_status("Selected connection is not connected");
                 return;
             };
@@ -101,7 +101,7 @@ impl App {
                 let Some(path) = source_remote else {
                     anyhow::bail!("missing remote source");
                 };
-                backend.remote_size(&conn, &path, source_is_dir)
+                backend.remote_size(&session, &path, source_is_dir)
             });
         }
     }
@@ -110,16 +110,23 @@ impl App {
         let Some(transfer) = self.transfer.take() else {
             return;
         };
-        let Some(config) = self.selected_connected_connection() else {
+        let Some(config) = self
+            .selected_connected_connection()
+            .map(|open| open.config.with_answers(&open.asked))
+        else {
             self.set_status("Selected connection is not connected");
             return;
         };
         let (tx, rx) = mpsc::channel();
         let (cancel_tx, cancel_rx) = mpsc::channel();
         let transfer_clone = transfer.clone();
+        let hooks = ConnectHooks {
+            prompts: Some(self.auth_prompt_tx.clone()),
+            ..ConnectHooks::default()
+        };
         std::thread::spawn(move || {
             let result = (|| -> Result<()> {
-                let session = connect_ssh(&config)?;
+                let session = connect_ssh_with_hooks(&config, &hooks)?;
                 match transfer_clone.direction {
                     TransferDirection::Upload => {
                         let Some(source) = transfer_clone.source_path else {
@@ -189,7 +196,8 @@ impl App {
                             log_message = Some(if total == 0 {
                                 format!("Transfer progress: {} B", transfer.progress_bytes)
                             } else {
-                                let percent = (transfer.progress_bytes as f64 / total as f64 * 100.0)
+                                let percent = (transfer.progress_bytes as f64 / total as f64
+                                    * 100.0)
                                     .round() as u64;
                                 let current_mb =
                                     (transfer.progress_bytes as f64 / MB_BYTES).round() as u64;
@@ -293,7 +301,10 @@ mod tests {
         app.select_source_path(PathBuf::from("/tmp/file"), false);
         let transfer = app.transfer.as_ref().unwrap();
         assert!(matches!(transfer.step, TransferStep::PickTarget));
-        assert_eq!(transfer.source_path.as_ref().unwrap().to_string_lossy(), "/tmp/file");
+        assert_eq!(
+            transfer.source_path.as_ref().unwrap().to_string_lossy(),
+            "/tmp/file"
+        );
     }
 
     #[test]
@@ -317,17 +328,24 @@ mod tests {
             name: "test".to_string(),
             user: "user".to_string(),
             host: "host".to_string(),
+            port: 22,
             auth: AuthConfig::Password {
-                password: "pw".to_string(),
+                password: "pw".into(),
             },
             history: vec![],
             last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
         };
         app.connections.push(connection.clone());
         app.open_connections.push(OpenConnection {
             config: connection,
-            session: ssh2::Session::new().unwrap(),
+            session: crate::ssh::SharedSession::new(ssh2::Session::new().unwrap()),
             connected_at: SystemTime::now(),
+            keepalive: crate::model::KeepaliveStatus::default(),
+            asked: Default::default(),
         });
         app.selected_saved = 0;
         app.start_transfer(TransferDirection::Download);
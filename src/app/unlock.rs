@@ -0,0 +1,559 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventState, KeyModifiers};
+
+use crate::app::App;
+use crate::app::constants::{
+    AUTO_LOCK_DEFAULT_MINUTES, AUTO_LOCK_ENV, LOG_NO_LOGS_MESSAGE, UNLOCK_RETRY_BASE,
+    UNLOCK_RETRY_MAX,
+};
+use crate::app::logging::prune_log_file;
+use crate::model::{
+    MasterConfig, MasterPasswordState, Mode, NewConnectionState, StoreFile, UnlockConfirm,
+    UnlockField, UnlockMode, UnlockState, same_identity,
+};
+use crate::profile::{Profile, list_profiles, next_profile};
+use crate::secret::{Secret, SecretKey};
+use crate::storage::{
+    OpenedVault, decrypt_connection, encrypt_connection, init_store, newest_valid_backup,
+    open_store, restore_backup, set_aside_store, store_fingerprint, unlock_master, unlock_store,
+};
+
+pub(crate) fn auto_lock_timeout() -> Option<Duration> {
+    let minutes = std::env::var(AUTO_LOCK_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(AUTO_LOCK_DEFAULT_MINUTES);
+    (minutes > 0).then(|| Duration::from_secs(minutes * 60))
+}
+
+pub(crate) fn retry_delay(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    UNLOCK_RETRY_BASE
+        .saturating_mul(1 << exponent)
+        .min(UNLOCK_RETRY_MAX)
+}
+
+impl App {
+    pub(crate) fn is_locked(&self) -> bool {
+        self.unlock.is_some()
+    }
+
+    pub(crate) fn note_activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub(super) fn begin_startup(&mut self, opened: Result<Option<StoreFile>>) {
+        match opened {
+            Ok(None) => self.unlock = Some(UnlockState::new(UnlockMode::Setup)),
+            Ok(Some(store)) => {
+                self.store_fingerprint = store_fingerprint(&self.config_path);
+                self.master = store.master.clone();
+                self.startup_store = Some(store);
+                self.unlock = Some(UnlockState::new(UnlockMode::Startup));
+            }
+            Err(err) => {
+                let mut state = UnlockState::new(UnlockMode::Startup);
+                state.error = Some(format!("Could not read the vault: {err:#}"));
+                self.startup_store = None;
+                self.unlock = Some(state);
+            }
+        }
+    }
+
+    pub(crate) fn poll_idle_lock(&mut self) {
+        if let Some(timeout) = self.auto_lock
+            && !self.is_locked()
+            && self.last_activity.elapsed() >= timeout
+            && let Err(err) = self.lock()
+        {
+            self.set_status(format!("Failed to lock: {err}"));
+        }
+    }
+
+    pub(crate) fn lock(&mut self) -> Result<()> {
+        self.locked_vault = self
+            .connections
+            .iter()
+            .map(|conn| encrypt_connection(conn, &self.master_key))
+            .collect::<Result<Vec<_>>>()?;
+        for conn in &mut self.connections {
+            conn.auth.wipe_secrets();
+        }
+        for open in &mut self.open_connections {
+            open.config.auth.wipe_secrets();
+            open.asked.clear();
+        }
+        for reconnect in &mut self.reconnects {
+            reconnect.config.auth.wipe_secrets();
+        }
+        for pending in self.pending_connects.values_mut() {
+            pending.config.auth.wipe_secrets();
+        }
+        self.master_key = SecretKey::default();
+        self.new_connection = NewConnectionState::default();
+        self.master_change = MasterPasswordState::default();
+        self.bundle_export = None;
+        self.bundle_import = None;
+        self.ssh_import = None;
+        self.edit_key = None;
+        if matches!(self.mode, Mode::NewConnection | Mode::ChangeMasterPassword) {
+            self.mode = Mode::Normal;
+        }
+        self.unlock = Some(UnlockState::new(UnlockMode::Idle));
+        self.log_line("Locked after inactivity");
+        Ok(())
+    }
+
+    pub(super) fn handle_unlock_key(&mut self, key: KeyEvent) {
+        let Some(state) = self.unlock.as_mut() else {
+            return;
+        };
+        if state.pending_confirm.is_some() {
+            match key.code {
+                KeyCode::Enter => self.run_unlock_confirm(),
+                KeyCode::Esc => state.pending_confirm = None,
+                _ => {}
+            }
+            return;
+        }
+        let setup = state.mode == UnlockMode::Setup;
+        match key.code {
+            KeyCode::Enter if setup && state.active_field == UnlockField::Password => {
+                state.active_field = UnlockField::Confirm;
+            }
+            KeyCode::Enter => self.submit_unlock(),
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down if setup => {
+                state.active_field = match state.active_field {
+                    UnlockField::Password => UnlockField::Confirm,
+                    UnlockField::Confirm => UnlockField::Password,
+                };
+            }
+            KeyCode::F(5) if state.mode == UnlockMode::Startup => {
+                match newest_valid_backup(&self.config_path) {
+                    Some((backup, _)) => {
+                        state.pending_confirm = Some(UnlockConfirm::RestoreBackup(backup));
+                    }
+                    None => state.error = Some("No readable backup found".to_string()),
+                }
+            }
+            KeyCode::F(4) if state.mode == UnlockMode::Startup => {
+                state.pending_confirm = Some(UnlockConfirm::StartOver);
+            }
+            KeyCode::F(2) if state.mode != UnlockMode::Idle => self.switch_profile(),
+            KeyCode::Esc => {
+                state.password.clear();
+                state.confirm.clear();
+                state.active_field = UnlockField::Password;
+            }
+            KeyCode::Backspace => {
+                active_input(state).pop();
+            }
+            KeyCode::Char(ch) => {
+                if ch.is_alphabetic() {
+                    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+                    state.caps_lock =
+                        key.state.contains(KeyEventState::CAPS_LOCK) || ch.is_uppercase() != shift;
+                }
+                active_input(state).push(ch);
+            }
+            _ => {}
+        }
+    }
+
+    fn submit_unlock(&mut self) {
+        let Some(state) = self.unlock.as_mut() else {
+            return;
+        };
+        if let Some(retry_at) = state.retry_at {
+            let wait = retry_at.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                state.error = Some(format!("Try again in {}s", wait.as_secs() + 1));
+                return;
+            }
+        }
+        let password = std::mem::take(&mut state.password);
+        let confirm = std::mem::take(&mut state.confirm);
+        let result = match state.mode {
+            UnlockMode::Setup => self.finish_setup(password.expose(), confirm.expose()),
+            UnlockMode::Startup => self.finish_startup(password.expose()),
+            UnlockMode::Idle => self.finish_idle_unlock(password.expose()),
+        };
+        let Some(state) = self.unlock.as_mut() else {
+            return;
+        };
+        match result {
+            Ok(true) => {}
+            Ok(false) => {
+                state.failed_attempts += 1;
+                state.retry_at = Some(Instant::now() + retry_delay(state.failed_attempts));
+                state.error = Some("Invalid master password".to_string());
+            }
+            Err(err) => state.error = Some(format!("{err:#}")),
+        }
+    }
+
+    fn finish_setup(&mut self, password: &str, confirm: &str) -> Result<bool> {
+        if password.is_empty() {
+            anyhow::bail!("Master password cannot be empty");
+        }
+        if password != confirm {
+            if let Some(state) = self.unlock.as_mut() {
+                state.active_field = UnlockField::Password;
+            }
+            anyhow::bail!("Passwords do not match");
+        }
+        let vault = init_store(&self.config_path, password)?;
+        self.store_fingerprint = store_fingerprint(&self.config_path);
+        self.open_vault(vault);
+        self.set_status("Vault created");
+        Ok(true)
+    }
+
+    fn finish_startup(&mut self, password: &str) -> Result<bool> {
+        let Some(store) = self.startup_store.as_ref() else {
+            anyhow::bail!("The vault could not be read; press F5 to restore a backup");
+        };
+        let vault = unlock_store(store, password)
+            .map_err(|err| err.context("press F5 to restore a backup"))?;
+        let Some(vault) = vault else {
+            return Ok(false);
+        };
+        self.startup_store = None;
+        let kdf_outdated = vault.kdf_outdated;
+        self.open_vault(vault);
+        if kdf_outdated && let Err(err) = self.rekey_store(password) {
+            self.set_status(format!("Failed to upgrade the vault key: {err}"));
+        }
+        Ok(true)
+    }
+
+    fn open_vault(&mut self, vault: OpenedVault) {
+        self.master = vault.master;
+        self.master_key = vault.master_key;
+        self.store_base = vault.connections.clone();
+        self.connections = vault.connections;
+        self.last_local_dir = vault.last_local_dir;
+        self.sealed_vault = vault.sealed;
+        self.unlock = None;
+        self.note_activity();
+        self.sort_connections_by_recent(None);
+    }
+
+    fn finish_idle_unlock(&mut self, password: &str) -> Result<bool> {
+        let Some(key) = unlock_master(&self.master, password)? else {
+            return Ok(false);
+        };
+        let vault = std::mem::take(&mut self.locked_vault)
+            .into_iter()
+            .map(|conn| decrypt_connection(conn, &key))
+            .collect::<Result<Vec<_>>>()?;
+        let configs = self
+            .connections
+            .iter_mut()
+            .chain(
+                self.open_connections
+                    .iter_mut()
+                    .map(|open| &mut open.config),
+            )
+            .chain(self.reconnects.iter_mut().map(|entry| &mut entry.config))
+            .chain(
+                self.pending_connects
+                    .values_mut()
+                    .map(|pending| &mut pending.config),
+            );
+        for config in configs {
+            if let Some(original) = vault.iter().find(|conn| same_identity(conn, config)) {
+                config.auth = original.auth.clone();
+            }
+        }
+        self.master_key = key;
+        self.unlock = None;
+        self.note_activity();
+        self.set_status("Unlocked");
+        if std::mem::take(&mut self.save_deferred)
+            && let Err(err) = self.save_store()
+        {
+            self.set_status(format!("Failed to save connections: {err}"));
+        }
+        Ok(true)
+    }
+
+    fn switch_profile(&mut self) {
+        let mut profiles = list_profiles();
+        if !profiles.contains(&self.profile) {
+            profiles.push(self.profile.clone());
+        }
+        let result = match next_profile(&profiles, &self.profile) {
+            Some(next) => next
+                .config_path()
+                .and_then(|config_path| Ok((config_path, next.log_path()?)))
+                .map(|(config_path, log_path)| self.open_profile(next, config_path, log_path)),
+            None => Err(anyhow::anyhow!(
+                "No other profiles; start with --profile NAME to create one"
+            )),
+        };
+        if let Err(err) = result
+            && let Some(state) = self.unlock.as_mut()
+        {
+            state.error = Some(format!("{err:#}"));
+        }
+    }
+
+    fn open_profile(&mut self, profile: Profile, config_path: PathBuf, log_path: PathBuf) {
+        let (failed_attempts, retry_at) = self
+            .unlock
+            .as_ref()
+            .map(|state| (state.failed_attempts, state.retry_at))
+            .unwrap_or_default();
+        prune_log_file(&log_path);
+        self.profile = profile;
+        self.config_path = config_path;
+        self.log_path = log_path;
+        self.log_lines.clear();
+        self.last_log = LOG_NO_LOGS_MESSAGE.to_string();
+        self.master = MasterConfig::default();
+        self.startup_store = None;
+        self.begin_startup(open_store(&self.config_path));
+        if let Some(state) = self.unlock.as_mut() {
+            state.failed_attempts = failed_attempts;
+            state.retry_at = retry_at;
+        }
+    }
+
+    fn run_unlock_confirm(&mut self) {
+        let Some(state) = self.unlock.as_mut() else {
+            return;
+        };
+        let Some(action) = state.pending_confirm.take() else {
+            return;
+        };
+        let result = match action {
+            UnlockConfirm::RestoreBackup(backup) => {
+                restore_backup(&self.config_path, &backup).map(|store| {
+                    self.log_line(&format!("Restored vault from {}", backup.display()));
+                    self.begin_startup(Ok(Some(store)));
+                })
+            }
+            UnlockConfirm::StartOver => set_aside_store(&self.config_path).map(|kept| {
+                self.log_line(&format!("Old vault kept as {}", kept.display()));
+                self.startup_store = None;
+                self.begin_startup(Ok(None));
+            }),
+        };
+        if let Err(err) = result
+            && let Some(state) = self.unlock.as_mut()
+        {
+            state.error = Some(format!("{err:#}"));
+        }
+    }
+}
+
+fn active_input(state: &mut UnlockState) -> &mut Secret {
+    match state.active_field {
+        UnlockField::Password => &mut state.password,
+        UnlockField::Confirm => &mut state.confirm,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AuthConfig, ConnectionConfig, KeepaliveConfig};
+
+    fn type_text(app: &mut App, text: &str) {
+        for ch in text.chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                .unwrap();
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+
+    fn password_conn() -> ConnectionConfig {
+        ConnectionConfig {
+            name: "db".to_string(),
+            user: "u".to_string(),
+            host: "h".to_string(),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: "hunter2".into(),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    #[test]
+    fn lock_wipes_secrets_and_unlock_restores_them() {
+        let mut app = App::for_test();
+        let key = app.master_key.clone();
+        app.connections = vec![password_conn()];
+        app.lock().unwrap();
+        assert!(app.master_key.is_empty());
+        assert_eq!(
+            app.connections[0].auth,
+            AuthConfig::Password {
+                password: Secret::default().into()
+            }
+        );
+
+        type_text(&mut app, "wrong");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.is_locked());
+        assert!(app.unlock.as_ref().unwrap().error.is_some());
+
+        type_text(&mut app, "test-password");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.is_locked());
+        app.unlock.as_mut().unwrap().retry_at = None;
+        press(&mut app, KeyCode::Enter);
+        assert!(!app.is_locked());
+        assert_eq!(app.master_key, key);
+        assert_eq!(app.connections[0].auth, password_conn().auth);
+    }
+
+    #[test]
+    fn idle_timeout_locks() {
+        let mut app = App::for_test();
+        app.auto_lock = Some(Duration::from_secs(60));
+        app.poll_idle_lock();
+        assert!(!app.is_locked());
+        app.last_activity = Instant::now() - Duration::from_secs(61);
+        app.poll_idle_lock();
+        assert!(app.is_locked());
+    }
+
+    #[test]
+    fn first_run_setup_then_startup_unlock() {
+        let mut app = App::for_test();
+        app.begin_startup(Ok(None));
+        type_text(&mut app, "secret");
+        press(&mut app, KeyCode::Enter);
+        type_text(&mut app, "other");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.is_locked());
+        assert_eq!(app.unlock.as_ref().unwrap().failed_attempts, 0);
+
+        type_text(&mut app, "secret");
+        press(&mut app, KeyCode::Tab);
+        type_text(&mut app, "secret");
+        press(&mut app, KeyCode::Enter);
+        assert!(!app.is_locked());
+        app.connections = vec![password_conn()];
+        app.save_store().unwrap();
+
+        let mut next = App::for_test();
+        next.config_path = app.config_path.clone();
+        next.begin_startup(crate::storage::open_store(&next.config_path));
+        assert_eq!(next.unlock.as_ref().unwrap().mode, UnlockMode::Startup);
+        type_text(&mut next, "secret");
+        press(&mut next, KeyCode::Enter);
+        assert!(!next.is_locked());
+        assert_eq!(next.connections, vec![password_conn()]);
+    }
+
+    #[test]
+    fn legacy_vault_upgrade_keeps_changes_saved_while_locked() {
+        use base64::Engine;
+        use base64::engine::general_purpose::STANDARD as Base64;
+
+        use crate::storage::{build_store, decrypt_string, derive_key, encrypt_string, save_store};
+
+        let mut app = App::for_test();
+        let salt = Base64.decode(&app.master.salt_b64).unwrap();
+        let legacy_key = derive_key("test-password", &salt, &crate::model::legacy_kdf()).unwrap();
+        let check = decrypt_string(&app.master.check, &app.master_key).unwrap();
+        let legacy = MasterConfig {
+            check: encrypt_string(check.expose(), &legacy_key).unwrap(),
+            kdf: crate::model::legacy_kdf(),
+            ..app.master.clone()
+        };
+        let path = app.config_path.clone();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let write = |connections: &[ConnectionConfig]| {
+            let store = build_store(&legacy, &legacy_key, connections, None, false).unwrap();
+            save_store(&path, &store).unwrap();
+        };
+        write(&[password_conn()]);
+        app.begin_startup(open_store(&path));
+        let mut other = password_conn();
+        other.host = "other".to_string();
+        write(&[password_conn(), other.clone()]);
+
+        type_text(&mut app, "test-password");
+        press(&mut app, KeyCode::Enter);
+        assert!(!app.is_locked());
+        assert_ne!(app.master.kdf, crate::model::legacy_kdf());
+        let store = crate::storage::load_store(&app.config_path).unwrap();
+        let opened = unlock_store(&store, "test-password").unwrap().unwrap();
+        assert!(!opened.kdf_outdated);
+        assert!(opened.connections.contains(&other));
+        assert!(app.connections.contains(&other));
+    }
+
+    #[test]
+    fn unreadable_vault_can_be_restored_from_backup() {
+        let mut app = App::for_test();
+        app.connections = vec![password_conn()];
+        app.save_store().unwrap();
+        app.save_store().unwrap();
+        std::fs::write(&app.config_path, "{ torn").unwrap();
+
+        app.begin_startup(crate::storage::open_store(&app.config_path));
+        assert!(app.unlock.as_ref().unwrap().error.is_some());
+        press(&mut app, KeyCode::F(5));
+        assert!(matches!(
+            app.unlock.as_ref().unwrap().pending_confirm,
+            Some(UnlockConfirm::RestoreBackup(_))
+        ));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.startup_store.is_some());
+        type_text(&mut app, "test-password");
+        press(&mut app, KeyCode::Enter);
+        assert!(!app.is_locked());
+        assert_eq!(app.connections, vec![password_conn()]);
+    }
+
+    #[test]
+    fn switching_profiles_opens_their_own_vault() {
+        let mut work = App::for_test();
+        work.save_store().unwrap();
+
+        let mut app = App::for_test();
+        app.begin_startup(Ok(None));
+        type_text(&mut app, "wrong");
+        app.unlock.as_mut().unwrap().failed_attempts = 2;
+        let log_path = work.config_path.with_file_name("ssh-client.log");
+        let profile = Profile::named("work").unwrap();
+        app.open_profile(profile.clone(), work.config_path.clone(), log_path.clone());
+        assert_eq!(app.profile, profile);
+        assert_eq!(app.log_path, log_path);
+        let state = app.unlock.as_ref().unwrap();
+        assert_eq!(state.mode, UnlockMode::Startup);
+        assert!(state.password.is_empty());
+        assert_eq!(state.failed_attempts, 2);
+
+        type_text(&mut app, "test-password");
+        app.unlock.as_mut().unwrap().retry_at = None;
+        press(&mut app, KeyCode::Enter);
+        assert!(!app.is_locked());
+        assert_eq!(app.config_path, work.config_path);
+    }
+
+    #[test]
+    fn retry_delay_grows_and_caps() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(retry_delay(40), UNLOCK_RETRY_MAX);
+    }
+}
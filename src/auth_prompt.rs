@@ -0,0 +1,175 @@
+use std::sync::mpsc::{self, Sender};
+
+use ssh2::{KeyboardInteractivePrompt, Prompt};
+
+use crate::secret::Secret;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AuthPromptField {
+    pub(crate) text: String,
+    pub(crate) echo: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct AuthPromptRequest {
+    pub(crate) target: String,
+    pub(crate) name: String,
+    pub(crate) instructions: String,
+    pub(crate) fields: Vec<AuthPromptField>,
+    pub(crate) reply: Sender<Option<Vec<String>>>,
+}
+
+pub(crate) fn ask_secret(
+    requests: &Sender<AuthPromptRequest>,
+    target: String,
+    label: &str,
+    note: &str,
+) -> Option<Secret> {
+    let (reply, answers) = mpsc::channel();
+    requests
+        .send(AuthPromptRequest {
+            target,
+            name: String::new(),
+            instructions: note.to_string(),
+            fields: vec![AuthPromptField {
+                text: label.to_string(),
+                echo: false,
+            }],
+            reply,
+        })
+        .ok()?;
+    let mut answers = answers.recv().ok().flatten()?;
+    answers.truncate(1);
+    answers.pop().map(Secret::from)
+}
+
+pub(crate) struct ChannelPrompter {
+    target: String,
+    requests: Sender<AuthPromptRequest>,
+    cancelled: bool,
+}
+
+impl ChannelPrompter {
+    pub(crate) fn new(target: String, requests: Sender<AuthPromptRequest>) -> Self {
+        Self {
+            target,
+            requests,
+            cancelled: false,
+        }
+    }
+
+    pub(crate) fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+}
+
+impl KeyboardInteractivePrompt for ChannelPrompter {
+    fn prompt<'a>(
+        &mut self,
+        username: &str,
+        instructions: &str,
+        prompts: &[Prompt<'a>],
+    ) -> Vec<String> {
+        if self.cancelled {
+            return vec![String::new(); prompts.len()];
+        }
+        let (reply, answers) = mpsc::channel();
+        let request = AuthPromptRequest {
+            target: self.target.clone(),
+            name: username.to_string(),
+            instructions: instructions.trim().to_string(),
+            fields: prompts
+                .iter()
+                .map(|prompt| AuthPromptField {
+                    text: prompt.text.trim_end().to_string(),
+                    echo: prompt.echo,
+                })
+                .collect(),
+            reply,
+        };
+        let answers = self
+            .requests
+            .send(request)
+            .ok()
+            .and_then(|()| answers.recv().ok().flatten());
+        match answers {
+            Some(mut answers) => {
+                answers.resize(prompts.len(), String::new());
+                answers
+            }
+            None => {
+                self.cancelled = true;
+                vec![String::new(); prompts.len()]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn prompter_round_trips_answers() {
+        let (tx, rx) = mpsc::channel::<AuthPromptRequest>();
+        let ui = std::thread::spawn(move || {
+            let request = rx.recv().unwrap();
+            assert_eq!(request.target, "user@host");
+            assert_eq!(request.fields.len(), 2);
+            assert!(!request.fields[1].echo);
+            request
+                .reply
+                .send(Some(vec!["alice".to_string(), "123456".to_string()]))
+                .unwrap();
+        });
+        let mut prompter = ChannelPrompter::new("user@host".to_string(), tx);
+        let prompts = [
+            Prompt {
+                text: Cow::Borrowed("User: "),
+                echo: true,
+            },
+            Prompt {
+                text: Cow::Borrowed("Verification code: "),
+                echo: false,
+            },
+        ];
+        let answers = prompter.prompt("", "", &prompts);
+        ui.join().unwrap();
+        assert_eq!(answers, vec!["alice".to_string(), "123456".to_string()]);
+        assert!(!prompter.cancelled());
+    }
+
+    #[test]
+    fn ask_secret_sends_one_hidden_field() {
+        let (tx, rx) = mpsc::channel::<AuthPromptRequest>();
+        let ui = std::thread::spawn(move || {
+            let request = rx.recv().unwrap();
+            assert_eq!(request.fields.len(), 1);
+            assert!(!request.fields[0].echo);
+            request
+                .reply
+                .send(Some(vec!["hunter2".to_string()]))
+                .unwrap();
+            let request = rx.recv().unwrap();
+            request.reply.send(None).unwrap();
+        });
+        let secret = ask_secret(&tx, "db".to_string(), "Password", "Not stored").unwrap();
+        assert_eq!(secret.expose(), "hunter2");
+        assert!(ask_secret(&tx, "db".to_string(), "Password", "Not stored").is_none());
+        ui.join().unwrap();
+    }
+
+    #[test]
+    fn prompter_marks_cancel() {
+        let (tx, rx) = mpsc::channel::<AuthPromptRequest>();
+        drop(rx);
+        let mut prompter = ChannelPrompter::new("host".to_string(), tx);
+        let prompts = [Prompt {
+            text: Cow::Borrowed("Password: "),
+            echo: false,
+        }];
+        assert_eq!(prompter.prompt("", "", &prompts), vec![String::new()]);
+        assert!(prompter.cancelled());
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::{ConnectionConfig, EncryptedBlob, MasterConfig};
+use crate::secret::Secret;
+use crate::storage::{
+    create_master_from_password, decrypt_string, encrypt_string, unlock_master, write_atomic,
+};
+
+const BUNDLE_FORMAT: &str = "ss-ssh-bundle";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleFile {
+    format: String,
+    version: u32,
+    passphrase: MasterConfig,
+    connections: EncryptedBlob,
+}
+
+pub(crate) fn default_bundle_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join("ss-ssh-bundle.json"))
+}
+
+pub(crate) fn write_bundle(
+    path: &Path,
+    connections: &[ConnectionConfig],
+    include_history: bool,
+    passphrase: &str,
+) -> Result<()> {
+    let connections: Vec<ConnectionConfig> = connections
+        .iter()
+        .map(|conn| ConnectionConfig {
+            history: if include_history {
+                conn.history.clone()
+            } else {
+                vec![]
+            },
+            via: vec![],
+            ..conn.clone()
+        })
+        .collect();
+    let json = Secret::from(serde_json::to_string(&connections).context("serialize bundle")?);
+    let (header, key) = create_master_from_password(passphrase)?;
+    let bundle = BundleFile {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        passphrase: header,
+        connections: encrypt_string(json.expose(), &key)?,
+    };
+    let content = serde_json::to_string_pretty(&bundle).context("serialize bundle")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    write_atomic(path, content.as_bytes())
+}
+
+pub(crate) fn read_bundle(path: &Path, passphrase: &str) -> Result<Option<Vec<ConnectionConfig>>> {
+    let content = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let document: Value = serde_json::from_str(&content).context("not an ss-ssh bundle")?;
+    if document.get("format").and_then(Value::as_str) != Some(BUNDLE_FORMAT) {
+        anyhow::bail!("not an ss-ssh bundle");
+    }
+    let version = document.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > u64::from(BUNDLE_VERSION) {
+        anyhow::bail!(
+            "bundle has version {version}, but this ss-ssh only understands up to \
+             {BUNDLE_VERSION}; update ss-ssh to open it"
+        );
+    }
+    let bundle: BundleFile = serde_json::from_value(document).context("parse bundle")?;
+    let Some(key) = unlock_master(&bundle.passphrase, passphrase)? else {
+        return Ok(None);
+    };
+    let json = decrypt_string(&bundle.connections, &key).context("bundle is damaged")?;
+    let connections = serde_json::from_str(json.expose()).context("parse bundle")?;
+    Ok(Some(connections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AuthConfig, HistoryEntry, HistoryState, KeepaliveConfig};
+
+    fn conn() -> ConnectionConfig {
+        ConnectionConfig {
+            name: "db".to_string(),
+            user: "u".to_string(),
+            host: "db.example".to_string(),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: "hunter2".into(),
+            },
+            history: vec![HistoryEntry {
+                ts: 1,
+                state: HistoryState::Success,
+            }],
+            last_remote_dir: Some("/srv".to_string()),
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ss-ssh-bundle-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn round_trip_keeps_secrets_and_drops_history_unless_asked() {
+        let path = temp_path("plain.json");
+        write_bundle(&path, &[conn()], false, "one-time").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("hunter2"));
+
+        let read = read_bundle(&path, "one-time").unwrap().unwrap();
+        assert_eq!(read[0].auth, conn().auth);
+        assert!(read[0].history.is_empty());
+        assert_eq!(read[0].last_remote_dir.as_deref(), Some("/srv"));
+
+        write_bundle(&path, &[conn()], true, "one-time").unwrap();
+        let read = read_bundle(&path, "one-time").unwrap().unwrap();
+        assert_eq!(read, vec![conn()]);
+    }
+
+    #[test]
+    fn wrong_passphrase_and_foreign_files_are_told_apart() {
+        let path = temp_path("wrong.json");
+        write_bundle(&path, &[conn()], false, "one-time").unwrap();
+        assert!(read_bundle(&path, "guess").unwrap().is_none());
+
+        fs::write(&path, "{\"version\": 2}").unwrap();
+        let err = read_bundle(&path, "one-time").unwrap_err();
+        assert!(err.to_string().contains("not an ss-ssh bundle"));
+    }
+}
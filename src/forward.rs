@@ -0,0 +1,636 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use ssh2::{Channel, ErrorCode, Session};
+
+use crate::model::{ForwardSpec, bracket_host};
+use crate::socks::{self, SocksReply, SocksRequest, SocksVersion};
+use crate::ssh::{Pipe, PipeState, SharedSession, TUNNEL_BUFFER_BYTES, TUNNEL_IDLE_SLEEP};
+
+const LIBSSH2_ERROR_EAGAIN: i32 = -37;
+const LIBSSH2_ERROR_REQUEST_DENIED: i32 = -32;
+const LOCAL_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const SOCKS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const CHANNEL_OPEN_WAIT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub(crate) struct ForwardStatus {
+    pub(crate) spec: ForwardSpec,
+    pub(crate) listening: bool,
+    pub(crate) bound_port: Option<u16>,
+    pub(crate) active: usize,
+    pub(crate) total: u64,
+    pub(crate) bytes_sent: u64,
+    pub(crate) bytes_received: u64,
+    pub(crate) destinations: Vec<String>,
+    pub(crate) error: Option<String>,
+}
+
+pub(crate) struct Forwarder {
+    status: Arc<Mutex<Vec<ForwardStatus>>>,
+    stop: Arc<AtomicBool>,
+    errors: Receiver<String>,
+}
+
+impl Forwarder {
+    pub(crate) fn start(forwards: Vec<ForwardSpec>, session: SharedSession) -> Self {
+        let status = Arc::new(Mutex::new(
+            forwards
+                .iter()
+                .map(|spec| ForwardStatus {
+                    spec: spec.clone(),
+                    listening: false,
+                    bound_port: None,
+                    active: 0,
+                    total: 0,
+                    bytes_sent: 0,
+                    bytes_received: 0,
+                    destinations: Vec::new(),
+                    error: None,
+                })
+                .collect(),
+        ));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (errors_tx, errors) = mpsc::channel();
+        let worker = Worker {
+            status: status.clone(),
+            errors: errors_tx,
+        };
+        let worker_stop = stop.clone();
+        std::thread::spawn(move || worker.run(&forwards, &session, &worker_stop));
+        Self {
+            status,
+            stop,
+            errors,
+        }
+    }
+
+    pub(crate) fn status(&self) -> Vec<ForwardStatus> {
+        self.status
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn take_errors(&self) -> Vec<String> {
+        self.errors.try_iter().collect()
+    }
+}
+
+impl Drop for Forwarder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+enum Listener {
+    Local(TcpListener),
+    Remote(ssh2::Listener),
+}
+
+enum Accepted {
+    Socket(TcpStream),
+    Channel(Channel),
+}
+
+struct Tunnel {
+    forward: usize,
+    pipe: Pipe<TcpStream>,
+    destination: Option<String>,
+    sent: u64,
+    received: u64,
+}
+
+struct PendingOpen {
+    forward: usize,
+    socket: TcpStream,
+    host: String,
+    port: u16,
+    origin: Option<(String, u16)>,
+    socks: Option<SocksVersion>,
+    asked: Option<Instant>,
+}
+
+impl PendingOpen {
+    fn new(
+        forward: usize,
+        socket: TcpStream,
+        host: String,
+        port: u16,
+        socks: Option<SocksVersion>,
+    ) -> Self {
+        let origin = socket
+            .peer_addr()
+            .ok()
+            .map(|addr| (addr.ip().to_string(), addr.port()));
+        Self {
+            forward,
+            socket,
+            host,
+            port,
+            origin,
+            socks,
+            asked: None,
+        }
+    }
+
+    fn destination(&self) -> String {
+        format!("{}:{}", bracket_host(&self.host), self.port)
+    }
+
+    fn open(&self, session: &Session) -> Result<Channel, ssh2::Error> {
+        let src = self
+            .origin
+            .as_ref()
+            .map(|(host, port)| (host.as_str(), *port));
+        session.channel_direct_tcpip(&self.host, self.port, src)
+    }
+
+    fn finish(mut self, channel: Result<Channel, ssh2::Error>) -> Result<Pipe<TcpStream>> {
+        let mut channel = match channel {
+            Ok(channel) => channel,
+            Err(err) => {
+                if let Some(version) = self.socks {
+                    let reply = socks::reply_for_channel_error(&err);
+                    let _ = socks::write_reply(&mut self.socket, version, reply);
+                }
+                return Err(err).with_context(|| format!("open channel to {}", self.destination()));
+            }
+        };
+        if let Some(version) = self.socks
+            && let Err(err) = socks::write_reply(&mut self.socket, version, SocksReply::Succeeded)
+        {
+            let _ = channel.close();
+            return Err(err);
+        }
+        self.socket
+            .set_read_timeout(None)
+            .and_then(|_| self.socket.set_nonblocking(true))
+            .context("configure forwarded socket")?;
+        Ok(Pipe::new(channel, self.socket))
+    }
+}
+
+enum ClientReady {
+    Socks(usize, Result<(TcpStream, SocksRequest)>),
+    Local(u64, Result<TcpStream>),
+}
+
+struct Clients {
+    opens: VecDeque<PendingOpen>,
+    connecting: HashMap<u64, (usize, Channel)>,
+    next_id: u64,
+    ready_tx: Sender<ClientReady>,
+    ready: Receiver<ClientReady>,
+}
+
+impl Clients {
+    fn new() -> Self {
+        let (ready_tx, ready) = mpsc::channel();
+        Self {
+            opens: VecDeque::new(),
+            connecting: HashMap::new(),
+            next_id: 0,
+            ready_tx,
+            ready,
+        }
+    }
+
+    fn start(&mut self, forward: usize, spec: &ForwardSpec, accepted: Accepted) {
+        match (accepted, spec) {
+            (
+                Accepted::Socket(socket),
+                ForwardSpec::Local {
+                    remote_host,
+                    remote_port,
+                    ..
+                },
+            ) => {
+                self.opens.push_back(PendingOpen::new(
+                    forward,
+                    socket,
+                    remote_host.clone(),
+                    *remote_port,
+                    None,
+                ));
+            }
+            (Accepted::Socket(socket), ForwardSpec::Dynamic { .. }) => {
+                let ready = self.ready_tx.clone();
+                std::thread::spawn(move || {
+                    let _ = ready.send(ClientReady::Socks(forward, socks_handshake(socket)));
+                });
+            }
+            (
+                Accepted::Channel(channel),
+                ForwardSpec::Remote {
+                    local_host,
+                    local_port,
+                    ..
+                },
+            ) => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.connecting.insert(id, (forward, channel));
+                let (host, port) = (local_host.clone(), *local_port);
+                let ready = self.ready_tx.clone();
+                std::thread::spawn(move || {
+                    let _ = ready.send(ClientReady::Local(id, connect_local(&host, port)));
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Worker {
+    status: Arc<Mutex<Vec<ForwardStatus>>>,
+    errors: Sender<String>,
+}
+
+impl Worker {
+    fn run(&self, forwards: &[ForwardSpec], shared: &SharedSession, stop: &AtomicBool) {
+        let session = shared.lock();
+        let mut listeners: Vec<Option<Listener>> = forwards
+            .iter()
+            .enumerate()
+            .map(|(index, spec)| match listen(&session, spec) {
+                Ok((listener, bound_port)) => {
+                    self.update(|entries| {
+                        entries[index].listening = true;
+                        entries[index].bound_port = bound_port;
+                    });
+                    Some(listener)
+                }
+                Err(err) => {
+                    self.fail(index, format!("{err:#}"));
+                    None
+                }
+            })
+            .collect();
+        drop(session);
+
+        let mut tunnels: Vec<Tunnel> = Vec::new();
+        let mut clients = Clients::new();
+        let mut buffer = vec![0u8; TUNNEL_BUFFER_BYTES];
+        // Kept across passes while the server still owes a channel open.
+        let mut held = None;
+        while !stop.load(Ordering::Relaxed) {
+            let mut idle = true;
+            let session = held.take().unwrap_or_else(|| shared.lock());
+            session.set_blocking(false);
+            for (index, listener) in listeners.iter_mut().enumerate() {
+                let Some(listener) = listener else {
+                    continue;
+                };
+                match accept(listener) {
+                    Ok(None) => {}
+                    Ok(Some(accepted)) => {
+                        idle = false;
+                        clients.start(index, &forwards[index], accepted);
+                    }
+                    Err(err) => {
+                        idle = false;
+                        self.fail(index, format!("{err:#}"));
+                    }
+                }
+            }
+            if self.take_ready(&mut clients, &mut tunnels) {
+                idle = false;
+            }
+            let waiting = self.open_next(&session, &mut clients, &mut tunnels);
+
+            let mut index = 0;
+            while index < tunnels.len() {
+                let tunnel = &mut tunnels[index];
+                let state = tunnel.pipe.pump(&mut buffer);
+                if state != PipeState::Idle {
+                    idle = false;
+                    let sent = tunnel.pipe.sent - tunnel.sent;
+                    let received = tunnel.pipe.received - tunnel.received;
+                    tunnel.sent = tunnel.pipe.sent;
+                    tunnel.received = tunnel.pipe.received;
+                    let forward = tunnel.forward;
+                    let destination = tunnel.destination.as_ref();
+                    self.update(|entries| {
+                        let entry = &mut entries[forward];
+                        entry.bytes_sent += sent;
+                        entry.bytes_received += received;
+                        if state == PipeState::Closed {
+                            entry.active = entry.active.saturating_sub(1);
+                            if let Some(position) = destination
+                                .and_then(|dest| entry.destinations.iter().position(|d| d == dest))
+                            {
+                                entry.destinations.remove(position);
+                            }
+                        }
+                    });
+                }
+                if state == PipeState::Closed {
+                    tunnels.swap_remove(index).pipe.close();
+                } else {
+                    index += 1;
+                }
+            }
+
+            if waiting {
+                held = Some(session);
+            } else {
+                drop(session);
+            }
+            if idle {
+                std::thread::sleep(TUNNEL_IDLE_SLEEP);
+            }
+        }
+        // Closing channels and dropping remote listeners, which cancels them on
+        // the server, both need the session in blocking mode. A half-done open
+        // is finished first so it cannot answer someone else's.
+        let session = held.take().unwrap_or_else(|| shared.lock());
+        session.set_blocking(true);
+        if let Some(open) = clients.opens.front().filter(|open| open.asked.is_some())
+            && let Ok(mut channel) = open.open(&session)
+        {
+            let _ = channel.close();
+        }
+        for tunnel in tunnels {
+            tunnel.pipe.close();
+        }
+        for (_, mut channel) in clients.connecting.into_values() {
+            let _ = channel.close();
+        }
+        drop(listeners);
+    }
+
+    fn take_ready(&self, clients: &mut Clients, tunnels: &mut Vec<Tunnel>) -> bool {
+        let ready: Vec<ClientReady> = clients.ready.try_iter().collect();
+        let any = !ready.is_empty();
+        for client in ready {
+            match client {
+                ClientReady::Socks(forward, Ok((socket, request))) => {
+                    clients.opens.push_back(PendingOpen::new(
+                        forward,
+                        socket,
+                        request.host,
+                        request.port,
+                        Some(request.version),
+                    ));
+                }
+                ClientReady::Socks(forward, Err(err)) => self.fail(forward, format!("{err:#}")),
+                ClientReady::Local(id, socket) => {
+                    let Some((forward, mut channel)) = clients.connecting.remove(&id) else {
+                        continue;
+                    };
+                    match socket {
+                        Ok(socket) => {
+                            self.add_tunnel(tunnels, forward, Pipe::new(channel, socket), None);
+                        }
+                        Err(err) => {
+                            let _ = channel.close();
+                            self.fail(forward, format!("{err:#}"));
+                        }
+                    }
+                }
+            }
+        }
+        any
+    }
+
+    /// Moves the oldest client's channel open along. Returns whether the server
+    /// still owes the answer: libssh2 opens one channel at a time per session,
+    /// so nobody else may use the session until it arrives.
+    fn open_next(
+        &self,
+        session: &Session,
+        clients: &mut Clients,
+        tunnels: &mut Vec<Tunnel>,
+    ) -> bool {
+        let Some(open) = clients.opens.front_mut() else {
+            return false;
+        };
+        let asked = *open.asked.get_or_insert_with(Instant::now);
+        // A server that takes too long gets the rest of the session timeout in
+        // blocking mode, so a failed open is cleaned up inside libssh2.
+        let overdue = asked.elapsed() >= CHANNEL_OPEN_WAIT;
+        if overdue {
+            session.set_blocking(true);
+        }
+        let channel = open.open(session);
+        if overdue {
+            session.set_blocking(false);
+        }
+        if matches!(&channel, Err(err) if err.code() == ErrorCode::Session(LIBSSH2_ERROR_EAGAIN)) {
+            return true;
+        }
+        let Some(open) = clients.opens.pop_front() else {
+            return false;
+        };
+        let forward = open.forward;
+        let destination = open.socks.map(|_| open.destination());
+        match open.finish(channel) {
+            Ok(pipe) => self.add_tunnel(tunnels, forward, pipe, destination),
+            Err(err) => self.fail(forward, format!("{err:#}")),
+        }
+        false
+    }
+
+    fn add_tunnel(
+        &self,
+        tunnels: &mut Vec<Tunnel>,
+        forward: usize,
+        pipe: Pipe<TcpStream>,
+        destination: Option<String>,
+    ) {
+        self.update(|entries| {
+            entries[forward].active += 1;
+            entries[forward].total += 1;
+            entries[forward].error = None;
+            entries[forward].destinations.extend(destination.clone());
+        });
+        tunnels.push(Tunnel {
+            forward,
+            pipe,
+            destination,
+            sent: 0,
+            received: 0,
+        });
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut Vec<ForwardStatus>)) {
+        if let Ok(mut entries) = self.status.lock() {
+            apply(&mut entries);
+        }
+    }
+
+    fn fail(&self, index: usize, message: String) {
+        let mut spec = None;
+        self.update(|entries| {
+            spec = Some(entries[index].spec.describe());
+            entries[index].error = Some(message.clone());
+        });
+        if let Some(spec) = spec {
+            let _ = self.errors.send(format!("{spec}: {message}"));
+        }
+    }
+}
+
+fn listen(session: &Session, spec: &ForwardSpec) -> Result<(Listener, Option<u16>)> {
+    match spec {
+        ForwardSpec::Local {
+            bind_address,
+            local_port,
+            ..
+        }
+        | ForwardSpec::Dynamic {
+            bind_address,
+            port: local_port,
+        } => {
+            let listener = TcpListener::bind((bind_address.as_str(), *local_port))
+                .with_context(|| format!("bind {}:{local_port}", bracket_host(bind_address)))?;
+            listener
+                .set_nonblocking(true)
+                .context("configure listener")?;
+            Ok((Listener::Local(listener), None))
+        }
+        ForwardSpec::Remote {
+            bind_address,
+            remote_port,
+            ..
+        } => match session.channel_forward_listen(*remote_port, Some(bind_address), None) {
+            Ok((listener, bound_port)) => Ok((Listener::Remote(listener), Some(bound_port))),
+            Err(err) if err.code() == ErrorCode::Session(LIBSSH2_ERROR_REQUEST_DENIED) => {
+                Err(anyhow::anyhow!(
+                    "server refused to listen on {}:{remote_port} (port in use or forwarding disabled)",
+                    bracket_host(bind_address)
+                ))
+            }
+            Err(err) => Err(err).with_context(|| {
+                format!(
+                    "listen on server {}:{remote_port}",
+                    bracket_host(bind_address)
+                )
+            }),
+        },
+    }
+}
+
+fn accept(listener: &mut Listener) -> Result<Option<Accepted>> {
+    match listener {
+        Listener::Local(listener) => match listener.accept() {
+            Ok((socket, _)) => Ok(Some(Accepted::Socket(socket))),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err).context("accept"),
+        },
+        Listener::Remote(listener) => match listener.accept() {
+            Ok(channel) => Ok(Some(Accepted::Channel(channel))),
+            Err(err) if err.code() == ErrorCode::Session(LIBSSH2_ERROR_EAGAIN) => Ok(None),
+            Err(err) => Err(err).context("accept forwarded channel"),
+        },
+    }
+}
+
+fn socks_handshake(mut socket: TcpStream) -> Result<(TcpStream, SocksRequest)> {
+    socket
+        .set_nonblocking(false)
+        .and_then(|_| socket.set_read_timeout(Some(SOCKS_HANDSHAKE_TIMEOUT)))
+        .context("configure SOCKS socket")?;
+    let request = socks::read_request(&mut socket)?;
+    Ok((socket, request))
+}
+
+fn connect_local(host: &str, port: u16) -> Result<TcpStream> {
+    let target = format!("{}:{port}", bracket_host(host));
+    let mut last_err = None;
+    for addr in (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("resolve {target}"))?
+    {
+        match TcpStream::connect_timeout(&addr, LOCAL_CONNECT_TIMEOUT) {
+            Ok(socket) => {
+                socket
+                    .set_nonblocking(true)
+                    .context("configure forwarded socket")?;
+                return Ok(socket);
+            }
+            Err(err) => last_err = Some(err),
+        }
+    }
+    match last_err {
+        Some(err) => Err(err).with_context(|| format!("connect {target}")),
+        None => anyhow::bail!("connect {target}: no addresses"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_forwards_do_not_stop_the_others() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        let local = |local_port| ForwardSpec::Local {
+            bind_address: "127.0.0.1".to_string(),
+            local_port,
+            remote_host: "db".to_string(),
+            remote_port: 5432,
+        };
+        let forwards = vec![local(0), local(taken_port)];
+        let session = SharedSession::new(Session::new().unwrap());
+        let forwarder = Forwarder::start(forwards, session.clone());
+        let mut errors = Vec::new();
+        for _ in 0..200 {
+            errors.extend(forwarder.take_errors());
+            if !errors.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(25));
+        }
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        let status = forwarder.status();
+        assert!(status[0].listening && status[0].error.is_none());
+        assert!(!status[1].listening && status[1].error.is_some());
+        drop(session.lock());
+    }
+
+    #[test]
+    fn silent_socks_clients_do_not_hold_up_others() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let forwards = vec![ForwardSpec::Dynamic {
+            bind_address: "127.0.0.1".to_string(),
+            port,
+        }];
+        let forwarder = Forwarder::start(forwards, SharedSession::new(Session::new().unwrap()));
+        let connect = || {
+            for _ in 0..200 {
+                if let Ok(socket) = TcpStream::connect(("127.0.0.1", port)) {
+                    return socket;
+                }
+                std::thread::sleep(Duration::from_millis(25));
+            }
+            panic!("forward never listened");
+        };
+        let _silent = connect();
+        let mut bad = connect();
+        std::io::Write::write_all(&mut bad, &[9]).unwrap();
+        let started = Instant::now();
+        let mut errors = Vec::new();
+        while errors.is_empty() && started.elapsed() < SOCKS_HANDSHAKE_TIMEOUT {
+            errors.extend(forwarder.take_errors());
+            std::thread::sleep(Duration::from_millis(25));
+        }
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(
+            errors[0].contains("unsupported SOCKS version 9"),
+            "{errors:?}"
+        );
+    }
+}
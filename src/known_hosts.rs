@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD as Base64, STANDARD_NO_PAD as Base64NoPad};
+use sha2::{Digest, Sha256};
+use ssh2::{CheckResult, KnownHostFileKind, Session};
+
+use crate::model::format_host_port;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HostKeyInfo {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) key_type: String,
+    pub(crate) fingerprint: String,
+    pub(crate) key: Vec<u8>,
+}
+
+impl HostKeyInfo {
+    pub(crate) fn from_blob(host: &str, port: u16, key: &[u8]) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            key_type: key_type_from_blob(key).unwrap_or_else(|| "unknown".to_string()),
+            fingerprint: fingerprint_sha256(key),
+            key: key.to_vec(),
+        }
+    }
+
+    pub(crate) fn address(&self) -> String {
+        format_host_port(&self.host, self.port)
+    }
+
+    fn trust_key(&self) -> String {
+        format!(
+            "{}|{}",
+            known_hosts_name(&self.host, self.port),
+            self.fingerprint
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum HostKeyError {
+    Unknown(HostKeyInfo),
+    Changed(HostKeyInfo),
+}
+
+impl fmt::Display for HostKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostKeyError::Unknown(info) => write!(
+                f,
+                "Host key for {} is not known ({} {})",
+                info.address(),
+                info.key_type,
+                info.fingerprint
+            ),
+            HostKeyError::Changed(info) => write!(
+                f,
+                "Host key for {} has changed ({} {}). Refusing to connect",
+                info.address(),
+                info.key_type,
+                info.fingerprint
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HostKeyError {}
+
+pub(crate) fn known_hosts_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh").join("known_hosts"))
+}
+
+pub(crate) fn verify_host_key(session: &Session, host: &str, port: u16) -> Result<()> {
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| anyhow::anyhow!("server did not provide a host key"))?;
+    let info = HostKeyInfo::from_blob(host, port, key);
+    let path = known_hosts_path();
+    match check_known_hosts(session, path.as_deref(), host, port, key)? {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(HostKeyError::Changed(info).into()),
+        CheckResult::Failure => {
+            anyhow::bail!(
+                "Could not check the host key for {} against known_hosts",
+                info.address()
+            )
+        }
+        CheckResult::NotFound => {
+            if trusted_once()
+                .lock()
+                .is_ok_and(|set| set.contains(&info.trust_key()))
+            {
+                Ok(())
+            } else {
+                Err(HostKeyError::Unknown(info).into())
+            }
+        }
+    }
+}
+
+pub(crate) fn trust_host_key_once(info: &HostKeyInfo) {
+    if let Ok(mut set) = trusted_once().lock() {
+        set.insert(info.trust_key());
+    }
+}
+
+pub(crate) fn save_known_host(info: &HostKeyInfo) -> Result<()> {
+    let path = known_hosts_path().ok_or_else(|| anyhow::anyhow!("home directory not found"))?;
+    append_known_host(&path, info)
+}
+
+fn append_known_host(path: &Path, info: &HostKeyInfo) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("create ssh dir")?;
+    }
+    let needs_newline = fs::read(path)
+        .map(|content| content.last().is_some_and(|byte| *byte != b'\n'))
+        .unwrap_or(false);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("open known_hosts")?;
+    if needs_newline {
+        writeln!(file).context("write known_hosts")?;
+    }
+    writeln!(file, "{}", known_hosts_line(info)).context("write known_hosts")?;
+    Ok(())
+}
+
+fn check_known_hosts(
+    session: &Session,
+    path: Option<&Path>,
+    host: &str,
+    port: u16,
+    key: &[u8],
+) -> Result<CheckResult> {
+    let mut known_hosts = session.known_hosts().context("init known hosts")?;
+    if let Some(path) = path.filter(|path| path.exists()) {
+        let content = fs::read_to_string(path).context("read known_hosts")?;
+        // Like OpenSSH, only keys of the type the server offered are compared,
+        // so a host known by RSA that now offers ed25519 is new, not changed.
+        let key_type = key_type_from_blob(key);
+        for line in content
+            .lines()
+            .filter(|line| line_key_type(line) == key_type.as_deref())
+        {
+            let _ = known_hosts.read_str(line, KnownHostFileKind::OpenSSH);
+        }
+    }
+    Ok(known_hosts.check_port(host, port, key))
+}
+
+fn line_key_type(line: &str) -> Option<&str> {
+    let mut fields = line.split_whitespace();
+    let first = fields.next()?;
+    if first.starts_with('#') {
+        return None;
+    }
+    if first.starts_with('@') {
+        fields.next()?;
+    }
+    fields.next()
+}
+
+fn trusted_once() -> &'static Mutex<HashSet<String>> {
+    static TRUSTED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    TRUSTED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+fn known_hosts_name(host: &str, port: u16) -> String {
+    if port == crate::model::DEFAULT_SSH_PORT {
+        host.to_string()
+    } else {
+        format!("[{host}]:{port}")
+    }
+}
+
+fn known_hosts_line(info: &HostKeyInfo) -> String {
+    format!(
+        "{} {} {}",
+        known_hosts_name(&info.host, info.port),
+        info.key_type,
+        Base64.encode(&info.key)
+    )
+}
+
+pub(crate) fn fingerprint_sha256(key: &[u8]) -> String {
+    format!("SHA256:{}", Base64NoPad.encode(Sha256::digest(key)))
+}
+
+fn key_type_from_blob(key: &[u8]) -> Option<String> {
+    let len = u32::from_be_bytes(key.get(0..4)?.try_into().ok()?) as usize;
+    let name = key.get(4..4 + len)?;
+    String::from_utf8(name.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_blob(seed: u8) -> Vec<u8> {
+        typed_blob(b"ssh-ed25519", seed)
+    }
+
+    fn typed_blob(name: &[u8], seed: u8) -> Vec<u8> {
+        let mut blob = Vec::new();
+        blob.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        blob.extend_from_slice(name);
+        blob.extend_from_slice(&32u32.to_be_bytes());
+        blob.extend_from_slice(&[seed; 32]);
+        blob
+    }
+
+    fn temp_known_hosts() -> PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        std::env::temp_dir().join(format!("ssh-client-known-hosts-{nanos}"))
+    }
+
+    #[test]
+    fn host_key_info_reads_type_and_fingerprint() {
+        let info = HostKeyInfo::from_blob("host", 22, &test_blob(1));
+        assert_eq!(info.key_type, "ssh-ed25519");
+        assert!(info.fingerprint.starts_with("SHA256:"));
+        assert!(!info.fingerprint.ends_with('='));
+    }
+
+    #[test]
+    fn known_hosts_line_brackets_custom_port() {
+        let info = HostKeyInfo::from_blob("host", 2222, &test_blob(1));
+        assert!(known_hosts_line(&info).starts_with("[host]:2222 ssh-ed25519 "));
+        let info = HostKeyInfo::from_blob("host", 22, &test_blob(1));
+        assert!(known_hosts_line(&info).starts_with("host ssh-ed25519 "));
+    }
+
+    #[test]
+    fn appended_host_matches_and_detects_change() {
+        let path = temp_known_hosts();
+        let session = Session::new().unwrap();
+        let info = HostKeyInfo::from_blob("host", 2222, &test_blob(1));
+        let result = check_known_hosts(&session, Some(&path), "host", 2222, &info.key).unwrap();
+        assert!(matches!(result, CheckResult::NotFound));
+
+        append_known_host(&path, &info).unwrap();
+        let result = check_known_hosts(&session, Some(&path), "host", 2222, &info.key).unwrap();
+        assert!(matches!(result, CheckResult::Match));
+
+        let changed = test_blob(2);
+        let result = check_known_hosts(&session, Some(&path), "host", 2222, &changed).unwrap();
+        assert!(matches!(result, CheckResult::Mismatch));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn keys_are_only_compared_within_their_type() {
+        let path = temp_known_hosts();
+        let session = Session::new().unwrap();
+        let rsa = HostKeyInfo::from_blob("host", 22, &typed_blob(b"ssh-rsa", 1));
+        append_known_host(&path, &rsa).unwrap();
+
+        let ed25519 = test_blob(2);
+        let result = check_known_hosts(&session, Some(&path), "host", 22, &ed25519).unwrap();
+        assert!(matches!(result, CheckResult::NotFound));
+
+        let other_rsa = typed_blob(b"ssh-rsa", 2);
+        let result = check_known_hosts(&session, Some(&path), "host", 22, &other_rsa).unwrap();
+        assert!(matches!(result, CheckResult::Mismatch));
+        let _ = fs::remove_file(&path);
+    }
+}
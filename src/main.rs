@@ -12,8 +12,19 @@ use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 
 mod app;
+mod auth_prompt;
+mod bundle;
+mod forward;
+mod known_hosts;
+mod merge;
+mod migrate;
 mod model;
+mod profile;
+mod secret;
+mod secret_command;
+mod socks;
 mod ssh;
+mod ssh_config;
 mod storage;
 mod ui;
 
@@ -24,7 +35,8 @@ use ui::constants::{HEADER_HEIGHT, TERMINAL_FOOTER_HEIGHT};
 const TICK_RATE: Duration = Duration::from_millis(33);
 
 fn main() -> Result<()> {
-    let mut app = App::load_with_master()?;
+    let profile = profile::profile_from_args(std::env::args().skip(1))?;
+    let mut app = App::load(profile)?;
 
     enable_raw_mode().context("enable raw mode")?;
     let mut stdout = io::stdout();
@@ -42,6 +54,7 @@ fn main() -> Result<()> {
     )
     .ok();
     terminal.show_cursor().ok();
+    drop(app);
 
     result
 }
@@ -78,7 +91,8 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                     let ctrl_shift_c = ctrl_c && key.modifiers.contains(KeyModifiers::SHIFT);
                     if ctrl_c
                         && !ctrl_shift_c
-                        && !(app.terminal_tabs_open() && app.active_terminal_tab > 0)
+                        && (app.is_locked()
+                            || !(app.terminal_tabs_open() && app.active_terminal_tab > 0))
                     {
                         return Ok(());
                     }
@@ -86,7 +100,9 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                         return Ok(());
                     }
                 }
+                Event::Mouse(_) if app.is_locked() => {}
                 Event::Mouse(mouse) => {
+                    app.note_activity();
                     let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
                     app.handle_terminal_mouse(mouse, cols, rows);
                 }
@@ -103,6 +119,12 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
         app.poll_transfer_progress();
         app.poll_terminal_output();
         app.poll_size_calc();
+        app.poll_pending_connects();
+        app.poll_auth_prompts();
+        app.poll_keepalives();
+        app.poll_reconnects();
+        app.poll_forwards();
+        app.poll_idle_lock();
 
         if let Some(action) = app.pending_action.take() {
             match action {
@@ -111,7 +133,7 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                     app.open_terminal_tab(
                         cols,
                         rows.saturating_sub(HEADER_HEIGHT + TERMINAL_FOOTER_HEIGHT),
-                    )?;
+                    );
                 }
             }
         }
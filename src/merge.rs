@@ -0,0 +1,178 @@
+use crate::model::{ConnectionConfig, HistoryEntry, same_identity};
+
+#[derive(Debug, Clone)]
+pub(crate) struct MergeConflict {
+    pub(crate) ours: ConnectionConfig,
+    pub(crate) theirs: ConnectionConfig,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Merge {
+    pub(crate) connections: Vec<ConnectionConfig>,
+    pub(crate) conflicts: Vec<MergeConflict>,
+}
+
+pub(crate) fn merge_connections(
+    base: &[ConnectionConfig],
+    ours: &[ConnectionConfig],
+    theirs: &[ConnectionConfig],
+) -> Merge {
+    let find = |list: &[ConnectionConfig], conn: &ConnectionConfig| {
+        list.iter()
+            .find(|other| same_identity(other, conn))
+            .cloned()
+    };
+    let mut merge = Merge::default();
+    for mine in ours {
+        let original = find(base, mine);
+        let Some(other) = find(theirs, mine) else {
+            if original.is_none_or(|original| !same_settings(&original, mine)) {
+                merge.connections.push(mine.clone());
+            }
+            continue;
+        };
+        let mut merged = mine.clone();
+        merged.history = merge_history(&mine.history, &other.history);
+        let base_dir = original
+            .as_ref()
+            .and_then(|conn| conn.last_remote_dir.as_ref());
+        if mine.last_remote_dir.as_ref() == base_dir {
+            merged.last_remote_dir = other.last_remote_dir.clone();
+        }
+        let ours_changed = original
+            .as_ref()
+            .is_none_or(|original| !same_settings(original, mine));
+        let theirs_changed = original
+            .as_ref()
+            .is_none_or(|original| !same_settings(original, &other));
+        if theirs_changed && !ours_changed {
+            merged = ConnectionConfig {
+                history: merged.history,
+                last_remote_dir: merged.last_remote_dir,
+                ..other.clone()
+            };
+        } else if theirs_changed && !same_settings(mine, &other) {
+            merge.conflicts.push(MergeConflict {
+                ours: merged.clone(),
+                theirs: ConnectionConfig {
+                    history: merged.history.clone(),
+                    last_remote_dir: merged.last_remote_dir.clone(),
+                    ..other.clone()
+                },
+            });
+        }
+        merge.connections.push(merged);
+    }
+    for other in theirs {
+        if find(ours, other).is_none() && find(base, other).is_none() {
+            merge.connections.push(other.clone());
+        }
+    }
+    merge
+}
+
+fn same_settings(left: &ConnectionConfig, right: &ConnectionConfig) -> bool {
+    let strip = |conn: &ConnectionConfig| ConnectionConfig {
+        history: vec![],
+        last_remote_dir: None,
+        via: vec![],
+        ..conn.clone()
+    };
+    strip(left) == strip(right)
+}
+
+fn merge_history(ours: &[HistoryEntry], theirs: &[HistoryEntry]) -> Vec<HistoryEntry> {
+    let mut history = ours.to_vec();
+    for entry in theirs {
+        if !history.contains(entry) {
+            history.push(entry.clone());
+        }
+    }
+    history.sort_by_key(|entry| entry.ts);
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AuthConfig, HistoryState, KeepaliveConfig};
+
+    fn conn(host: &str) -> ConnectionConfig {
+        ConnectionConfig {
+            name: host.to_string(),
+            user: "u".to_string(),
+            host: host.to_string(),
+            port: 22,
+            auth: AuthConfig::Agent { identity: None },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    fn success(ts: u64) -> HistoryEntry {
+        HistoryEntry {
+            ts,
+            state: HistoryState::Success,
+        }
+    }
+
+    #[test]
+    fn combines_history_dirs_and_new_connections() {
+        let base = vec![conn("a"), conn("b")];
+        let mut ours = base.clone();
+        ours[0].history.push(success(2));
+        ours.push(conn("mine"));
+        let mut theirs = base.clone();
+        theirs[0].history.push(success(1));
+        theirs[1].last_remote_dir = Some("/srv".to_string());
+        theirs.push(conn("theirs"));
+
+        let merge = merge_connections(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty());
+        let hosts: Vec<&str> = merge.connections.iter().map(|c| c.host.as_str()).collect();
+        assert_eq!(hosts, ["a", "b", "mine", "theirs"]);
+        assert_eq!(merge.connections[0].history, [success(1), success(2)]);
+        assert_eq!(
+            merge.connections[1].last_remote_dir.as_deref(),
+            Some("/srv")
+        );
+    }
+
+    #[test]
+    fn one_sided_edits_and_deletes_win() {
+        let base = vec![conn("a"), conn("b"), conn("c")];
+        let mut ours = base.clone();
+        ours.retain(|c| c.host != "c");
+        let mut theirs = base.clone();
+        theirs[0].name = "renamed".to_string();
+        theirs.retain(|c| c.host != "b");
+
+        let merge = merge_connections(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.connections.len(), 1);
+        assert_eq!(merge.connections[0].name, "renamed");
+    }
+
+    #[test]
+    fn edits_on_both_sides_conflict() {
+        let base = vec![conn("a")];
+        let mut ours = base.clone();
+        ours[0].name = "mine".to_string();
+        let mut theirs = base.clone();
+        theirs[0].name = "theirs".to_string();
+        theirs[0].history.push(success(3));
+
+        let merge = merge_connections(&base, &ours, &theirs);
+        assert_eq!(merge.connections[0].name, "mine");
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].theirs.name, "theirs");
+        assert_eq!(merge.conflicts[0].theirs.history, [success(3)]);
+
+        let merge = merge_connections(&base, &ours, &ours);
+        assert!(merge.conflicts.is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use crate::model::legacy_kdf;
+
+pub(crate) const STORE_VERSION: u32 = 3;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+const MIGRATIONS: [Migration; STORE_VERSION as usize] =
+    [history_entries, explicit_kdf, sealed_layout];
+
+pub(crate) fn migrate(document: &mut Value) -> Result<u32> {
+    let root = document
+        .as_object_mut()
+        .context("config file is not a JSON object")?;
+    let from = match root.get("version") {
+        None => 0,
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .context("config file version is not a number")?,
+    };
+    if from > STORE_VERSION {
+        anyhow::bail!(
+            "config file has store version {from}, but this ss-ssh only understands up to \
+             {STORE_VERSION}; update ss-ssh to open it"
+        );
+    }
+    for (version, step) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        step(root).with_context(|| format!("migrate config from version {version}"))?;
+        root.insert("version".to_string(), Value::from(version as u32 + 1));
+    }
+    Ok(from)
+}
+
+fn connections(root: &mut Map<String, Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    root.get_mut("connections")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+fn history_entries(root: &mut Map<String, Value>) -> Result<()> {
+    for connection in connections(root) {
+        let Some(history) = connection.get_mut("history").and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for entry in history.iter_mut() {
+            if let Some(ts) = entry.as_u64() {
+                *entry = serde_json::json!({ "ts": ts, "state": "Success" });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn explicit_kdf(root: &mut Map<String, Value>) -> Result<()> {
+    let master = root
+        .get_mut("master")
+        .and_then(Value::as_object_mut)
+        .context("config file has no master section")?;
+    if !master.contains_key("kdf") {
+        master.insert("kdf".to_string(), serde_json::to_value(legacy_kdf())?);
+    }
+    Ok(())
+}
+
+/// 2 → 3: nothing to convert. Version 3 files may be sealed, and older builds
+/// would overwrite the sealed blob with an empty connection list.
+fn sealed_layout(_root: &mut Map<String, Value>) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn version_zero() -> Value {
+        json!({
+            "master": { "salt_b64": "c2FsdA==", "check": { "nonce": "", "ciphertext": "" } },
+            "connections": [
+                { "user": "u", "host": "h", "auth": "KeyboardInteractive", "history": [5, 7] },
+                { "user": "v", "host": "g", "auth": "KeyboardInteractive" }
+            ]
+        })
+    }
+
+    #[test]
+    fn history_entries_converts_bare_timestamps() {
+        let mut document = version_zero();
+        history_entries(document.as_object_mut().unwrap()).unwrap();
+        assert_eq!(
+            document["connections"][0]["history"],
+            json!([{ "ts": 5, "state": "Success" }, { "ts": 7, "state": "Success" }])
+        );
+        assert!(document["connections"][1].get("history").is_none());
+    }
+
+    #[test]
+    fn explicit_kdf_keeps_recorded_parameters() {
+        let mut document = version_zero();
+        explicit_kdf(document.as_object_mut().unwrap()).unwrap();
+        assert_eq!(
+            document["master"]["kdf"],
+            json!({ "algorithm": "pbkdf2_sha256", "iterations": 100_000 })
+        );
+
+        let recorded =
+            json!({ "algorithm": "argon2id", "memory_kib": 8, "time_cost": 1, "parallelism": 1 });
+        document["master"]["kdf"] = recorded.clone();
+        explicit_kdf(document.as_object_mut().unwrap()).unwrap();
+        assert_eq!(document["master"]["kdf"], recorded);
+    }
+
+    #[test]
+    fn migrate_runs_every_step_and_loads() {
+        let mut document = version_zero();
+        assert_eq!(migrate(&mut document).unwrap(), 0);
+        assert_eq!(document["version"], json!(STORE_VERSION));
+        let store: crate::model::StoreFile = serde_json::from_value(document.clone()).unwrap();
+        assert_eq!(store.connections[0].history.len(), 2);
+        assert_eq!(store.master.kdf, legacy_kdf());
+
+        assert_eq!(migrate(&mut document).unwrap(), STORE_VERSION);
+    }
+
+    #[test]
+    fn migrate_refuses_newer_files() {
+        let mut document = version_zero();
+        document["version"] = json!(STORE_VERSION + 1);
+        let before = document.clone();
+        let err = migrate(&mut document).unwrap_err();
+        assert!(err.to_string().contains("update ss-ssh"));
+        assert_eq!(document, before);
+    }
+}
@@ -1,9 +1,21 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use chrono::Local;
 use serde::{Deserialize, Serialize};
-use ssh2::Session;
+
+use crate::secret::{AskPolicy, Secret, SecretSource};
+use crate::ssh::SharedSession;
+
+pub(crate) const DEFAULT_SSH_PORT: u16 = 22;
+pub(crate) const DEFAULT_KEEPALIVE_INTERVAL_SECS: u32 = 30;
+pub(crate) const DEFAULT_KEEPALIVE_MAX_MISSED: u32 = 3;
+pub(crate) const DEFAULT_FORWARD_BIND: &str = "127.0.0.1";
+pub(crate) const DEFAULT_ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+pub(crate) const DEFAULT_ARGON2_TIME_COST: u32 = 3;
+pub(crate) const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+pub(crate) const LEGACY_PBKDF2_ITERATIONS: u32 = 100_000;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub(crate) struct ConnectionConfig {
@@ -11,11 +23,21 @@ pub(crate) struct ConnectionConfig {
     pub(crate) name: String,
     pub(crate) user: String,
     pub(crate) host: String,
+    #[serde(default = "default_port")]
+    pub(crate) port: u16,
     pub(crate) auth: AuthConfig,
     #[serde(default)]
     pub(crate) history: Vec<HistoryEntry>,
     #[serde(default)]
     pub(crate) last_remote_dir: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_jump_host")]
+    pub(crate) jump_host: Option<String>,
+    #[serde(skip)]
+    pub(crate) via: Vec<ConnectionConfig>,
+    #[serde(default)]
+    pub(crate) keepalive: KeepaliveConfig,
+    #[serde(default)]
+    pub(crate) forwards: Vec<ForwardSpec>,
 }
 
 impl ConnectionConfig {
@@ -26,17 +48,174 @@ impl ConnectionConfig {
             self.name.clone()
         }
     }
+
+    pub(crate) fn address(&self) -> String {
+        format_host_port(&self.host, self.port)
+    }
+
+    pub(crate) fn with_answers(&self, answers: &AskedSecrets) -> ConnectionConfig {
+        let mut config = self.clone();
+        if let Some(answer) = answers.get(&connection_key(self)) {
+            config.auth.fill_asked(answer);
+        }
+        for hop in &mut config.via {
+            if let Some(answer) = answers.get(&connection_key(hop)) {
+                hop.auth.fill_asked(answer);
+            }
+        }
+        config
+    }
+}
+
+pub(crate) type AskedSecrets = HashMap<String, Secret>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct KeepaliveConfig {
+    pub(crate) interval_secs: u32,
+    pub(crate) max_missed: u32,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: DEFAULT_KEEPALIVE_INTERVAL_SECS,
+            max_missed: DEFAULT_KEEPALIVE_MAX_MISSED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(crate) enum ForwardSpec {
+    Local {
+        bind_address: String,
+        local_port: u16,
+        remote_host: String,
+        remote_port: u16,
+    },
+    Remote {
+        bind_address: String,
+        remote_port: u16,
+        local_host: String,
+        local_port: u16,
+    },
+    Dynamic {
+        bind_address: String,
+        port: u16,
+    },
+}
+
+impl ForwardSpec {
+    pub(crate) fn spec_string(&self) -> String {
+        match self {
+            ForwardSpec::Local {
+                bind_address,
+                local_port,
+                remote_host,
+                remote_port,
+            } => {
+                let bind = bind_prefix(bind_address);
+                format!(
+                    "L {bind}{local_port}:{}:{remote_port}",
+                    bracket_host(remote_host)
+                )
+            }
+            ForwardSpec::Remote {
+                bind_address,
+                remote_port,
+                local_host,
+                local_port,
+            } => {
+                let bind = bind_prefix(bind_address);
+                format!(
+                    "R {bind}{remote_port}:{}:{local_port}",
+                    bracket_host(local_host)
+                )
+            }
+            ForwardSpec::Dynamic { bind_address, port } => {
+                format!("D {}{port}", bind_prefix(bind_address))
+            }
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            ForwardSpec::Local {
+                bind_address,
+                local_port,
+                remote_host,
+                remote_port,
+            } => format!(
+                "L {}:{local_port} -> {}:{remote_port}",
+                bracket_host(bind_address),
+                bracket_host(remote_host)
+            ),
+            ForwardSpec::Remote {
+                bind_address,
+                remote_port,
+                local_host,
+                local_port,
+            } => format!(
+                "R remote {}:{remote_port} -> {}:{local_port}",
+                bracket_host(bind_address),
+                bracket_host(local_host)
+            ),
+            ForwardSpec::Dynamic { bind_address, port } => {
+                format!("D SOCKS {}:{port}", bracket_host(bind_address))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub(crate) enum AuthConfig {
     Password {
-        password: String,
+        password: SecretSource,
     },
     PrivateKey {
         path: String,
-        password: Option<String>,
+        password: Option<SecretSource>,
     },
+    Agent {
+        identity: Option<String>,
+    },
+    KeyboardInteractive,
+}
+
+impl AuthConfig {
+    pub(crate) fn wipe_secrets(&mut self) {
+        match self {
+            AuthConfig::Password { password } => password.wipe(),
+            AuthConfig::PrivateKey { password, .. } => {
+                if matches!(password, Some(SecretSource::Stored(_))) {
+                    *password = None;
+                }
+            }
+            AuthConfig::Agent { .. } | AuthConfig::KeyboardInteractive => {}
+        }
+    }
+
+    pub(crate) fn asks_user(&self) -> bool {
+        match self {
+            AuthConfig::Password { password } => matches!(password, SecretSource::Ask { .. }),
+            AuthConfig::PrivateKey { password, .. } => {
+                matches!(password, Some(SecretSource::Ask { .. }))
+            }
+            AuthConfig::Agent { .. } => false,
+            AuthConfig::KeyboardInteractive => true,
+        }
+    }
+
+    fn fill_asked(&mut self, answer: &Secret) {
+        let source = match self {
+            AuthConfig::Password { password } => Some(password),
+            AuthConfig::PrivateKey { password, .. } => password.as_mut(),
+            AuthConfig::Agent { .. } | AuthConfig::KeyboardInteractive => None,
+        };
+        if let Some(source) = source.filter(|source| matches!(source, SecretSource::Ask { .. })) {
+            *source = SecretSource::Stored(answer.clone());
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -53,19 +232,55 @@ pub(crate) struct HistoryEntry {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct StoreFile {
+    #[serde(default)]
+    pub(crate) version: u32,
     pub(crate) master: MasterConfig,
+    #[serde(default)]
     pub(crate) connections: Vec<StoredConnection>,
     #[serde(default)]
     pub(crate) last_local_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sealed: Option<EncryptedBlob>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(crate) struct MasterConfig {
     pub(crate) salt_b64: String,
     pub(crate) check: EncryptedBlob,
+    #[serde(default = "legacy_kdf")]
+    pub(crate) kdf: KdfParams,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "algorithm", rename_all = "snake_case")]
+pub(crate) enum KdfParams {
+    Pbkdf2Sha256 {
+        iterations: u32,
+    },
+    Argon2id {
+        memory_kib: u32,
+        time_cost: u32,
+        parallelism: u32,
+    },
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::Argon2id {
+            memory_kib: DEFAULT_ARGON2_MEMORY_KIB,
+            time_cost: DEFAULT_ARGON2_TIME_COST,
+            parallelism: DEFAULT_ARGON2_PARALLELISM,
+        }
+    }
+}
+
+pub(crate) fn legacy_kdf() -> KdfParams {
+    KdfParams::Pbkdf2Sha256 {
+        iterations: LEGACY_PBKDF2_ITERATIONS,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(crate) struct EncryptedBlob {
     pub(crate) nonce: String,
     pub(crate) ciphertext: String,
@@ -77,26 +292,46 @@ pub(crate) struct StoredConnection {
     pub(crate) name: String,
     pub(crate) user: String,
     pub(crate) host: String,
+    #[serde(default = "default_port")]
+    pub(crate) port: u16,
     pub(crate) auth: StoredAuthConfig,
     #[serde(default, deserialize_with = "deserialize_history")]
     pub(crate) history: Vec<HistoryEntry>,
     #[serde(default)]
     pub(crate) last_remote_dir: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_jump_host")]
+    pub(crate) jump_host: Option<String>,
+    #[serde(default)]
+    pub(crate) keepalive: KeepaliveConfig,
+    #[serde(default)]
+    pub(crate) forwards: Vec<ForwardSpec>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) enum StoredAuthConfig {
     Password {
-        password: EncryptedBlob,
+        password: StoredSecret,
     },
     PrivateKey {
         path: String,
-        password: Option<EncryptedBlob>,
+        password: Option<StoredSecret>,
+    },
+    Agent {
+        identity: Option<String>,
     },
+    KeyboardInteractive,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum StoredSecret {
+    Encrypted(EncryptedBlob),
+    Command { command: EncryptedBlob },
+    Ask { ask: AskPolicy },
 }
 
 pub(crate) fn same_identity(left: &ConnectionConfig, right: &ConnectionConfig) -> bool {
-    if left.user != right.user || left.host != right.host {
+    if left.user != right.user || left.host != right.host || left.port != right.port {
         return false;
     }
     match (&left.auth, &right.auth) {
@@ -109,6 +344,15 @@ pub(crate) fn same_identity(left: &ConnectionConfig, right: &ConnectionConfig) -
                 path: right_path, ..
             },
         ) => left_path == right_path,
+        (
+            AuthConfig::Agent {
+                identity: left_identity,
+            },
+            AuthConfig::Agent {
+                identity: right_identity,
+            },
+        ) => left_identity == right_identity,
+        (AuthConfig::KeyboardInteractive, AuthConfig::KeyboardInteractive) => true,
         _ => false,
     }
 }
@@ -117,16 +361,213 @@ pub(crate) fn connection_key(conn: &ConnectionConfig) -> String {
     let auth_key = match &conn.auth {
         AuthConfig::Password { .. } => "pw".to_string(),
         AuthConfig::PrivateKey { path, .. } => format!("pk:{}", path),
+        AuthConfig::Agent { identity: None } => "agent".to_string(),
+        AuthConfig::Agent {
+            identity: Some(identity),
+        } => format!("agent:{}", identity),
+        AuthConfig::KeyboardInteractive => "kbdint".to_string(),
+    };
+    format!("{}@{}:{}|{}", conn.user, conn.host, conn.port, auth_key)
+}
+
+pub(crate) fn jump_reference(conn: &ConnectionConfig) -> String {
+    format!("{}@{}:{}", conn.user, conn.host, conn.port)
+}
+
+pub(crate) fn default_port() -> u16 {
+    DEFAULT_SSH_PORT
+}
+
+pub(crate) fn parse_host_port(input: &str) -> Result<(String, Option<u16>), String> {
+    let input = input.trim();
+    if let Some(rest) = input.strip_prefix('[') {
+        let Some((host, tail)) = rest.split_once(']') else {
+            return Err("Missing closing ] in host".to_string());
+        };
+        if host.is_empty() {
+            return Err("Host is required".to_string());
+        }
+        if tail.is_empty() {
+            return Ok((host.to_string(), None));
+        }
+        let Some(port) = tail.strip_prefix(':') else {
+            return Err("Unexpected characters after ]".to_string());
+        };
+        return parse_port(port).map(|port| (host.to_string(), Some(port)));
+    }
+    match input.split_once(':') {
+        Some((host, port)) if !port.contains(':') => {
+            if host.is_empty() {
+                return Err("Host is required".to_string());
+            }
+            parse_port(port).map(|port| (host.to_string(), Some(port)))
+        }
+        _ => Ok((input.to_string(), None)),
+    }
+}
+
+pub(crate) fn parse_port(value: &str) -> Result<u16, String> {
+    match value.trim().parse::<u16>() {
+        Ok(0) | Err(_) => Err(format!("Invalid port: {}", value.trim())),
+        Ok(port) => Ok(port),
+    }
+}
+
+pub(crate) fn parse_count(value: &str) -> Result<Option<u32>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse::<u32>()
+        .map(Some)
+        .map_err(|_| format!("Invalid number: {value}"))
+}
+
+pub(crate) fn parse_forwards(value: &str) -> Result<Vec<ForwardSpec>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(parse_forward)
+        .collect()
+}
+
+pub(crate) fn format_forwards(forwards: &[ForwardSpec]) -> String {
+    forwards
+        .iter()
+        .map(ForwardSpec::spec_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn parse_forward(entry: &str) -> Result<ForwardSpec, String> {
+    let Some((kind, rest)) = entry.split_once(char::is_whitespace) else {
+        return Err(format!("Invalid forward: {entry}"));
     };
-    format!("{}@{}|{}", conn.user, conn.host, auth_key)
+    let fields = split_forward_fields(rest.trim())?;
+    if matches!(kind, "D" | "d") {
+        let (bind_address, port) = match fields.as_slice() {
+            [port] => (DEFAULT_FORWARD_BIND.to_string(), port),
+            [bind, port] => (bind.clone(), port),
+            _ => return Err(format!("Expected D [bind:]port, got {entry}")),
+        };
+        return Ok(ForwardSpec::Dynamic {
+            bind_address,
+            port: parse_port(port)?,
+        });
+    }
+    let (bind_address, fields) = match fields.as_slice() {
+        [_, _, _] => (DEFAULT_FORWARD_BIND.to_string(), &fields[..]),
+        [bind, rest @ ..] if rest.len() == 3 => (bind.clone(), rest),
+        _ => {
+            return Err(format!(
+                "Expected {kind} [bind:]port:host:hostport, got {entry}"
+            ));
+        }
+    };
+    if fields[1].is_empty() {
+        return Err(format!("Missing target host in {entry}"));
+    }
+    match kind {
+        "L" | "l" => Ok(ForwardSpec::Local {
+            bind_address,
+            local_port: parse_port(&fields[0])?,
+            remote_host: fields[1].clone(),
+            remote_port: parse_port(&fields[2])?,
+        }),
+        "R" | "r" => Ok(ForwardSpec::Remote {
+            bind_address,
+            remote_port: if fields[0].trim() == "0" {
+                0
+            } else {
+                parse_port(&fields[0])?
+            },
+            local_host: fields[1].clone(),
+            local_port: parse_port(&fields[2])?,
+        }),
+        _ => Err(format!("Unknown forward type {kind} (use L, R or D)")),
+    }
+}
+
+fn split_forward_fields(value: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![String::new()];
+    let mut in_brackets = false;
+    for ch in value.chars() {
+        match ch {
+            '[' if !in_brackets => in_brackets = true,
+            ']' if in_brackets => in_brackets = false,
+            ':' if !in_brackets => fields.push(String::new()),
+            _ => fields.last_mut().expect("fields is never empty").push(ch),
+        }
+    }
+    if in_brackets {
+        return Err(format!("Missing closing ] in {value}"));
+    }
+    Ok(fields)
+}
+
+pub(crate) fn bind_prefix(bind_address: &str) -> String {
+    if bind_address == DEFAULT_FORWARD_BIND {
+        String::new()
+    } else {
+        format!("{}:", bracket_host(bind_address))
+    }
+}
+
+pub(crate) fn bracket_host(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    }
+}
+
+pub(crate) fn format_host_port(host: &str, port: u16) -> String {
+    let host = if host.contains(':') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    };
+    if port == DEFAULT_SSH_PORT {
+        host
+    } else {
+        format!("{host}:{port}")
+    }
 }
 
 #[derive(Clone)]
 pub(crate) struct OpenConnection {
     pub(crate) config: ConnectionConfig,
-    pub(crate) session: Session,
+    pub(crate) session: SharedSession,
     #[allow(dead_code)]
     pub(crate) connected_at: SystemTime,
+    pub(crate) keepalive: KeepaliveStatus,
+    pub(crate) asked: AskedSecrets,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConnectionHealth {
+    Alive,
+    Stale,
+    Dead,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KeepaliveStatus {
+    pub(crate) health: ConnectionHealth,
+    pub(crate) missed: u32,
+    pub(crate) last_probe: Instant,
+}
+
+impl Default for KeepaliveStatus {
+    fn default() -> Self {
+        Self {
+            health: ConnectionHealth::Alive,
+            missed: 0,
+            last_probe: Instant::now(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -142,9 +583,16 @@ pub(crate) enum Field {
     Name,
     User,
     Host,
+    Port,
+    JumpHost,
     AuthType,
     KeyPath,
+    AgentIdentity,
+    SecretSource,
     Password,
+    KeepaliveInterval,
+    KeepaliveMissed,
+    Forwards,
     ActionTest,
     ActionSave,
 }
@@ -155,6 +603,7 @@ pub(crate) enum MasterField {
     New,
     Confirm,
     ActionSave,
+    ActionLayout,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -162,6 +611,15 @@ pub(crate) enum AuthKind {
     PasswordOnly,
     PrivateKey,
     PrivateKeyWithPassword,
+    Agent,
+    KeyboardInteractive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SecretKind {
+    Stored,
+    Command,
+    Ask(AskPolicy),
 }
 
 #[derive(Debug, Clone)]
@@ -169,9 +627,17 @@ pub(crate) struct NewConnectionState {
     pub(crate) name: String,
     pub(crate) user: String,
     pub(crate) host: String,
+    pub(crate) port: String,
     pub(crate) auth_kind: AuthKind,
     pub(crate) key_path: String,
-    pub(crate) password: String,
+    pub(crate) agent_identity: String,
+    pub(crate) secret_kind: SecretKind,
+    pub(crate) password: Secret,
+    pub(crate) secret_command: String,
+    pub(crate) jump_host: Option<String>,
+    pub(crate) keepalive_interval: String,
+    pub(crate) keepalive_missed: String,
+    pub(crate) forwards: String,
     pub(crate) active_field: Field,
 }
 
@@ -181,9 +647,17 @@ impl Default for NewConnectionState {
             name: String::new(),
             user: String::new(),
             host: String::new(),
+            port: DEFAULT_SSH_PORT.to_string(),
             auth_kind: AuthKind::PasswordOnly,
             key_path: String::new(),
-            password: String::new(),
+            agent_identity: String::new(),
+            secret_kind: SecretKind::Stored,
+            password: Secret::default(),
+            secret_command: String::new(),
+            jump_host: None,
+            keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL_SECS.to_string(),
+            keepalive_missed: DEFAULT_KEEPALIVE_MAX_MISSED.to_string(),
+            forwards: String::new(),
             active_field: Field::User,
         }
     }
@@ -191,18 +665,66 @@ impl Default for NewConnectionState {
 
 #[derive(Debug, Clone)]
 pub(crate) struct MasterPasswordState {
-    pub(crate) current: String,
-    pub(crate) new_password: String,
-    pub(crate) confirm: String,
+    pub(crate) current: Secret,
+    pub(crate) new_password: Secret,
+    pub(crate) confirm: Secret,
     pub(crate) active_field: MasterField,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnlockMode {
+    Setup,
+    Startup,
+    Idle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnlockField {
+    Password,
+    Confirm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum UnlockConfirm {
+    RestoreBackup(PathBuf),
+    StartOver,
+}
+
+#[derive(Debug)]
+pub(crate) struct UnlockState {
+    pub(crate) mode: UnlockMode,
+    pub(crate) password: Secret,
+    pub(crate) confirm: Secret,
+    pub(crate) active_field: UnlockField,
+    pub(crate) error: Option<String>,
+    pub(crate) failed_attempts: u32,
+    pub(crate) retry_at: Option<Instant>,
+    pub(crate) caps_lock: bool,
+    pub(crate) pending_confirm: Option<UnlockConfirm>,
+}
+
+impl UnlockState {
+    pub(crate) fn new(mode: UnlockMode) -> Self {
+        Self {
+            mode,
+            password: Secret::default(),
+            confirm: Secret::default(),
+            active_field: UnlockField::Password,
+            error: None,
+            failed_attempts: 0,
+            retry_at: None,
+            caps_lock: false,
+            pending_confirm: None,
+        }
+    }
+}
+
 impl Default for MasterPasswordState {
     fn default() -> Self {
         Self {
-            current: String::new(),
-            new_password: String::new(),
-            confirm: String::new(),
+            current: Secret::default(),
+            new_password: Secret::default(),
+            confirm: Secret::default(),
             active_field: MasterField::Current,
         }
     }
@@ -268,6 +790,58 @@ pub(crate) struct KeyPickerState {
     pub(crate) selected: usize,
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct ImportCandidate {
+    pub(crate) config: ConnectionConfig,
+    pub(crate) checked: bool,
+    pub(crate) duplicate: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImportSource {
+    SshConfig,
+    Bundle,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SshImportState {
+    pub(crate) source: PathBuf,
+    pub(crate) origin: ImportSource,
+    pub(crate) candidates: Vec<ImportCandidate>,
+    pub(crate) selected: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SshExportState {
+    pub(crate) path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BundleField {
+    File,
+    Passphrase,
+    Confirm,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BundleExportState {
+    pub(crate) checked: Vec<bool>,
+    pub(crate) selected: usize,
+    pub(crate) include_history: bool,
+    pub(crate) picking: bool,
+    pub(crate) path: String,
+    pub(crate) passphrase: Secret,
+    pub(crate) confirm: Secret,
+    pub(crate) active_field: BundleField,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BundleImportState {
+    pub(crate) path: String,
+    pub(crate) passphrase: Secret,
+    pub(crate) active_field: BundleField,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum TransferDirection {
     Upload,
@@ -280,9 +854,15 @@ pub(crate) enum TransferUpdate {
 }
 
 #[derive(Debug, Clone)]
-pub(crate) struct KeyCandidate {
-    pub(crate) path: String,
-    pub(crate) password: Option<String>,
+pub(crate) enum KeyCandidate {
+    File {
+        path: String,
+        password: Option<SecretSource>,
+    },
+    Agent {
+        comment: String,
+        fingerprint: String,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -342,6 +922,17 @@ where
     }
 }
 
+pub(crate) fn deserialize_jump_host<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let reference = Option::<String>::deserialize(deserializer)?;
+    Ok(reference.map(|reference| match reference.split_once('|') {
+        Some((endpoint, _)) => endpoint.to_string(),
+        None => reference,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,28 +943,77 @@ mod tests {
             name: String::new(),
             user: "u".to_string(),
             host: "host".to_string(),
+            port: 22,
             auth: AuthConfig::Password {
-                password: "pw".to_string(),
+                password: "pw".into(),
             },
             history: vec![],
             last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
         };
         assert_eq!(conn.label(), "host");
         conn.name = "friendly".to_string();
         assert_eq!(conn.label(), "friendly");
     }
 
+    #[test]
+    fn remembered_answers_fill_asked_secrets_only() {
+        let ask = SecretSource::Ask {
+            ask: AskPolicy::WhileConnected,
+        };
+        let hop = ConnectionConfig {
+            name: String::new(),
+            user: "u".to_string(),
+            host: "bastion".to_string(),
+            port: 22,
+            auth: AuthConfig::PrivateKey {
+                path: "~/.ssh/id".to_string(),
+                password: Some(ask.clone()),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        };
+        let target = ConnectionConfig {
+            host: "db".to_string(),
+            auth: AuthConfig::Password { password: ask },
+            via: vec![hop.clone()],
+            ..hop.clone()
+        };
+        let answers = AskedSecrets::from([(connection_key(&hop), Secret::from("phrase"))]);
+        let filled = target.with_answers(&answers);
+        assert_eq!(filled.auth, target.auth);
+        assert_eq!(
+            filled.via[0].auth,
+            AuthConfig::PrivateKey {
+                path: "~/.ssh/id".to_string(),
+                password: Some("phrase".into()),
+            }
+        );
+    }
+
     #[test]
     fn same_identity_matches_auth_type() {
         let base = ConnectionConfig {
             name: String::new(),
             user: "u".to_string(),
             host: "h".to_string(),
+            port: 22,
             auth: AuthConfig::Password {
-                password: "pw".to_string(),
+                password: "pw".into(),
             },
             history: vec![],
             last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
         };
         let other = ConnectionConfig {
             auth: AuthConfig::PrivateKey {
@@ -385,11 +1025,25 @@ mod tests {
         assert!(!same_identity(&base, &other));
         let other_pw = ConnectionConfig {
             auth: AuthConfig::Password {
-                password: "pw2".to_string(),
+                password: "pw2".into(),
             },
             ..base.clone()
         };
         assert!(same_identity(&base, &other_pw));
+        let agent = ConnectionConfig {
+            auth: AuthConfig::Agent { identity: None },
+            ..base.clone()
+        };
+        let pinned = ConnectionConfig {
+            auth: AuthConfig::Agent {
+                identity: Some("work".to_string()),
+            },
+            ..base.clone()
+        };
+        assert!(!same_identity(&base, &agent));
+        assert!(!same_identity(&agent, &pinned));
+        assert!(same_identity(&pinned, &pinned.clone()));
+        assert!(connection_key(&pinned).ends_with("|agent:work"));
     }
 
     #[test]
@@ -398,13 +1052,125 @@ mod tests {
             name: String::new(),
             user: "u".to_string(),
             host: "h".to_string(),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: "pw".into(),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
+        };
+        assert!(connection_key(&conn).contains("u@h:22|pw"));
+    }
+
+    #[test]
+    fn same_identity_distinguishes_ports() {
+        let base = ConnectionConfig {
+            name: String::new(),
+            user: "u".to_string(),
+            host: "h".to_string(),
+            port: 22,
             auth: AuthConfig::Password {
-                password: "pw".to_string(),
+                password: "pw".into(),
             },
             history: vec![],
             last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: KeepaliveConfig::default(),
+            forwards: vec![],
         };
-        assert!(connection_key(&conn).contains("u@h|pw"));
+        let other = ConnectionConfig {
+            port: 2222,
+            ..base.clone()
+        };
+        assert!(!same_identity(&base, &other));
+        assert_ne!(connection_key(&base), connection_key(&other));
+    }
+
+    #[test]
+    fn parse_host_port_handles_forms() {
+        assert_eq!(parse_host_port("host"), Ok(("host".to_string(), None)));
+        assert_eq!(
+            parse_host_port("host:2222"),
+            Ok(("host".to_string(), Some(2222)))
+        );
+        assert_eq!(
+            parse_host_port("[::1]:2200"),
+            Ok(("::1".to_string(), Some(2200)))
+        );
+        assert_eq!(
+            parse_host_port("[fe80::1]"),
+            Ok(("fe80::1".to_string(), None))
+        );
+        assert_eq!(
+            parse_host_port("fe80::1"),
+            Ok(("fe80::1".to_string(), None))
+        );
+        assert!(parse_host_port("host:0").is_err());
+        assert!(parse_host_port("host:abc").is_err());
+        assert!(parse_host_port("[::1").is_err());
+    }
+
+    #[test]
+    fn format_host_port_brackets_ipv6() {
+        assert_eq!(format_host_port("host", 22), "host");
+        assert_eq!(format_host_port("host", 2222), "host:2222");
+        assert_eq!(format_host_port("::1", 2222), "[::1]:2222");
+    }
+
+    #[test]
+    fn parse_forwards_round_trips_local_specs() {
+        let forwards = parse_forwards("L 5432:db:5432, l 0.0.0.0:8080:localhost:80,").unwrap();
+        assert_eq!(
+            forwards[0],
+            ForwardSpec::Local {
+                bind_address: DEFAULT_FORWARD_BIND.to_string(),
+                local_port: 5432,
+                remote_host: "db".to_string(),
+                remote_port: 5432,
+            }
+        );
+        assert_eq!(
+            format_forwards(&forwards),
+            "L 5432:db:5432, L 0.0.0.0:8080:localhost:80"
+        );
+        let ipv6 = parse_forwards("L [::1]:9000:[fd00::2]:22").unwrap();
+        assert_eq!(parse_forwards(&format_forwards(&ipv6)).unwrap(), ipv6);
+        assert!(parse_forwards("").unwrap().is_empty());
+        assert!(parse_forwards("L 5432:db").is_err());
+        assert!(parse_forwards("L 0:db:5432").is_err());
+        assert!(parse_forwards("X 1:h:2").is_err());
+
+        let remote = parse_forwards("R 0:localhost:3000, R 0.0.0.0:8080:web:80").unwrap();
+        assert!(matches!(
+            &remote[0],
+            ForwardSpec::Remote {
+                remote_port: 0,
+                local_port: 3000,
+                ..
+            }
+        ));
+        assert_eq!(
+            format_forwards(&remote),
+            "R 0:localhost:3000, R 0.0.0.0:8080:web:80"
+        );
+        assert!(parse_forwards("R 8080:localhost:0").is_err());
+
+        let dynamic = parse_forwards("D 1080, D [::1]:1081").unwrap();
+        assert_eq!(
+            dynamic[1],
+            ForwardSpec::Dynamic {
+                bind_address: "::1".to_string(),
+                port: 1081,
+            }
+        );
+        assert_eq!(format_forwards(&dynamic), "D 1080, D [::1]:1081");
+        assert!(parse_forwards("D 1:2:3").is_err());
+        assert!(parse_forwards("L [::1:9000:h:22").is_err());
     }
 
     #[test]
@@ -420,9 +1186,19 @@ mod tests {
         "#;
         let stored: StoredConnection = serde_json::from_str(json).unwrap();
         assert_eq!(stored.history.len(), 2);
+        assert_eq!(stored.jump_host, None);
+        assert_eq!(stored.port, DEFAULT_SSH_PORT);
         assert!(matches!(stored.history[0].state, HistoryState::Success));
     }
 
+    #[test]
+    fn jump_hosts_saved_with_their_auth_still_resolve() {
+        let json = r#"{ "user": "u", "host": "h", "auth": "KeyboardInteractive",
+            "jump_host": "ops@bastion:2222|pk:/keys/id" }"#;
+        let conn: ConnectionConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(conn.jump_host.as_deref(), Some("ops@bastion:2222"));
+    }
+
     #[test]
     fn format_history_entry_includes_state() {
         let entry = HistoryEntry {
@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+const PROFILE_ENV: &str = "SS_SSH_PROFILE";
+const PROFILE_ARG: &str = "--profile";
+const DEFAULT_PROFILE: &str = "default";
+const APP_DIR: &str = "ssh-client";
+const PROFILES_DIR: &str = "profiles";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Profile(Option<String>);
+
+impl Profile {
+    pub(crate) fn named(name: &str) -> Result<Self> {
+        let name = name.trim();
+        if name == DEFAULT_PROFILE {
+            return Ok(Self::default());
+        }
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+        if !valid {
+            anyhow::bail!("invalid profile name {name:?}: use letters, digits, '-' and '_'");
+        }
+        Ok(Self(Some(name.to_string())))
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.0.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    pub(crate) fn is_default(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub(crate) fn config_path(&self) -> Result<PathBuf> {
+        self.file_path("config.json", "ssh-client-config.json")
+    }
+
+    pub(crate) fn log_path(&self) -> Result<PathBuf> {
+        self.file_path("ssh-client.log", "ssh-client.log")
+    }
+
+    fn file_path(&self, file: &str, fallback: &str) -> Result<PathBuf> {
+        if let Some(root) = app_dir() {
+            return Ok(self.dir_in(&root).join(file));
+        }
+        let dir = std::env::current_dir().context("current dir")?;
+        Ok(match &self.0 {
+            Some(name) => dir.join(format!("ssh-client-{name}-{file}")),
+            None => dir.join(fallback),
+        })
+    }
+
+    fn dir_in(&self, root: &Path) -> PathBuf {
+        match &self.0 {
+            Some(name) => root.join(PROFILES_DIR).join(name),
+            None => root.to_path_buf(),
+        }
+    }
+}
+
+fn app_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub(crate) fn profile_from_args(args: impl IntoIterator<Item = String>) -> Result<Profile> {
+    select_profile(args, std::env::var(PROFILE_ENV).ok())
+}
+
+fn select_profile(args: impl IntoIterator<Item = String>, env: Option<String>) -> Result<Profile> {
+    let mut args = args.into_iter();
+    let mut chosen = None;
+    while let Some(arg) = args.next() {
+        if arg == PROFILE_ARG {
+            let name = args
+                .next()
+                .with_context(|| format!("{PROFILE_ARG} needs a profile name"))?;
+            chosen = Some(name);
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            chosen = Some(name.to_string());
+        } else {
+            anyhow::bail!("unknown argument {arg:?}; usage: ss-ssh [{PROFILE_ARG} NAME]");
+        }
+    }
+    match chosen.or(env.filter(|name| !name.trim().is_empty())) {
+        Some(name) => Profile::named(&name),
+        None => Ok(Profile::default()),
+    }
+}
+
+pub(crate) fn list_profiles() -> Vec<Profile> {
+    app_dir()
+        .map(|root| profiles_in(&root))
+        .unwrap_or_else(|| vec![Profile::default()])
+}
+
+fn profiles_in(root: &Path) -> Vec<Profile> {
+    let mut names: Vec<String> = fs::read_dir(root.join(PROFILES_DIR))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name != DEFAULT_PROFILE)
+        .collect();
+    names.sort();
+    std::iter::once(Profile::default())
+        .chain(names.iter().filter_map(|name| Profile::named(name).ok()))
+        .collect()
+}
+
+pub(crate) fn next_profile(profiles: &[Profile], current: &Profile) -> Option<Profile> {
+    let index = profiles.iter().position(|profile| profile == current)?;
+    let next = &profiles[(index + 1) % profiles.len()];
+    (next != current).then(|| next.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn argument_beats_environment() {
+        let env = Some("home".to_string());
+        let profile = select_profile(args(&["--profile", "work"]), env.clone()).unwrap();
+        assert_eq!(profile.name(), "work");
+        let profile = select_profile(args(&["--profile=ops"]), env.clone()).unwrap();
+        assert_eq!(profile.name(), "ops");
+        assert_eq!(select_profile(args(&[]), env).unwrap().name(), "home");
+        assert!(select_profile(args(&[]), None).unwrap().is_default());
+        assert!(select_profile(args(&["--profile"]), None).is_err());
+        assert!(select_profile(args(&["--verbose"]), None).is_err());
+    }
+
+    #[test]
+    fn names_must_be_safe_directory_names() {
+        assert!(Profile::named("work_2-eu").is_ok());
+        assert!(Profile::named("default").unwrap().is_default());
+        for bad in ["", "../etc", "a/b", "with space", ".hidden"] {
+            assert!(Profile::named(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn named_profiles_live_in_their_own_directory() {
+        let root = Path::new("/cfg/ssh-client");
+        let work = Profile::named("work").unwrap();
+        assert_eq!(
+            work.dir_in(root),
+            Path::new("/cfg/ssh-client/profiles/work")
+        );
+        assert_eq!(Profile::default().dir_in(root), root);
+    }
+
+    #[test]
+    fn lists_default_first_and_cycles() {
+        let root = std::env::temp_dir().join(format!("ss-ssh-profiles-{}", std::process::id()));
+        for name in ["work", "home", "not valid"] {
+            fs::create_dir_all(root.join(PROFILES_DIR).join(name)).unwrap();
+        }
+        let profiles = profiles_in(&root);
+        let names: Vec<&str> = profiles.iter().map(Profile::name).collect();
+        assert_eq!(names, ["default", "home", "work"]);
+        let work = Profile::named("work").unwrap();
+        assert_eq!(next_profile(&profiles, &work), Some(Profile::default()));
+        assert_eq!(
+            next_profile(&[Profile::default()], &Profile::default()),
+            None
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+}
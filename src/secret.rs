@@ -0,0 +1,189 @@
+use std::fmt;
+use std::ops::Deref;
+
+use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::{Zeroize, Zeroizing};
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub(crate) fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn char_count(&self) -> usize {
+        self.0.chars().count()
+    }
+
+    /// Appends a typed character. Growing moves the text into a fresh buffer
+    /// so the old allocation is zeroed instead of left behind by `realloc`.
+    pub(crate) fn push(&mut self, ch: char) {
+        let needed = self.0.len() + ch.len_utf8();
+        if needed > self.0.capacity() {
+            let mut grown = Zeroizing::new(String::with_capacity(needed.max(32) * 2));
+            grown.push_str(&self.0);
+            self.0 = grown;
+        }
+        self.0.push(ch);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<char> {
+        self.0.pop()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::from(value.to_string())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose())
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum SecretSource {
+    Stored(Secret),
+    Command { command: String },
+    Ask { ask: AskPolicy },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum AskPolicy {
+    EveryTime,
+    WhileConnected,
+}
+
+impl SecretSource {
+    pub(crate) fn wipe(&mut self) {
+        match self {
+            SecretSource::Stored(secret) => secret.clear(),
+            SecretSource::Command { .. } | SecretSource::Ask { .. } => {}
+        }
+    }
+}
+
+impl From<Secret> for SecretSource {
+    fn from(value: Secret) -> Self {
+        Self::Stored(value)
+    }
+}
+
+impl From<&str> for SecretSource {
+    fn from(value: &str) -> Self {
+        Self::Stored(Secret::from(value))
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) struct SecretKey(Zeroizing<Vec<u8>>);
+
+impl SecretKey {
+    pub(crate) fn zeroed(len: usize) -> Self {
+        Self(Zeroizing::new(vec![0u8; len]))
+    }
+
+    pub(crate) fn as_mut_bytes(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_is_redacted() {
+        let secret = Secret::from("hunter2");
+        assert_eq!(format!("{secret:?}"), "<redacted>");
+        assert_eq!(format!("{:?}", Some(secret)), "Some(<redacted>)");
+        assert_eq!(format!("{:?}", SecretKey::zeroed(4)), "<redacted>");
+    }
+
+    #[test]
+    fn editing_keeps_the_text() {
+        let mut secret = Secret::default();
+        for ch in "pässword".chars() {
+            secret.push(ch);
+        }
+        assert_eq!(secret.pop(), Some('d'));
+        assert_eq!(secret.expose(), "pässwor");
+        assert_eq!(secret.char_count(), 7);
+        secret.clear();
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn sources_serialize_compatibly() {
+        let stored = SecretSource::from("hunter2");
+        assert_eq!(serde_json::to_string(&stored).unwrap(), "\"hunter2\"");
+        let command = SecretSource::Command {
+            command: "pass show db".to_string(),
+        };
+        let json = serde_json::to_string(&command).unwrap();
+        assert_eq!(json, r#"{"command":"pass show db"}"#);
+        assert_eq!(
+            serde_json::from_str::<SecretSource>(&json).unwrap(),
+            command
+        );
+        assert_eq!(
+            serde_json::from_str::<SecretSource>("\"hunter2\"").unwrap(),
+            stored
+        );
+        let ask = SecretSource::Ask {
+            ask: AskPolicy::WhileConnected,
+        };
+        let json = serde_json::to_string(&ask).unwrap();
+        assert_eq!(json, r#"{"ask":"while-connected"}"#);
+        assert_eq!(serde_json::from_str::<SecretSource>(&json).unwrap(), ask);
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::{self, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use zeroize::Zeroizing;
+
+use crate::secret::Secret;
+
+const TIMEOUT_ENV: &str = "SS_SSH_SECRET_COMMAND_TIMEOUT_SECS";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const MAX_OUTPUT_BYTES: u64 = 64 * 1024;
+const MAX_STDERR_CHARS: usize = 300;
+
+fn command_timeout() -> Duration {
+    std::env::var(TIMEOUT_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .map_or(DEFAULT_TIMEOUT, Duration::from_secs)
+}
+
+pub(crate) fn run_secret_command(command: &str) -> Result<Secret> {
+    run_with_timeout(command, command_timeout())
+}
+
+fn run_with_timeout(command: &str, timeout: Duration) -> Result<Secret> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not run `{command}`"))?;
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let Some(status) = wait_with_timeout(&mut child, timeout)? else {
+        // The readers are left behind: a grandchild may still hold the pipes open.
+        anyhow::bail!("`{command}` timed out after {}s", timeout.as_secs());
+    };
+    let output = stdout.join().unwrap_or_default();
+    let errors = stderr.join().unwrap_or_default();
+    let errors = String::from_utf8_lossy(&errors);
+    let errors = errors.trim();
+    let detail = if errors.is_empty() {
+        String::new()
+    } else {
+        format!(
+            ": {}",
+            errors.chars().take(MAX_STDERR_CHARS).collect::<String>()
+        )
+    };
+    if !status.success() {
+        anyhow::bail!("`{command}` failed ({status}){detail}");
+    }
+    let line = output
+        .split(|byte| *byte == b'\n')
+        .next()
+        .unwrap_or_default();
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let Ok(secret) = std::str::from_utf8(line) else {
+        anyhow::bail!("`{command}` printed something that is not UTF-8");
+    };
+    if secret.is_empty() {
+        anyhow::bail!("`{command}` printed nothing{detail}");
+    }
+    Ok(Secret::from(secret))
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Zeroizing<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Zeroizing::new(Vec::with_capacity(MAX_OUTPUT_BYTES as usize));
+        if let Some(mut pipe) = pipe {
+            let _ = pipe
+                .by_ref()
+                .take(MAX_OUTPUT_BYTES)
+                .read_to_end(&mut buffer);
+            // The rest is discarded but still read, so the command never blocks on a full pipe.
+            let _ = io::copy(&mut pipe, &mut io::sink());
+        }
+        buffer
+    })
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().context("wait for secret command")? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_the_first_line_of_stdout() {
+        let secret = run_secret_command("printf 'hunter2\\nurl: db1\\n'").unwrap();
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn failures_show_stderr() {
+        let err = run_secret_command("echo 'entry not found' >&2; exit 1").unwrap_err();
+        assert!(err.to_string().contains("entry not found"), "{err}");
+        let err = run_secret_command("true").unwrap_err();
+        assert!(err.to_string().contains("printed nothing"), "{err}");
+    }
+
+    #[test]
+    fn long_output_is_drained_not_mistaken_for_a_hang() {
+        let secret = run_with_timeout(
+            "printf 'hunter2\\n'; head -c 1000000 /dev/zero",
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn slow_commands_time_out() {
+        let started = Instant::now();
+        let err = run_with_timeout("sleep 5", Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,290 @@
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use anyhow::{Context, Result};
+use ssh2::ErrorCode;
+
+const SOCKS4_VERSION: u8 = 4;
+const SOCKS5_VERSION: u8 = 5;
+const CMD_CONNECT: u8 = 1;
+const SOCKS5_NO_AUTH: u8 = 0x00;
+const SOCKS5_NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const SOCKS4_GRANTED: u8 = 0x5a;
+const SOCKS4_REJECTED: u8 = 0x5b;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+const LIBSSH2_ERROR_TIMEOUT: i32 = -9;
+const LIBSSH2_ERROR_CHANNEL_FAILURE: i32 = -21;
+const LIBSSH2_ERROR_SOCKET_TIMEOUT: i32 = -30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SocksVersion {
+    V4,
+    V5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SocksReply {
+    Succeeded = 0x00,
+    GeneralFailure = 0x01,
+    HostUnreachable = 0x04,
+    ConnectionRefused = 0x05,
+    CommandNotSupported = 0x07,
+    AddressTypeNotSupported = 0x08,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SocksRequest {
+    pub(crate) version: SocksVersion,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+}
+
+pub(crate) fn read_request(stream: &mut (impl Read + Write)) -> Result<SocksRequest> {
+    match read_u8(stream)? {
+        SOCKS5_VERSION => read_socks5_request(stream),
+        SOCKS4_VERSION => read_socks4_request(stream),
+        version => anyhow::bail!("unsupported SOCKS version {version}"),
+    }
+}
+
+pub(crate) fn write_reply(
+    stream: &mut impl Write,
+    version: SocksVersion,
+    reply: SocksReply,
+) -> Result<()> {
+    let bytes: &[u8] = match version {
+        SocksVersion::V5 => &[SOCKS5_VERSION, reply as u8, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0],
+        SocksVersion::V4 => {
+            let code = if reply == SocksReply::Succeeded {
+                SOCKS4_GRANTED
+            } else {
+                SOCKS4_REJECTED
+            };
+            &[0, code, 0, 0, 0, 0, 0, 0]
+        }
+    };
+    stream.write_all(bytes).context("write SOCKS reply")
+}
+
+/// Maps why a `direct-tcpip` channel could not be opened to the closest reply
+/// code. libssh2 reports every refusal by the server (prohibited, connect
+/// failed, unknown host) as the same channel failure, answered as refused.
+pub(crate) fn reply_for_channel_error(err: &ssh2::Error) -> SocksReply {
+    match err.code() {
+        ErrorCode::Session(LIBSSH2_ERROR_CHANNEL_FAILURE) => SocksReply::ConnectionRefused,
+        ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT | LIBSSH2_ERROR_SOCKET_TIMEOUT) => {
+            SocksReply::HostUnreachable
+        }
+        _ => SocksReply::GeneralFailure,
+    }
+}
+
+fn read_socks5_request(stream: &mut (impl Read + Write)) -> Result<SocksRequest> {
+    let method_count = read_u8(stream)?;
+    let mut methods = vec![0u8; method_count.into()];
+    stream
+        .read_exact(&mut methods)
+        .context("read SOCKS5 methods")?;
+    if !methods.contains(&SOCKS5_NO_AUTH) {
+        stream
+            .write_all(&[SOCKS5_VERSION, SOCKS5_NO_ACCEPTABLE_METHOD])
+            .context("write SOCKS5 method")?;
+        anyhow::bail!("SOCKS5 client requires authentication");
+    }
+    stream
+        .write_all(&[SOCKS5_VERSION, SOCKS5_NO_AUTH])
+        .context("write SOCKS5 method")?;
+
+    let mut header = [0u8; 4];
+    stream
+        .read_exact(&mut header)
+        .context("read SOCKS5 request")?;
+    let [_, command, _, address_type] = header;
+    if command != CMD_CONNECT {
+        write_reply(stream, SocksVersion::V5, SocksReply::CommandNotSupported)?;
+        anyhow::bail!("unsupported SOCKS5 command {command}");
+    }
+    let host = match address_type {
+        ATYP_IPV4 => {
+            let mut octets = [0u8; 4];
+            stream
+                .read_exact(&mut octets)
+                .context("read SOCKS5 address")?;
+            Ipv4Addr::from(octets).to_string()
+        }
+        ATYP_IPV6 => {
+            let mut octets = [0u8; 16];
+            stream
+                .read_exact(&mut octets)
+                .context("read SOCKS5 address")?;
+            Ipv6Addr::from(octets).to_string()
+        }
+        ATYP_DOMAIN => {
+            let mut name = vec![0u8; read_u8(stream)?.into()];
+            stream
+                .read_exact(&mut name)
+                .context("read SOCKS5 address")?;
+            String::from_utf8(name).context("SOCKS5 host name is not UTF-8")?
+        }
+        _ => {
+            write_reply(
+                stream,
+                SocksVersion::V5,
+                SocksReply::AddressTypeNotSupported,
+            )?;
+            anyhow::bail!("unsupported SOCKS5 address type {address_type}");
+        }
+    };
+    let port = read_port(stream)?;
+    Ok(SocksRequest {
+        version: SocksVersion::V5,
+        host,
+        port,
+    })
+}
+
+fn read_socks4_request(stream: &mut (impl Read + Write)) -> Result<SocksRequest> {
+    let command = read_u8(stream)?;
+    let port = read_port(stream)?;
+    let mut octets = [0u8; 4];
+    stream
+        .read_exact(&mut octets)
+        .context("read SOCKS4 address")?;
+    read_nul_terminated(stream)?;
+    if command != CMD_CONNECT {
+        write_reply(stream, SocksVersion::V4, SocksReply::CommandNotSupported)?;
+        anyhow::bail!("unsupported SOCKS4 command {command}");
+    }
+    // SOCKS4a: 0.0.0.x with x != 0 means a host name follows.
+    let host = if octets[..3] == [0, 0, 0] && octets[3] != 0 {
+        String::from_utf8(read_nul_terminated(stream)?).context("SOCKS4a host name is not UTF-8")?
+    } else {
+        Ipv4Addr::from(octets).to_string()
+    };
+    Ok(SocksRequest {
+        version: SocksVersion::V4,
+        host,
+        port,
+    })
+}
+
+fn read_u8(stream: &mut impl Read) -> Result<u8> {
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte).context("read SOCKS request")?;
+    Ok(byte[0])
+}
+
+fn read_port(stream: &mut impl Read) -> Result<u16> {
+    let mut port = [0u8; 2];
+    stream.read_exact(&mut port).context("read SOCKS port")?;
+    Ok(u16::from_be_bytes(port))
+}
+
+fn read_nul_terminated(stream: &mut impl Read) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match read_u8(stream)? {
+            0 => return Ok(bytes),
+            _ if bytes.len() >= u8::MAX.into() => anyhow::bail!("SOCKS4 field too long"),
+            byte => bytes.push(byte),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    struct Client {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Client {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Client {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Client {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn socks5_connect_by_domain() {
+        let mut client = Client::new(&[
+            5, 1, 0, 5, 1, 0, 3, 11, b'i', b'n', b't', b'r', b'a', b'.', b'l', b'o', b'c', b'a',
+            b'l', 0x01, 0xbb,
+        ]);
+        let request = read_request(&mut client).unwrap();
+        assert_eq!(
+            request,
+            SocksRequest {
+                version: SocksVersion::V5,
+                host: "intra.local".to_string(),
+                port: 443,
+            }
+        );
+        assert_eq!(client.output, [5, 0]);
+    }
+
+    #[test]
+    fn socks5_rejects_bind_and_auth_only_clients() {
+        let mut bind = Client::new(&[5, 1, 0, 5, 2, 0, 1, 10, 0, 0, 1, 0, 80]);
+        assert!(read_request(&mut bind).is_err());
+        assert_eq!(bind.output, [5, 0, 5, 7, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        let mut auth = Client::new(&[5, 1, 2]);
+        assert!(read_request(&mut auth).is_err());
+        assert_eq!(auth.output, [5, 0xff]);
+    }
+
+    #[test]
+    fn socks4a_reads_host_name_after_user_id() {
+        let mut client = Client::new(&[
+            4, 1, 0x1f, 0x90, 0, 0, 0, 1, b'm', b'e', 0, b'w', b'i', b'k', b'i', 0,
+        ]);
+        let request = read_request(&mut client).unwrap();
+        assert_eq!(request.version, SocksVersion::V4);
+        assert_eq!((request.host.as_str(), request.port), ("wiki", 8080));
+
+        let mut reply = Vec::new();
+        write_reply(&mut reply, SocksVersion::V4, SocksReply::ConnectionRefused).unwrap();
+        assert_eq!(reply, [0, SOCKS4_REJECTED, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn channel_errors_map_to_reply_codes() {
+        let error = |code| ssh2::Error::new(ErrorCode::Session(code), "Channel open failure");
+        assert_eq!(
+            reply_for_channel_error(&error(LIBSSH2_ERROR_CHANNEL_FAILURE)),
+            SocksReply::ConnectionRefused
+        );
+        assert_eq!(
+            reply_for_channel_error(&error(LIBSSH2_ERROR_SOCKET_TIMEOUT)),
+            SocksReply::HostUnreachable
+        );
+        assert_eq!(
+            reply_for_channel_error(&error(-13)),
+            SocksReply::GeneralFailure
+        );
+    }
+}
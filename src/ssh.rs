@@ -1,23 +1,185 @@
 use std::fs::File;
 use std::io::{self, Read, Write};
 use std::net::{TcpStream, ToSocketAddrs};
+use std::ops::Deref;
+use std::os::unix::net::UnixStream;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::Duration;
 
 use anyhow::{Context, Result};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use ssh2::Session;
+use ssh2::{Channel, Session};
 
-use crate::model::{AuthConfig, ConnectionConfig};
+use crate::auth_prompt::{AuthPromptRequest, ChannelPrompter, ask_secret};
+use crate::model::{AuthConfig, ConnectionConfig, connection_key, format_host_port};
+use crate::secret::{AskPolicy, Secret, SecretSource};
+use crate::secret_command::run_secret_command;
 
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+pub(crate) const TUNNEL_BUFFER_BYTES: usize = 32 * 1024;
+pub(crate) const TUNNEL_IDLE_SLEEP: Duration = Duration::from_millis(2);
+const LIBSSH2_ERROR_CHANNEL_FAILURE: i32 = -21;
 
-pub(crate) fn connect_ssh(config: &ConnectionConfig) -> Result<Session> {
-    let address = format!("{}:22", config.host);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConnectPhase {
+    Resolve,
+    Tcp,
+    Tunnel,
+    Handshake,
+    Auth,
+}
+
+impl ConnectPhase {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ConnectPhase::Resolve => "resolving",
+            ConnectPhase::Tcp => "TCP connect",
+            ConnectPhase::Tunnel => "opening tunnel",
+            ConnectPhase::Handshake => "handshake",
+            ConnectPhase::Auth => "authenticating",
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ConnectHooks {
+    pub(crate) prompts: Option<Sender<AuthPromptRequest>>,
+    pub(crate) phases: Option<Sender<ConnectPhase>>,
+    pub(crate) cancel: Option<Arc<AtomicBool>>,
+    pub(crate) answers: Option<Sender<(String, Secret)>>,
+}
+
+impl ConnectHooks {
+    fn check_cancelled(&self) -> Result<()> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+        {
+            anyhow::bail!("connect cancelled");
+        }
+        Ok(())
+    }
+
+    fn enter(&self, phase: ConnectPhase) -> Result<()> {
+        self.check_cancelled()?;
+        if let Some(phases) = &self.phases {
+            let _ = phases.send(phase);
+        }
+        Ok(())
+    }
+}
+
+/// The session of an open connection, shared by the UI, keepalive probes and
+/// the forward pump. The pump drives its channels in short non-blocking
+/// passes; everyone else borrows the session through [`SharedSession::lock`],
+/// which waits for the current pass and hands it out in blocking mode.
+#[derive(Clone)]
+pub(crate) struct SharedSession {
+    session: Session,
+    gate: Arc<Mutex<()>>,
+}
+
+impl SharedSession {
+    pub(crate) fn new(session: Session) -> Self {
+        Self {
+            session,
+            gate: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> SessionGuard<'_> {
+        SessionGuard {
+            session: &self.session,
+            _gate: self.gate.lock().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+}
+
+pub(crate) struct SessionGuard<'a> {
+    session: &'a Session,
+    _gate: MutexGuard<'a, ()>,
+}
+
+impl Deref for SessionGuard<'_> {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        self.session
+    }
+}
+
+impl Drop for SessionGuard<'_> {
+    fn drop(&mut self) {
+        self.session.set_blocking(true);
+    }
+}
+
+pub(crate) fn connect_ssh_with_hooks(
+    config: &ConnectionConfig,
+    hooks: &ConnectHooks,
+) -> Result<Session> {
+    let mut outer = None;
+    for hop in &config.via {
+        let session = open_session(hop, outer.take(), hooks)
+            .with_context(|| format!("jump host {}", hop.label()))?;
+        outer = Some(session);
+    }
+    open_session(config, outer, hooks)
+}
+
+fn open_session(
+    config: &ConnectionConfig,
+    outer: Option<Session>,
+    hooks: &ConnectHooks,
+) -> Result<Session> {
+    let mut session = Session::new().context("create session")?;
+    session.set_timeout(CONNECT_TIMEOUT.as_millis() as u32);
+    match outer {
+        Some(outer) => {
+            hooks.enter(ConnectPhase::Tunnel)?;
+            session.set_tcp_stream(open_tunnel(outer, &config.host, config.port)?)
+        }
+        None => session.set_tcp_stream(connect_tcp(config, hooks)?),
+    }
+    hooks.enter(ConnectPhase::Handshake)?;
+    session.handshake().context("ssh handshake")?;
+    crate::known_hosts::verify_host_key(&session, &config.host, config.port)?;
+
+    hooks.enter(ConnectPhase::Auth)?;
+    authenticate(&session, config, hooks)?;
+    hooks.check_cancelled()?;
+    session.set_keepalive(true, config.keepalive.interval_secs);
+    Ok(session)
+}
+
+pub(crate) fn probe_session(session: &Session) -> bool {
+    if session.keepalive_send().is_err() {
+        return false;
+    }
+    match session.channel_session() {
+        Ok(mut channel) => {
+            let _ = channel.close();
+            true
+        }
+        // The server refused the channel, which still proves it is there.
+        Err(err) => err.code() == ssh2::ErrorCode::Session(LIBSSH2_ERROR_CHANNEL_FAILURE),
+    }
+}
+
+fn connect_tcp(config: &ConnectionConfig, hooks: &ConnectHooks) -> Result<TcpStream> {
     let mut last_err = None;
     let mut tcp = None;
-    for addr in address.to_socket_addrs().context("resolve address")? {
+    hooks.enter(ConnectPhase::Resolve)?;
+    let addrs = (config.host.as_str(), config.port)
+        .to_socket_addrs()
+        .context("resolve address")?;
+    for addr in addrs {
+        hooks.enter(ConnectPhase::Tcp)?;
         match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
             Ok(stream) => {
                 tcp = Some(stream);
@@ -33,48 +195,297 @@ pub(crate) fn connect_ssh(config: &ConnectionConfig) -> Result<Session> {
     })?;
     tcp.set_read_timeout(Some(CONNECT_TIMEOUT)).ok();
     tcp.set_write_timeout(Some(CONNECT_TIMEOUT)).ok();
+    Ok(tcp)
+}
 
-    let mut session = Session::new().context("create session")?;
-    session.set_timeout(CONNECT_TIMEOUT.as_millis() as u32);
-    session.set_tcp_stream(tcp);
-    session.handshake().context("ssh handshake")?;
+fn open_tunnel(outer: Session, host: &str, port: u16) -> Result<UnixStream> {
+    let channel = outer
+        .channel_direct_tcpip(host, port, None)
+        .with_context(|| format!("open tunnel to {}", format_host_port(host, port)))?;
+    let (local, remote) = UnixStream::pair().context("create tunnel socket")?;
+    remote
+        .set_nonblocking(true)
+        .context("configure tunnel socket")?;
+    outer.set_blocking(false);
+    std::thread::spawn(move || pump_tunnel(outer, channel, remote));
+    Ok(local)
+}
 
-    match &config.auth {
+fn pump_tunnel(outer: Session, channel: Channel, socket: UnixStream) {
+    let mut buffer = [0u8; TUNNEL_BUFFER_BYTES];
+    let mut pipe = Pipe::new(channel, socket);
+    loop {
+        match pipe.pump(&mut buffer) {
+            PipeState::Closed => break,
+            PipeState::Busy => {}
+            PipeState::Idle => {
+                // libssh2 only sends once the hop's keepalive interval has passed.
+                let _ = outer.keepalive_send();
+                std::thread::sleep(TUNNEL_IDLE_SLEEP);
+            }
+        }
+    }
+    pipe.close();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PipeState {
+    Idle,
+    Busy,
+    Closed,
+}
+
+pub(crate) struct Pipe<S> {
+    channel: Channel,
+    socket: S,
+    to_socket: Vec<u8>,
+    to_channel: Vec<u8>,
+    pub(crate) sent: u64,
+    pub(crate) received: u64,
+}
+
+impl<S: Read + Write> Pipe<S> {
+    pub(crate) fn new(channel: Channel, socket: S) -> Self {
+        Self {
+            channel,
+            socket,
+            to_socket: Vec::new(),
+            to_channel: Vec::new(),
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    pub(crate) fn pump(&mut self, buffer: &mut [u8]) -> PipeState {
+        let mut state = PipeState::Idle;
+        if self.to_socket.is_empty() {
+            match self.channel.read(buffer) {
+                Ok(0) if self.channel.eof() => return PipeState::Closed,
+                Ok(0) => {}
+                Ok(count) => {
+                    self.to_socket.extend_from_slice(&buffer[..count]);
+                    state = PipeState::Busy;
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => return PipeState::Closed,
+            }
+        }
+        if self.to_channel.is_empty() {
+            match self.socket.read(buffer) {
+                Ok(0) => return PipeState::Closed,
+                Ok(count) => {
+                    self.to_channel.extend_from_slice(&buffer[..count]);
+                    state = PipeState::Busy;
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => return PipeState::Closed,
+            }
+        }
+        if !self.to_socket.is_empty() {
+            match self.socket.write(&self.to_socket) {
+                Ok(count) => {
+                    self.to_socket.drain(..count);
+                    self.received += count as u64;
+                    state = PipeState::Busy;
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => return PipeState::Closed,
+            }
+        }
+        if !self.to_channel.is_empty() {
+            match self.channel.write(&self.to_channel) {
+                Ok(count) => {
+                    self.to_channel.drain(..count);
+                    self.sent += count as u64;
+                    state = PipeState::Busy;
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => return PipeState::Closed,
+            }
+        }
+        state
+    }
+
+    pub(crate) fn close(mut self) {
+        let _ = self.channel.close();
+    }
+}
+
+fn authenticate(session: &Session, config: &ConnectionConfig, hooks: &ConnectHooks) -> Result<()> {
+    let prompts = hooks.prompts.as_ref();
+    let offered = session
+        .auth_methods(&config.user)
+        .map(str::to_string)
+        .unwrap_or_default();
+    if session.authenticated() {
+        return Ok(());
+    }
+    let result = match &config.auth {
         AuthConfig::Password { password } => {
+            let password = secret_for(password, config, "Password", hooks)?;
             session
-                .userauth_password(&config.user, password)
-                .context("password auth")?;
+                .userauth_password(&config.user, password.expose())
+                .context("password auth")
         }
         AuthConfig::PrivateKey { path, password } => {
             let path = expand_tilde(path);
             if !path.exists() {
                 anyhow::bail!("Private key not found at {}", path.display());
             }
+            let password = password
+                .as_ref()
+                .map(|source| secret_for(source, config, "Key passphrase", hooks))
+                .transpose()?;
             session
-                .userauth_pubkey_file(&config.user, None, &path, password.as_deref())
-                .context("private key auth")?;
+                .userauth_pubkey_file(
+                    &config.user,
+                    None,
+                    &path,
+                    password.as_ref().map(Secret::expose),
+                )
+                .context("private key auth")
+        }
+        AuthConfig::Agent { identity } => {
+            authenticate_with_agent(session, &config.user, identity.as_deref())
         }
+        AuthConfig::KeyboardInteractive => keyboard_interactive(session, config, prompts),
+    };
+    if session.authenticated() {
+        return Ok(());
+    }
+    // libssh2 reports a partial success as a plain failure, but the server then
+    // narrows the list of methods it still accepts.
+    let remaining = session
+        .auth_methods(&config.user)
+        .map(str::to_string)
+        .unwrap_or_default();
+    if !matches!(config.auth, AuthConfig::KeyboardInteractive)
+        && is_partial_success(&offered, &remaining)
+    {
+        keyboard_interactive(session, config, prompts)?;
+    } else {
+        result?;
     }
-
     if !session.authenticated() {
         anyhow::bail!("Authentication failed");
     }
+    Ok(())
+}
+
+fn secret_for(
+    source: &SecretSource,
+    config: &ConnectionConfig,
+    label: &str,
+    hooks: &ConnectHooks,
+) -> Result<Secret> {
+    let ask = match source {
+        SecretSource::Stored(secret) => return Ok(secret.clone()),
+        SecretSource::Command { command } => return run_secret_command(command),
+        SecretSource::Ask { ask } => ask,
+    };
+    let Some(prompts) = &hooks.prompts else {
+        anyhow::bail!(
+            "{label} for {} is asked for, which needs an interactive prompt",
+            config.label()
+        );
+    };
+    let note = match ask {
+        AskPolicy::EveryTime => "Not stored; asked for every time.",
+        AskPolicy::WhileConnected => "Not stored; kept in memory until you disconnect.",
+    };
+    let Some(secret) = ask_secret(prompts, config.label(), label, note) else {
+        anyhow::bail!("Authentication cancelled");
+    };
+    if *ask == AskPolicy::WhileConnected
+        && let Some(answers) = &hooks.answers
+    {
+        let _ = answers.send((connection_key(config), secret.clone()));
+    }
+    Ok(secret)
+}
 
-    Ok(session)
+fn is_partial_success(offered: &str, remaining: &str) -> bool {
+    !offered.is_empty()
+        && offered != remaining
+        && remaining
+            .split(',')
+            .any(|method| method.trim() == "keyboard-interactive")
 }
 
-pub(crate) fn remote_size(session: &Session, path: &str, is_dir: bool) -> Result<u64> {
-    if let Ok(Some(size)) = remote_size_via_du(session, path) {
-        return Ok(size);
+fn keyboard_interactive(
+    session: &Session,
+    config: &ConnectionConfig,
+    prompts: Option<&Sender<AuthPromptRequest>>,
+) -> Result<()> {
+    let Some(prompts) = prompts else {
+        anyhow::bail!("Keyboard-interactive auth needs an interactive prompt");
+    };
+    let mut prompter = ChannelPrompter::new(config.label(), prompts.clone());
+    let result = session.userauth_keyboard_interactive(&config.user, &mut prompter);
+    if prompter.cancelled() {
+        anyhow::bail!("Authentication cancelled");
     }
-    let sftp = session.sftp().context("open sftp")?;
-    if !is_dir {
-        let stat = sftp.stat(Path::new(path)).context("stat remote file")?;
-        return Ok(stat.size.unwrap_or(0));
+    result.context("keyboard-interactive auth")
+}
+
+fn authenticate_with_agent(session: &Session, user: &str, identity: Option<&str>) -> Result<()> {
+    if std::env::var_os("SSH_AUTH_SOCK").is_none() {
+        anyhow::bail!("SSH_AUTH_SOCK is not set, is ssh-agent running?");
     }
-    fn walk(sftp: &ssh2::Sftp, path: &str) -> Result<u64> {
+    let Some(identity) = identity else {
+        return session.userauth_agent(user).context("agent auth");
+    };
+    let mut agent = session.agent().context("init agent")?;
+    agent.connect().context("connect to ssh-agent")?;
+    agent.list_identities().context("list agent identities")?;
+    let key = agent
+        .identities()
+        .context("read agent identities")?
+        .into_iter()
+        .find(|key| agent_identity_matches(identity, key.comment(), key.blob()))
+        .ok_or_else(|| anyhow::anyhow!("No agent identity matches {identity}"))?;
+    let result = agent.userauth(user, &key).context("agent auth");
+    agent.disconnect().ok();
+    result
+}
+
+pub(crate) fn list_agent_identities() -> Result<Vec<(String, String)>> {
+    let session = Session::new().context("create session")?;
+    let mut agent = session.agent().context("init agent")?;
+    agent.connect().context("connect to ssh-agent")?;
+    agent.list_identities().context("list agent identities")?;
+    let identities = agent
+        .identities()
+        .context("read agent identities")?
+        .iter()
+        .map(|key| {
+            (
+                key.comment().to_string(),
+                crate::known_hosts::fingerprint_sha256(key.blob()),
+            )
+        })
+        .collect();
+    agent.disconnect().ok();
+    Ok(identities)
+}
+
+pub(crate) fn agent_identity_matches(identity: &str, comment: &str, blob: &[u8]) -> bool {
+    let identity = identity.trim();
+    if identity == comment {
+        return true;
+    }
+    let fingerprint = crate::known_hosts::fingerprint_sha256(blob);
+    identity == fingerprint || fingerprint.strip_prefix("SHA256:") == Some(identity)
+}
+
+pub(crate) fn remote_size(session: &SharedSession, path: &str, is_dir: bool) -> Result<u64> {
+    fn walk(session: &SharedSession, sftp: &ssh2::Sftp, path: &str) -> Result<u64> {
         let mut total = 0u64;
-        for (child, stat) in sftp.readdir(Path::new(path)).context("read remote dir")? {
+        let entries = {
+            let _session = session.lock();
+            sftp.readdir(Path::new(path)).context("read remote dir")?
+        };
+        for (child, stat) in entries {
             let name = child
                 .file_name()
                 .map(|s| s.to_string_lossy().into_owned())
@@ -89,14 +500,28 @@ pub(crate) fn remote_size(session: &Session, path: &str, is_dir: bool) -> Result
             };
             let is_dir = stat.perm.unwrap_or(0) & 0o040000 != 0;
             if is_dir {
-                total = total.saturating_add(walk(sftp, &child_path)?);
+                total = total.saturating_add(walk(session, sftp, &child_path)?);
             } else {
                 total = total.saturating_add(stat.size.unwrap_or(0));
             }
         }
         Ok(total)
     }
-    walk(&sftp, path)
+    if let Ok(Some(size)) = remote_size_via_du(&session.lock(), path) {
+        return Ok(size);
+    }
+    let sftp = session.lock().sftp().context("open sftp")?;
+    let size = if is_dir {
+        walk(session, &sftp, path)
+    } else {
+        let _session = session.lock();
+        sftp.stat(Path::new(path))
+            .context("stat remote file")
+            .map(|stat| stat.size.unwrap_or(0))
+    };
+    let _session = session.lock();
+    drop(sftp);
+    size
 }
 
 pub(crate) fn remote_home_dir(session: &Session) -> Result<String> {
@@ -366,6 +791,35 @@ mod tests {
         assert_eq!(terminal_key_bytes(key), Some(b"\x1b[A".to_vec()));
     }
 
+    #[test]
+    fn partial_success_requires_narrowed_keyboard_interactive() {
+        assert!(is_partial_success("publickey", "keyboard-interactive"));
+        assert!(is_partial_success(
+            "publickey,password",
+            "password,keyboard-interactive"
+        ));
+        assert!(!is_partial_success(
+            "publickey,keyboard-interactive",
+            "publickey,keyboard-interactive"
+        ));
+        assert!(!is_partial_success("publickey", "password"));
+        assert!(!is_partial_success("", "keyboard-interactive"));
+    }
+
+    #[test]
+    fn agent_identity_matches_comment_or_fingerprint() {
+        let blob = b"key-blob";
+        let fingerprint = crate::known_hosts::fingerprint_sha256(blob);
+        assert!(agent_identity_matches("me@laptop", "me@laptop", blob));
+        assert!(agent_identity_matches(&fingerprint, "me@laptop", blob));
+        assert!(agent_identity_matches(
+            fingerprint.trim_start_matches("SHA256:"),
+            "me@laptop",
+            blob
+        ));
+        assert!(!agent_identity_matches("other", "me@laptop", blob));
+    }
+
     #[test]
     fn terminal_key_bytes_ctrl() {
         let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::model::{
+    AuthConfig, ConnectionConfig, DEFAULT_SSH_PORT, ForwardSpec, bind_prefix, bracket_host,
+    jump_reference,
+};
+use crate::ssh::expand_tilde;
+
+const MAX_INCLUDE_DEPTH: usize = 16;
+const MANAGED_BEGIN: &str = "# BEGIN ss-ssh managed block";
+const MANAGED_END: &str = "# END ss-ssh managed block";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SshHostEntry {
+    pub(crate) alias: String,
+    pub(crate) host_name: String,
+    pub(crate) user: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) identity_file: Option<String>,
+    pub(crate) proxy_jump: Option<String>,
+}
+
+struct HostBlock {
+    patterns: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+pub(crate) fn ssh_config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh").join("config"))
+}
+
+pub(crate) fn default_export_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh").join("config.d").join("ss-ssh"))
+}
+
+pub(crate) fn export_ssh_config(path: &Path, connections: &[ConnectionConfig]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+    };
+    let content = replace_managed_block(&existing, &render_host_blocks(connections));
+    fs::write(path, content).with_context(|| format!("write {}", path.display()))
+}
+
+fn render_host_blocks(connections: &[ConnectionConfig]) -> String {
+    let aliases = host_aliases(connections);
+    let mut out = String::new();
+    for (conn, alias) in connections.iter().zip(&aliases) {
+        out.push_str(&format!("Host {alias}\n"));
+        out.push_str(&format!("    HostName {}\n", conn.host));
+        out.push_str(&format!("    User {}\n", quote_arg(&conn.user)));
+        if conn.port != DEFAULT_SSH_PORT {
+            out.push_str(&format!("    Port {}\n", conn.port));
+        }
+        match &conn.auth {
+            AuthConfig::PrivateKey { path, .. } => {
+                out.push_str(&format!("    IdentityFile {}\n", quote_arg(path)));
+            }
+            AuthConfig::KeyboardInteractive => {
+                out.push_str("    PreferredAuthentications keyboard-interactive\n");
+            }
+            AuthConfig::Password { .. } | AuthConfig::Agent { .. } => {}
+        }
+        let jump_alias = conn.jump_host.as_ref().and_then(|reference| {
+            connections
+                .iter()
+                .position(|other| jump_reference(other) == *reference)
+                .map(|index| &aliases[index])
+        });
+        if let Some(jump_alias) = jump_alias {
+            out.push_str(&format!("    ProxyJump {jump_alias}\n"));
+        }
+        for forward in &conn.forwards {
+            out.push_str(&format!("    {}\n", forward_directive(forward)));
+        }
+    }
+    out
+}
+
+fn forward_directive(forward: &ForwardSpec) -> String {
+    match forward {
+        ForwardSpec::Local {
+            bind_address,
+            local_port,
+            remote_host,
+            remote_port,
+        } => {
+            let bind = bind_prefix(bind_address);
+            format!(
+                "LocalForward {bind}{local_port} {}:{remote_port}",
+                bracket_host(remote_host)
+            )
+        }
+        ForwardSpec::Remote {
+            bind_address,
+            remote_port,
+            local_host,
+            local_port,
+        } => {
+            let bind = bind_prefix(bind_address);
+            format!(
+                "RemoteForward {bind}{remote_port} {}:{local_port}",
+                bracket_host(local_host)
+            )
+        }
+        ForwardSpec::Dynamic { bind_address, port } => {
+            format!("DynamicForward {}{port}", bind_prefix(bind_address))
+        }
+    }
+}
+
+fn host_aliases(connections: &[ConnectionConfig]) -> Vec<String> {
+    let mut aliases: Vec<String> = Vec::with_capacity(connections.len());
+    for conn in connections {
+        let base: String = conn
+            .label()
+            .trim()
+            .chars()
+            .map(|ch| {
+                if ch.is_whitespace() || matches!(ch, '*' | '?' | '!' | ',' | '"' | '#') {
+                    '-'
+                } else {
+                    ch
+                }
+            })
+            .collect();
+        let mut alias = base.clone();
+        let mut suffix = 2;
+        while aliases.contains(&alias) {
+            alias = format!("{base}-{suffix}");
+            suffix += 1;
+        }
+        aliases.push(alias);
+    }
+    aliases
+}
+
+fn quote_arg(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
+
+fn replace_managed_block(existing: &str, hosts: &str) -> String {
+    let block = format!("{MANAGED_BEGIN}\n{hosts}{MANAGED_END}\n");
+    let lines: Vec<&str> = existing.lines().collect();
+    let begin = lines.iter().position(|line| line.trim() == MANAGED_BEGIN);
+    let end = begin.and_then(|begin| {
+        lines[begin..]
+            .iter()
+            .position(|line| line.trim() == MANAGED_END)
+            .map(|offset| begin + offset)
+    });
+    match (begin, end) {
+        (Some(begin), Some(end)) => {
+            let mut out = String::new();
+            for line in &lines[..begin] {
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push_str(&block);
+            for line in &lines[end + 1..] {
+                out.push_str(line);
+                out.push('\n');
+            }
+            out
+        }
+        _ if existing.trim().is_empty() => block,
+        _ => {
+            let mut out = existing.trim_end_matches('\n').to_string();
+            out.push_str("\n\n");
+            out.push_str(&block);
+            out
+        }
+    }
+}
+
+pub(crate) fn load_ssh_config(path: &Path) -> Result<Vec<SshHostEntry>> {
+    let base_dir = path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let content = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let mut blocks = vec![HostBlock {
+        patterns: vec!["*".to_string()],
+        options: vec![],
+    }];
+    parse_into(&content, &base_dir, &mut blocks, 0)?;
+    Ok(resolve_hosts(&blocks))
+}
+
+fn parse_into(
+    content: &str,
+    base_dir: &Path,
+    blocks: &mut Vec<HostBlock>,
+    depth: usize,
+) -> Result<()> {
+    if depth > MAX_INCLUDE_DEPTH {
+        anyhow::bail!("Include nested too deeply");
+    }
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((keyword, value)) = split_directive(line) else {
+            continue;
+        };
+        match keyword.as_str() {
+            "host" => blocks.push(HostBlock {
+                patterns: split_args(value),
+                options: vec![],
+            }),
+            "match" => blocks.push(HostBlock {
+                patterns: vec![],
+                options: vec![],
+            }),
+            "include" => {
+                for pattern in split_args(value) {
+                    for path in expand_include(&pattern, base_dir) {
+                        if let Ok(content) = fs::read_to_string(&path) {
+                            parse_into(&content, base_dir, blocks, depth + 1)?;
+                        }
+                    }
+                }
+            }
+            _ => {
+                if let Some(block) = blocks.last_mut() {
+                    block.options.push((keyword, value.to_string()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn resolve_hosts(blocks: &[HostBlock]) -> Vec<SshHostEntry> {
+    let mut aliases: Vec<&str> = Vec::new();
+    for block in blocks {
+        for pattern in &block.patterns {
+            let concrete = !pattern.contains(['*', '?']) && !pattern.starts_with('!');
+            if concrete && !aliases.contains(&pattern.as_str()) {
+                aliases.push(pattern);
+            }
+        }
+    }
+    aliases
+        .into_iter()
+        .map(|alias| {
+            let mut options: HashMap<&str, &str> = HashMap::new();
+            for block in blocks
+                .iter()
+                .filter(|block| host_matches(&block.patterns, alias))
+            {
+                for (key, value) in &block.options {
+                    options.entry(key.as_str()).or_insert(value.as_str());
+                }
+            }
+            let first_arg = |key: &str| {
+                options
+                    .get(key)
+                    .and_then(|value| split_args(value).into_iter().next())
+            };
+            SshHostEntry {
+                alias: alias.to_string(),
+                host_name: first_arg("hostname")
+                    .map(|name| name.replace("%h", alias))
+                    .unwrap_or_else(|| alias.to_string()),
+                user: first_arg("user"),
+                port: first_arg("port").and_then(|port| port.parse().ok()),
+                identity_file: first_arg("identityfile"),
+                proxy_jump: first_arg("proxyjump")
+                    .filter(|jump| !jump.eq_ignore_ascii_case("none")),
+            }
+        })
+        .collect()
+}
+
+fn host_matches(patterns: &[String], alias: &str) -> bool {
+    let alias = alias.to_ascii_lowercase();
+    let mut matched = false;
+    for pattern in patterns {
+        let pattern = pattern.to_ascii_lowercase();
+        if let Some(negated) = pattern.strip_prefix('!') {
+            if wildcard_match(negated, &alias) {
+                return false;
+            }
+        } else if wildcard_match(&pattern, &alias) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+fn split_directive(line: &str) -> Option<(String, &str)> {
+    let end = line.find(|ch: char| ch.is_whitespace() || ch == '=')?;
+    let keyword = line[..end].to_ascii_lowercase();
+    let value = line[end..].trim_start();
+    let value = value.strip_prefix('=').unwrap_or(value).trim();
+    Some((keyword, value))
+}
+
+fn split_args(value: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted = !quoted,
+            ch if ch.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            ch => current.push(ch),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+    args
+}
+
+fn expand_include(pattern: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let path = expand_tilde(pattern);
+    let path = if path.is_absolute() {
+        path
+    } else {
+        base_dir.join(path)
+    };
+    let Some(file_pattern) = path.file_name().and_then(|name| name.to_str()) else {
+        return vec![];
+    };
+    if !file_pattern.contains(['*', '?']) {
+        return vec![path];
+    }
+    let Some(dir) = path.parent() else {
+        return vec![];
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| wildcard_match(file_pattern, name))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Vec<SshHostEntry> {
+        let mut blocks = vec![HostBlock {
+            patterns: vec!["*".to_string()],
+            options: vec![],
+        }];
+        parse_into(content, Path::new("."), &mut blocks, 0).unwrap();
+        resolve_hosts(&blocks)
+    }
+
+    #[test]
+    fn resolves_hosts_with_wildcard_defaults() {
+        let entries = parse(
+            "Host web db\n  HostName %h.internal\n  Port=2222\n\n\
+             Host bastion\n  HostName bastion.example.com\n  IdentityFile \"~/.ssh/id work\"\n\n\
+             Host *.internal !db\n  User nobody\n\n\
+             Host *\n  User admin\n  ProxyJump bastion\n",
+        );
+        let aliases: Vec<_> = entries.iter().map(|entry| entry.alias.as_str()).collect();
+        assert_eq!(aliases, vec!["web", "db", "bastion"]);
+        assert_eq!(entries[0].host_name, "web.internal");
+        assert_eq!(entries[0].port, Some(2222));
+        assert_eq!(entries[0].user.as_deref(), Some("admin"));
+        assert_eq!(entries[0].proxy_jump.as_deref(), Some("bastion"));
+        assert_eq!(entries[2].identity_file.as_deref(), Some("~/.ssh/id work"));
+        assert_eq!(entries[2].port, None);
+    }
+
+    #[test]
+    fn first_value_wins_and_proxy_jump_none_clears() {
+        let entries = parse(
+            "User early\nHost app\n  User late\n  ProxyJump none\nHost *\n  ProxyJump edge\n",
+        );
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].user.as_deref(), Some("early"));
+        assert_eq!(entries[0].proxy_jump, None);
+    }
+
+    #[test]
+    fn negated_patterns_exclude_hosts() {
+        assert!(host_matches(&["*.example".to_string()], "a.example"));
+        assert!(!host_matches(
+            &["*.example".to_string(), "!b.example".to_string()],
+            "b.example"
+        ));
+        assert!(wildcard_match("id_*", "id_ed25519"));
+        assert!(!wildcard_match("web?", "web10"));
+    }
+
+    fn conn(name: &str, auth: AuthConfig, jump_host: Option<String>) -> ConnectionConfig {
+        ConnectionConfig {
+            name: name.to_string(),
+            user: "dev".to_string(),
+            host: format!("{}.example", name.replace(' ', "")),
+            port: 2222,
+            auth,
+            history: vec![],
+            last_remote_dir: None,
+            jump_host,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        }
+    }
+
+    #[test]
+    fn exported_hosts_skip_passwords_and_link_jump_hosts() {
+        let bastion = conn(
+            "bastion",
+            AuthConfig::PrivateKey {
+                path: "~/.ssh/id work".to_string(),
+                password: Some("keysecret".into()),
+            },
+            None,
+        );
+        let mut app = conn(
+            "app server",
+            AuthConfig::Password {
+                password: "hunter2".into(),
+            },
+            Some(jump_reference(&bastion)),
+        );
+        app.forwards = crate::model::parse_forwards(
+            "L 5432:db:5432, L [::1]:8080:localhost:80, R 9000:localhost:3000, D 1080",
+        )
+        .unwrap();
+        let rendered = render_host_blocks(&[bastion, app]);
+        assert!(!rendered.contains("hunter2") && !rendered.contains("keysecret"));
+        assert!(rendered.contains("Host bastion\n"));
+        assert!(rendered.contains("    IdentityFile \"~/.ssh/id work\"\n"));
+        assert!(rendered.contains("Host app-server\n"));
+        assert!(rendered.contains("    Port 2222\n    ProxyJump bastion\n"));
+        assert!(rendered.contains("    LocalForward 5432 db:5432\n"));
+        assert!(rendered.contains("    LocalForward [::1]:8080 localhost:80\n"));
+        assert!(rendered.contains("    RemoteForward 9000 localhost:3000\n"));
+        assert!(rendered.contains("    DynamicForward 1080\n"));
+
+        let entries = parse(&rendered);
+        assert_eq!(entries[1].alias, "app-server");
+        assert_eq!(entries[1].proxy_jump.as_deref(), Some("bastion"));
+        assert_eq!(entries[0].identity_file.as_deref(), Some("~/.ssh/id work"));
+    }
+
+    #[test]
+    fn reexport_replaces_managed_block_only() {
+        let first = replace_managed_block("Host mine\n  User me\n", "Host a\n");
+        assert!(first.starts_with("Host mine\n  User me\n\n# BEGIN ss-ssh"));
+        let second = replace_managed_block(&format!("{first}Host after\n"), "Host b\n");
+        assert_eq!(second.matches(MANAGED_BEGIN).count(), 1);
+        assert!(second.contains("Host b\n") && !second.contains("Host a\n"));
+        assert!(second.starts_with("Host mine\n") && second.ends_with("Host after\n"));
+        assert_eq!(
+            replace_managed_block("", ""),
+            format!("{MANAGED_BEGIN}\n{MANAGED_END}\n")
+        );
+    }
+
+    #[test]
+    fn includes_are_read_relative_to_config_dir() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("ssh-client-config-{nanos}"));
+        fs::create_dir_all(dir.join("config.d")).unwrap();
+        fs::write(dir.join("config.d").join("work"), "Host work\n  User dev\n").unwrap();
+        fs::write(dir.join("config"), "Include config.d/*\nHost home\n").unwrap();
+        let entries = load_ssh_config(&dir.join("config")).unwrap();
+        let aliases: Vec<_> = entries.iter().map(|entry| entry.alias.as_str()).collect();
+        assert_eq!(aliases, vec!["work", "home"]);
+        assert_eq!(entries[0].user.as_deref(), Some("dev"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,145 +1,385 @@
-use std::fs;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
 use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as Base64;
 use pbkdf2::pbkdf2_hmac;
 use rand_core::OsRng;
 use rand_core::TryRngCore;
-use rpassword::prompt_password;
-use sha2::Sha256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
 
-use crate::model::{ConnectionConfig, EncryptedBlob, MasterConfig, StoreFile, StoredConnection};
+use crate::migrate::{self, STORE_VERSION};
+use crate::model::{
+    ConnectionConfig, DEFAULT_ARGON2_MEMORY_KIB, DEFAULT_ARGON2_PARALLELISM,
+    DEFAULT_ARGON2_TIME_COST, EncryptedBlob, KdfParams, MasterConfig, StoreFile, StoredConnection,
+    StoredSecret,
+};
+use crate::secret::{Secret, SecretKey, SecretSource};
 
-pub(crate) fn config_path() -> Result<PathBuf> {
-    if let Some(mut dir) = dirs::config_dir() {
-        dir.push("ssh-client");
-        dir.push("config.json");
-        return Ok(dir);
-    }
-    let mut fallback = std::env::current_dir().context("current dir")?;
-    fallback.push("ssh-client-config.json");
-    Ok(fallback)
+const MASTER_CHECK: &str = "ssh-client-check";
+const KDF_MEMORY_ENV: &str = "SS_SSH_ARGON2_MEMORY_KIB";
+const KDF_TIME_COST_ENV: &str = "SS_SSH_ARGON2_TIME_COST";
+const STORE_BACKUPS: usize = 3;
+const NONCE_BYTES: usize = 12;
+const SEALED_AAD_TAG: &[u8] = b"ss-ssh sealed vault v1\n";
+
+pub(crate) struct OpenedVault {
+    pub(crate) master: MasterConfig,
+    pub(crate) master_key: SecretKey,
+    pub(crate) connections: Vec<ConnectionConfig>,
+    pub(crate) last_local_dir: Option<PathBuf>,
+    pub(crate) sealed: bool,
+    pub(crate) kdf_outdated: bool,
 }
 
-pub(crate) fn log_path() -> Result<PathBuf> {
-    if let Some(mut dir) = dirs::config_dir() {
-        dir.push("ssh-client");
-        dir.push("ssh-client.log");
-        return Ok(dir);
-    }
-    let mut fallback = std::env::current_dir().context("current dir")?;
-    fallback.push("ssh-client.log");
-    Ok(fallback)
+#[derive(Serialize, Deserialize)]
+struct SealedContent {
+    connections: Vec<StoredConnection>,
+    #[serde(default)]
+    last_local_dir: Option<String>,
 }
 
-pub(crate) fn load_or_init_store(
-    path: &Path,
-) -> Result<(MasterConfig, Vec<u8>, Vec<ConnectionConfig>, Option<PathBuf>)> {
-    if path.exists() {
-        let store = load_store(path)?;
-        let master_key = prompt_existing_master(&store.master)?;
-        let connections = store
-            .connections
-            .into_iter()
-            .map(|conn| decrypt_connection(conn, &master_key))
-            .collect::<Result<Vec<_>>>()?;
-        let last_local_dir = store
-            .last_local_dir
-            .as_ref()
-            .map(|value| PathBuf::from(value))
-            .filter(|value| value.is_dir());
-        return Ok((store.master, master_key, connections, last_local_dir));
+pub(crate) fn open_store(path: &Path) -> Result<Option<StoreFile>> {
+    if !path.exists() {
+        return Ok(None);
     }
+    load_and_migrate(path).map(Some)
+}
 
-    let (master, master_key) = setup_master()?;
-    let store = StoreFile {
+pub(crate) fn unlock_store(store: &StoreFile, password: &str) -> Result<Option<OpenedVault>> {
+    let Some(master_key) = unlock_master(&store.master, password)? else {
+        return Ok(None);
+    };
+    let mut store = store.clone();
+    let sealed = store.sealed.is_some();
+    unseal_store(&mut store, &master_key)?;
+    let connections = store
+        .connections
+        .iter()
+        .cloned()
+        .map(|conn| decrypt_connection(conn, &master_key))
+        .collect::<Result<Vec<_>>>()?;
+    let last_local_dir = store
+        .last_local_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .filter(|value| value.is_dir());
+    Ok(Some(OpenedVault {
+        kdf_outdated: store.master.kdf != configured_kdf(),
+        master: store.master,
+        master_key,
+        connections,
+        last_local_dir,
+        sealed,
+    }))
+}
+
+pub(crate) fn init_store(path: &Path, password: &str) -> Result<OpenedVault> {
+    let (master, master_key) = create_master_from_password(password)?;
+    let store = build_store(&master, &master_key, &[], None, false)?;
+    save_store(path, &store)?;
+    Ok(OpenedVault {
+        master,
+        master_key,
+        connections: vec![],
+        last_local_dir: None,
+        sealed: false,
+        kdf_outdated: false,
+    })
+}
+
+pub(crate) fn build_store(
+    master: &MasterConfig,
+    key: &[u8],
+    connections: &[ConnectionConfig],
+    last_local_dir: Option<&Path>,
+    sealed: bool,
+) -> Result<StoreFile> {
+    let connections = connections
+        .iter()
+        .map(|conn| encrypt_connection(conn, key))
+        .collect::<Result<Vec<_>>>()?;
+    let last_local_dir = last_local_dir.map(|dir| dir.to_string_lossy().into_owned());
+    if !sealed {
+        return Ok(StoreFile {
+            version: STORE_VERSION,
+            master: master.clone(),
+            connections,
+            last_local_dir,
+            sealed: None,
+        });
+    }
+    let content = SealedContent {
+        connections,
+        last_local_dir,
+    };
+    let json = Secret::from(serde_json::to_string(&content).context("serialize vault")?);
+    Ok(StoreFile {
+        version: STORE_VERSION,
         master: master.clone(),
         connections: vec![],
         last_local_dir: None,
+        sealed: Some(encrypt_with_aad(json.expose(), &sealed_aad(master)?, key)?),
+    })
+}
+
+pub(crate) fn unseal_store(store: &mut StoreFile, key: &[u8]) -> Result<()> {
+    let Some(blob) = store.sealed.take() else {
+        return Ok(());
     };
-    save_store(path, &store)?;
-    Ok((master, master_key, vec![], None))
+    let json = decrypt_with_aad(&blob, &sealed_aad(&store.master)?, key)
+        .context("sealed vault is damaged or its header was altered")?;
+    let content: SealedContent =
+        serde_json::from_str(json.expose()).context("parse sealed vault")?;
+    store.connections = content.connections;
+    store.last_local_dir = content.last_local_dir;
+    Ok(())
+}
+
+/// The header without the schema version, which migrations rewrite.
+fn sealed_aad(master: &MasterConfig) -> Result<Vec<u8>> {
+    let mut aad = SEALED_AAD_TAG.to_vec();
+    aad.extend(serde_json::to_vec(master).context("serialize vault header")?);
+    Ok(aad)
+}
+
+pub(crate) fn remove_unsealed_backups(path: &Path) -> Result<()> {
+    let mut forgotten = path.file_name().unwrap_or_default().to_os_string();
+    forgotten.push(".forgotten");
+    let copies = (1..=STORE_BACKUPS)
+        .map(|generation| backup_path(path, generation))
+        .chain((0..STORE_VERSION).map(|version| pre_migration_path(path, version)))
+        .chain([path.with_file_name(forgotten)]);
+    for copy in copies.filter(|copy| load_store(copy).is_ok_and(|store| store.sealed.is_none())) {
+        fs::remove_file(&copy).with_context(|| format!("remove {}", copy.display()))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn set_aside_store(path: &Path) -> Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".forgotten");
+    let target = path.with_file_name(name);
+    fs::rename(path, &target).context("set aside config file")?;
+    Ok(target)
 }
 
 pub(crate) fn load_store(path: &Path) -> Result<StoreFile> {
+    read_store(path).map(|(store, _)| store)
+}
+
+fn read_store(path: &Path) -> Result<(StoreFile, u32)> {
     let content = fs::read_to_string(path).context("read config file")?;
-    let store = serde_json::from_str(&content).context("parse config file")?;
+    let mut document: serde_json::Value =
+        serde_json::from_str(&content).context("parse config file")?;
+    let version = migrate::migrate(&mut document)?;
+    let store = serde_json::from_value(document).context("parse config file")?;
+    Ok((store, version))
+}
+
+fn load_and_migrate(path: &Path) -> Result<StoreFile> {
+    let (store, version) = read_store(path)?;
+    if version < STORE_VERSION {
+        let backup = pre_migration_path(path, version);
+        fs::copy(path, &backup).context("back up config before migration")?;
+        restrict_permissions(&backup)?;
+        save_store(path, &store)?;
+    }
     Ok(store)
 }
 
+pub(crate) fn pre_migration_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{version}.bak"));
+    path.with_file_name(name)
+}
+
 pub(crate) fn save_store(path: &Path, store: &StoreFile) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).context("create config dir")?;
     }
     let content = serde_json::to_string_pretty(store).context("serialize config")?;
-    fs::write(path, content).context("write config file")?;
-    Ok(())
+    if path.exists() {
+        rotate_backups(path)?;
+    }
+    write_atomic(path, content.as_bytes())
 }
 
-pub(crate) fn prompt_existing_master(master: &MasterConfig) -> Result<Vec<u8>> {
-    loop {
-        let password = prompt_password("Master password: ").context("read master password")?;
-        let salt = Base64.decode(&master.salt_b64).context("decode salt")?;
-        let key = derive_key(&password, &salt);
-        match decrypt_string(&master.check, &key) {
-            Ok(check) if check == "ssh-client-check" => return Ok(key),
-            _ => {
-                eprintln!("Invalid master password.");
-            }
-        }
+pub(crate) fn store_fingerprint(path: &Path) -> Option<[u8; 32]> {
+    fs::read(path)
+        .ok()
+        .map(|content| Sha256::digest(content).into())
+}
+
+pub(crate) fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak.{generation}"));
+    path.with_file_name(name)
+}
+
+pub(crate) fn newest_valid_backup(path: &Path) -> Option<(PathBuf, StoreFile)> {
+    (1..=STORE_BACKUPS)
+        .map(|generation| backup_path(path, generation))
+        .find_map(|backup| {
+            let store = load_store(&backup).ok()?;
+            decode_blob(&store.master.check).ok()?;
+            Some((backup, store))
+        })
+}
+
+pub(crate) fn restore_backup(path: &Path, backup: &Path) -> Result<StoreFile> {
+    let content = fs::read(backup).context("read backup")?;
+    if path.exists() {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".corrupt");
+        fs::rename(path, path.with_file_name(name)).context("set aside broken config")?;
     }
+    write_atomic(path, &content)?;
+    load_store(path)
 }
 
-pub(crate) fn setup_master() -> Result<(MasterConfig, Vec<u8>)> {
-    loop {
-        let password = prompt_password("Set master password: ").context("read master password")?;
-        let confirm =
-            prompt_password("Confirm master password: ").context("read confirm password")?;
-        if password != confirm {
-            eprintln!("Passwords do not match.");
-            continue;
+fn rotate_backups(path: &Path) -> Result<()> {
+    for generation in (1..STORE_BACKUPS).rev() {
+        let from = backup_path(path, generation);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, generation + 1)).context("rotate backups")?;
         }
-        if password.is_empty() {
-            eprintln!("Master password cannot be empty.");
-            continue;
-        }
-        return create_master_from_password(&password);
     }
+    let newest = backup_path(path, 1);
+    fs::copy(path, &newest).context("back up config file")?;
+    restrict_permissions(&newest)
 }
 
-pub(crate) fn create_master_from_password(password: &str) -> Result<(MasterConfig, Vec<u8>)> {
+/// Writes a sibling temp file, syncs it and renames it over `path`, so a crash
+/// leaves either the old or the new content but never a torn file. The temp
+/// name is unique per write, so concurrent writers never share one.
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let suffix = OsRng
+        .try_next_u32()
+        .map_err(|err| anyhow::anyhow!("random temp name failed: {err:?}"))?;
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{suffix:08x}.tmp", std::process::id()));
+    let temp = path.with_file_name(name);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&temp).context("create temp config file")?;
+    let written = restrict_permissions(&temp).and_then(|_| {
+        file.write_all(content)
+            .and_then(|_| file.sync_all())
+            .context("write config file")
+    });
+    drop(file);
+    if let Err(err) = written.and_then(|_| fs::rename(&temp, path).context("replace config file")) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    // Persist the rename itself; not every platform can open a directory.
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .context("restrict config file permissions")?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+pub(crate) fn unlock_master(master: &MasterConfig, password: &str) -> Result<Option<SecretKey>> {
+    let salt = Base64.decode(&master.salt_b64).context("decode salt")?;
+    decode_blob(&master.check).context("master check is damaged")?;
+    let key = derive_key(password, &salt, &master.kdf)?;
+    match decrypt_string(&master.check, &key) {
+        Ok(check) if check.expose() == MASTER_CHECK => Ok(Some(key)),
+        _ => Ok(None),
+    }
+}
+
+pub(crate) fn create_master_from_password(password: &str) -> Result<(MasterConfig, SecretKey)> {
     let mut salt = [0u8; 16];
     let mut rng = OsRng;
     rng.try_fill_bytes(&mut salt)
         .map_err(|err| anyhow::anyhow!("random salt failed: {err:?}"))?;
-    let key = derive_key(password, &salt);
-    let check = encrypt_string("ssh-client-check", &key)?;
+    let kdf = configured_kdf();
+    let key = derive_key(password, &salt, &kdf)?;
+    let check = encrypt_string(MASTER_CHECK, &key)?;
     let master = MasterConfig {
         salt_b64: Base64.encode(salt),
         check,
+        kdf,
     };
     Ok((master, key))
 }
 
-pub(crate) fn derive_key(password: &str, salt: &[u8]) -> Vec<u8> {
-    let mut key = vec![0u8; 32];
-    pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, 100_000, &mut key);
-    key
+pub(crate) fn configured_kdf() -> KdfParams {
+    let env_u32 = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+    };
+    KdfParams::Argon2id {
+        memory_kib: env_u32(KDF_MEMORY_ENV).unwrap_or(DEFAULT_ARGON2_MEMORY_KIB),
+        time_cost: env_u32(KDF_TIME_COST_ENV).unwrap_or(DEFAULT_ARGON2_TIME_COST),
+        parallelism: DEFAULT_ARGON2_PARALLELISM,
+    }
+}
+
+pub(crate) fn derive_key(password: &str, salt: &[u8], kdf: &KdfParams) -> Result<SecretKey> {
+    let mut key = SecretKey::zeroed(32);
+    match *kdf {
+        KdfParams::Pbkdf2Sha256 { iterations } => {
+            pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, key.as_mut_bytes());
+        }
+        KdfParams::Argon2id {
+            memory_kib,
+            time_cost,
+            parallelism,
+        } => {
+            let params = Params::new(memory_kib, time_cost, parallelism, Some(key.len()))
+                .map_err(|err| anyhow::anyhow!("invalid Argon2 parameters: {err}"))?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(password.as_bytes(), salt, key.as_mut_bytes())
+                .map_err(|err| anyhow::anyhow!("derive key failed: {err}"))?;
+        }
+    }
+    Ok(key)
 }
 
 pub(crate) fn encrypt_string(plaintext: &str, key: &[u8]) -> Result<EncryptedBlob> {
+    encrypt_with_aad(plaintext, &[], key)
+}
+
+fn encrypt_with_aad(plaintext: &str, aad: &[u8], key: &[u8]) -> Result<EncryptedBlob> {
     let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
     let mut nonce_bytes = [0u8; 12];
     let mut rng = OsRng;
     rng.try_fill_bytes(&mut nonce_bytes)
         .map_err(|err| anyhow::anyhow!("random nonce failed: {err:?}"))?;
     let nonce = Nonce::from_slice(&nonce_bytes);
+    let payload = Payload {
+        msg: plaintext.as_bytes(),
+        aad,
+    };
     let ciphertext = cipher
-        .encrypt(nonce, plaintext.as_bytes())
+        .encrypt(nonce, payload)
         .map_err(|err| anyhow::anyhow!("encrypt failed: {err:?}"))?;
     Ok(EncryptedBlob {
         nonce: Base64.encode(nonce_bytes),
@@ -147,44 +387,95 @@ pub(crate) fn encrypt_string(plaintext: &str, key: &[u8]) -> Result<EncryptedBlo
     })
 }
 
-pub(crate) fn decrypt_string(blob: &EncryptedBlob, key: &[u8]) -> Result<String> {
+pub(crate) fn decrypt_string(blob: &EncryptedBlob, key: &[u8]) -> Result<Secret> {
+    decrypt_with_aad(blob, &[], key)
+}
+
+fn decrypt_with_aad(blob: &EncryptedBlob, aad: &[u8], key: &[u8]) -> Result<Secret> {
     let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
-    let nonce_bytes = Base64.decode(&blob.nonce).context("decode nonce")?;
-    let ciphertext = Base64
-        .decode(&blob.ciphertext)
-        .context("decode ciphertext")?;
+    let (nonce_bytes, ciphertext) = decode_blob(blob)?;
     let nonce = Nonce::from_slice(&nonce_bytes);
+    let payload = Payload {
+        msg: ciphertext.as_ref(),
+        aad,
+    };
     let plaintext = cipher
-        .decrypt(nonce, ciphertext.as_ref())
+        .decrypt(nonce, payload)
         .map_err(|err| anyhow::anyhow!("decrypt failed: {err:?}"))?;
-    let text = String::from_utf8(plaintext).context("decode utf8")?;
-    Ok(text)
+    let text = String::from_utf8(plaintext).map_err(|err| {
+        err.into_bytes().zeroize();
+        anyhow::anyhow!("decode utf8")
+    })?;
+    Ok(Secret::from(text))
+}
+
+fn decode_blob(blob: &EncryptedBlob) -> Result<(Vec<u8>, Vec<u8>)> {
+    let nonce = Base64.decode(&blob.nonce).context("decode nonce")?;
+    if nonce.len() != NONCE_BYTES {
+        anyhow::bail!("nonce has {} bytes, expected {NONCE_BYTES}", nonce.len());
+    }
+    let ciphertext = Base64
+        .decode(&blob.ciphertext)
+        .context("decode ciphertext")?;
+    Ok((nonce, ciphertext))
 }
 
 pub(crate) fn encrypt_connection(conn: &ConnectionConfig, key: &[u8]) -> Result<StoredConnection> {
     let auth = match &conn.auth {
         crate::model::AuthConfig::Password { password } => {
             crate::model::StoredAuthConfig::Password {
-                password: encrypt_string(password, key)?,
+                password: encrypt_source(password, key)?,
             }
         }
         crate::model::AuthConfig::PrivateKey { path, password } => {
             crate::model::StoredAuthConfig::PrivateKey {
                 path: path.clone(),
                 password: match password {
-                    Some(pass) => Some(encrypt_string(pass, key)?),
+                    Some(pass) => Some(encrypt_source(pass, key)?),
                     None => None,
                 },
             }
         }
+        crate::model::AuthConfig::Agent { identity } => crate::model::StoredAuthConfig::Agent {
+            identity: identity.clone(),
+        },
+        crate::model::AuthConfig::KeyboardInteractive => {
+            crate::model::StoredAuthConfig::KeyboardInteractive
+        }
     };
     Ok(StoredConnection {
         name: conn.name.clone(),
         user: conn.user.clone(),
         host: conn.host.clone(),
+        port: conn.port,
         auth,
         history: conn.history.clone(),
         last_remote_dir: conn.last_remote_dir.clone(),
+        jump_host: conn.jump_host.clone(),
+        keepalive: conn.keepalive,
+        forwards: conn.forwards.clone(),
+    })
+}
+
+fn encrypt_source(source: &SecretSource, key: &[u8]) -> Result<StoredSecret> {
+    Ok(match source {
+        SecretSource::Stored(secret) => {
+            StoredSecret::Encrypted(encrypt_string(secret.expose(), key)?)
+        }
+        SecretSource::Command { command } => StoredSecret::Command {
+            command: encrypt_string(command, key)?,
+        },
+        SecretSource::Ask { ask } => StoredSecret::Ask { ask: *ask },
+    })
+}
+
+fn decrypt_source(stored: &StoredSecret, key: &[u8]) -> Result<SecretSource> {
+    Ok(match stored {
+        StoredSecret::Encrypted(blob) => SecretSource::Stored(decrypt_string(blob, key)?),
+        StoredSecret::Command { command } => SecretSource::Command {
+            command: decrypt_string(command, key)?.expose().to_string(),
+        },
+        StoredSecret::Ask { ask } => SecretSource::Ask { ask: *ask },
     })
 }
 
@@ -192,18 +483,24 @@ pub(crate) fn decrypt_connection(conn: StoredConnection, key: &[u8]) -> Result<C
     let auth = match conn.auth {
         crate::model::StoredAuthConfig::Password { password } => {
             crate::model::AuthConfig::Password {
-                password: decrypt_string(&password, key)?,
+                password: decrypt_source(&password, key)?,
             }
         }
         crate::model::StoredAuthConfig::PrivateKey { path, password } => {
             crate::model::AuthConfig::PrivateKey {
                 path,
                 password: match password {
-                    Some(pass) => Some(decrypt_string(&pass, key)?),
+                    Some(pass) => Some(decrypt_source(&pass, key)?),
                     None => None,
                 },
             }
         }
+        crate::model::StoredAuthConfig::Agent { identity } => {
+            crate::model::AuthConfig::Agent { identity }
+        }
+        crate::model::StoredAuthConfig::KeyboardInteractive => {
+            crate::model::AuthConfig::KeyboardInteractive
+        }
     };
     let name = if conn.name.trim().is_empty() {
         conn.host.clone()
@@ -214,9 +511,14 @@ pub(crate) fn decrypt_connection(conn: StoredConnection, key: &[u8]) -> Result<C
         name,
         user: conn.user,
         host: conn.host,
+        port: conn.port,
         auth,
         history: conn.history,
         last_remote_dir: conn.last_remote_dir,
+        jump_host: conn.jump_host,
+        via: vec![],
+        keepalive: conn.keepalive,
+        forwards: conn.forwards,
     })
 }
 
@@ -224,6 +526,7 @@ pub(crate) fn decrypt_connection(conn: StoredConnection, key: &[u8]) -> Result<C
 mod tests {
     use super::*;
     use crate::model::AuthConfig;
+    use crate::secret::AskPolicy;
 
     #[test]
     fn encrypt_and_decrypt_roundtrip() {
@@ -231,8 +534,223 @@ mod tests {
         let text = "secret";
         let blob = encrypt_string(text, &key).unwrap();
         let decrypted = decrypt_string(&blob, &key).unwrap();
-        assert_eq!(decrypted, text);
+        assert_eq!(decrypted.expose(), text);
         assert!(!master.salt_b64.is_empty());
+        assert!(matches!(master.kdf, KdfParams::Argon2id { .. }));
+        assert_eq!(unlock_master(&master, "test-password").unwrap(), Some(key));
+        assert_eq!(unlock_master(&master, "wrong").unwrap(), None);
+    }
+
+    #[test]
+    fn legacy_pbkdf2_vault_unlocks_as_outdated() {
+        let salt = [7u8; 16];
+        let legacy_key = derive_key("old-password", &salt, &crate::model::legacy_kdf()).unwrap();
+        let json = serde_json::json!({
+            "master": {
+                "salt_b64": Base64.encode(salt),
+                "check": encrypt_string(MASTER_CHECK, &legacy_key).unwrap(),
+            },
+            "connections": [],
+        });
+        let store: StoreFile = serde_json::from_value(json).unwrap();
+        assert_eq!(store.master.kdf, crate::model::legacy_kdf());
+        assert_eq!(
+            unlock_master(&store.master, "old-password").unwrap(),
+            Some(legacy_key)
+        );
+
+        let opened = unlock_store(&store, "old-password").unwrap().unwrap();
+        assert!(opened.kdf_outdated);
+        assert_eq!(opened.master.kdf, crate::model::legacy_kdf());
+    }
+
+    fn temp_store_dir(tag: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ssh-client-store-{tag}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn leftover_temp_files(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn concurrent_atomic_writes_each_use_their_own_temp_file() {
+        let dir = temp_store_dir("concurrent");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        let contents: Vec<Vec<u8>> = (0..8u8).map(|n| vec![b'a' + n; 64 * 1024]).collect();
+        std::thread::scope(|scope| {
+            for content in &contents {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        write_atomic(path, content).unwrap();
+                    }
+                });
+            }
+        });
+        let written = fs::read(&path).unwrap();
+        assert!(contents.contains(&written));
+        assert!(leftover_temp_files(&dir).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_store_rotates_backups_and_restricts_permissions() {
+        let dir = temp_store_dir("rotate");
+        let path = dir.join("config.json");
+        let (master, _key) = create_master_from_password("test-password").unwrap();
+        for generation in 0..=STORE_BACKUPS + 1 {
+            let store = StoreFile {
+                version: STORE_VERSION,
+                master: master.clone(),
+                connections: vec![],
+                last_local_dir: Some(format!("/gen{generation}")),
+                sealed: None,
+            };
+            save_store(&path, &store).unwrap();
+        }
+        let dir_of = |path: &Path| load_store(path).unwrap().last_local_dir.unwrap();
+        assert_eq!(dir_of(&path), format!("/gen{}", STORE_BACKUPS + 1));
+        assert_eq!(
+            dir_of(&backup_path(&path, 1)),
+            format!("/gen{STORE_BACKUPS}")
+        );
+        assert_eq!(dir_of(&backup_path(&path, STORE_BACKUPS)), "/gen1");
+        assert!(!backup_path(&path, STORE_BACKUPS + 1).exists());
+        assert!(leftover_temp_files(&dir).is_empty());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restore_skips_broken_backups() {
+        let dir = temp_store_dir("restore");
+        let path = dir.join("config.json");
+        let (master, _key) = create_master_from_password("test-password").unwrap();
+        let store = StoreFile {
+            version: STORE_VERSION,
+            master,
+            connections: vec![],
+            last_local_dir: Some("/good".to_string()),
+            sealed: None,
+        };
+        save_store(&path, &store).unwrap();
+        save_store(&path, &store).unwrap();
+        fs::write(backup_path(&path, 1), "{ not json").unwrap();
+        fs::copy(&path, backup_path(&path, 2)).unwrap();
+        fs::write(&path, "{ torn").unwrap();
+        assert!(load_store(&path).is_err());
+
+        let (backup, _) = newest_valid_backup(&path).unwrap();
+        assert_eq!(backup, backup_path(&path, 2));
+        let restored = restore_backup(&path, &backup).unwrap();
+        assert_eq!(restored.last_local_dir.as_deref(), Some("/good"));
+        assert!(load_store(&path).is_ok());
+        assert!(dir.join("config.json.corrupt").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn old_store_is_migrated_with_a_backup() {
+        let dir = temp_store_dir("migrate");
+        let path = dir.join("config.json");
+        fs::create_dir_all(&dir).unwrap();
+        let legacy = r#"{"master":{"salt_b64":"c2FsdA==","check":{"nonce":"","ciphertext":""}},"connections":[]}"#;
+        fs::write(&path, legacy).unwrap();
+
+        let store = load_and_migrate(&path).unwrap();
+        assert_eq!(store.version, STORE_VERSION);
+        assert_eq!(
+            fs::read_to_string(pre_migration_path(&path, 0)).unwrap(),
+            legacy
+        );
+        assert_eq!(read_store(&path).unwrap().1, STORE_VERSION);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sealed_store_hides_everything_and_binds_the_header() {
+        let (master, key) = create_master_from_password("test-password").unwrap();
+        let conn = ConnectionConfig {
+            name: "payroll".to_string(),
+            user: "admin".to_string(),
+            host: "db.internal".to_string(),
+            port: 22,
+            auth: AuthConfig::Password {
+                password: "pw".into(),
+            },
+            history: vec![],
+            last_remote_dir: Some("/srv/payroll".to_string()),
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        };
+        let sealed = build_store(
+            &master,
+            &key,
+            std::slice::from_ref(&conn),
+            Some(Path::new("/tmp")),
+            true,
+        )
+        .unwrap();
+        let json = serde_json::to_string(&sealed).unwrap();
+        for leak in ["payroll", "admin", "db.internal", "/tmp"] {
+            assert!(!json.contains(leak), "{leak}");
+        }
+
+        let mut opened: StoreFile = serde_json::from_str(&json).unwrap();
+        unseal_store(&mut opened, &key).unwrap();
+        assert_eq!(opened.last_local_dir.as_deref(), Some("/tmp"));
+        let decoded = decrypt_connection(opened.connections.remove(0), &key).unwrap();
+        assert_eq!(decoded, conn);
+
+        let mut tampered: StoreFile = serde_json::from_str(&json).unwrap();
+        tampered.master.kdf = crate::model::legacy_kdf();
+        assert!(unseal_store(&mut tampered, &key).is_err());
+    }
+
+    #[test]
+    fn sealing_removes_backups_in_the_clear() {
+        let dir = temp_store_dir("seal");
+        let path = dir.join("config.json");
+        let (master, key) = create_master_from_password("test-password").unwrap();
+        save_store(
+            &path,
+            &build_store(&master, &key, &[], None, false).unwrap(),
+        )
+        .unwrap();
+        fs::copy(&path, pre_migration_path(&path, 1)).unwrap();
+        fs::copy(&path, dir.join("config.json.forgotten")).unwrap();
+        fs::write(dir.join("config.json.corrupt"), "{\"connections\": [").unwrap();
+        save_store(&path, &build_store(&master, &key, &[], None, true).unwrap()).unwrap();
+        save_store(&path, &build_store(&master, &key, &[], None, true).unwrap()).unwrap();
+        remove_unsealed_backups(&path).unwrap();
+        assert!(backup_path(&path, 1).exists());
+        assert!(!backup_path(&path, 2).exists());
+        assert!(!pre_migration_path(&path, 1).exists());
+        assert!(!dir.join("config.json.forgotten").exists());
+        assert!(dir.join("config.json.corrupt").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn damaged_master_check_is_an_error_not_a_wrong_password() {
+        let (mut master, _key) = create_master_from_password("test-password").unwrap();
+        master.check.nonce = Base64.encode([0u8; 3]);
+        assert!(unlock_master(&master, "test-password").is_err());
     }
 
     #[test]
@@ -242,29 +760,118 @@ mod tests {
             name: "name".to_string(),
             user: "user".to_string(),
             host: "host".to_string(),
+            port: 22,
             auth: AuthConfig::PrivateKey {
                 path: "/key".to_string(),
-                password: Some("pw".to_string()),
+                password: Some("pw".into()),
             },
             history: vec![crate::model::HistoryEntry {
                 ts: 1,
                 state: crate::model::HistoryState::Success,
             }],
             last_remote_dir: Some("/home/user".to_string()),
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
         };
         let stored = encrypt_connection(&conn, &key).unwrap();
         let decoded = decrypt_connection(stored, &key).unwrap();
         assert_eq!(decoded.name, "name");
         assert_eq!(decoded.user, "user");
         assert_eq!(decoded.host, "host");
+        assert_eq!(decoded.port, 22);
         assert_eq!(decoded.history.len(), 1);
         assert_eq!(decoded.last_remote_dir.as_deref(), Some("/home/user"));
         match decoded.auth {
             AuthConfig::PrivateKey { path, password } => {
                 assert_eq!(path, "/key");
-                assert_eq!(password.as_deref(), Some("pw"));
+                assert_eq!(password, Some("pw".into()));
             }
             _ => panic!("expected private key auth"),
         }
     }
+
+    #[test]
+    fn agent_connection_roundtrip_keeps_identity() {
+        let (_master, key) = create_master_from_password("test-password").unwrap();
+        let conn = ConnectionConfig {
+            name: "name".to_string(),
+            user: "user".to_string(),
+            host: "host".to_string(),
+            port: 22,
+            auth: AuthConfig::Agent {
+                identity: Some("SHA256:abc".to_string()),
+            },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        };
+        let stored = encrypt_connection(&conn, &key).unwrap();
+        let decoded = decrypt_connection(stored, &key).unwrap();
+        assert_eq!(decoded.auth, conn.auth);
+    }
+
+    #[test]
+    fn secret_command_is_stored_encrypted_and_old_secrets_still_read() {
+        let (_master, key) = create_master_from_password("test-password").unwrap();
+        let auth = AuthConfig::Password {
+            password: SecretSource::Command {
+                command: "pass show prod/db1".to_string(),
+            },
+        };
+        let conn = ConnectionConfig {
+            name: "db1".to_string(),
+            user: "user".to_string(),
+            host: "host".to_string(),
+            port: 22,
+            auth: auth.clone(),
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        };
+        let stored = encrypt_connection(&conn, &key).unwrap();
+        let json = serde_json::to_string(&stored).unwrap();
+        assert!(!json.contains("prod/db1"));
+        let stored: StoredConnection = serde_json::from_str(&json).unwrap();
+        assert_eq!(decrypt_connection(stored, &key).unwrap().auth, auth);
+
+        let asked = ConnectionConfig {
+            auth: AuthConfig::PrivateKey {
+                path: "~/.ssh/id_ed25519".to_string(),
+                password: Some(SecretSource::Ask {
+                    ask: AskPolicy::EveryTime,
+                }),
+            },
+            ..conn
+        };
+        let stored = encrypt_connection(&asked, &key).unwrap();
+        let json = serde_json::to_string(&stored).unwrap();
+        assert!(
+            json.contains(r#""password":{"ask":"every-time"}"#),
+            "{json}"
+        );
+        let stored: StoredConnection = serde_json::from_str(&json).unwrap();
+        assert_eq!(decrypt_connection(stored, &key).unwrap().auth, asked.auth);
+
+        let blob = encrypt_string("hunter2", &key).unwrap();
+        let old = serde_json::json!({
+            "user": "u",
+            "host": "h",
+            "auth": { "Password": { "password": blob } },
+        });
+        let old: StoredConnection = serde_json::from_value(old).unwrap();
+        assert_eq!(
+            decrypt_connection(old, &key).unwrap().auth,
+            AuthConfig::Password {
+                password: "hunter2".into()
+            }
+        );
+    }
 }
@@ -1,8 +1,12 @@
 use ratatui::layout::Constraint;
 
-pub(crate) const HELP_TEXT: &str = "(t)erminal | (u)pload | (d)ownload | (o)ptions | (v)iew | (q)uit";
+pub(crate) const HELP_TEXT: &str = "(t)erminal | (u)pload | (d)ownload | (i)mport | ex(p)ort | (b)undle | (o)ptions | (v)iew | (q)uit";
+
+pub(crate) const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+pub(crate) const SPINNER_FRAME_MILLIS: u128 = 100;
 
 pub(crate) const LABEL_WIDTH: usize = 9;
+pub(crate) const AUTH_PROMPT_MAX_LABEL_WIDTH: usize = 24;
 pub(crate) const TRANSFER_PICKER_WIDTH: u16 = 60;
 pub(crate) const TRANSFER_PICKER_HEIGHT: u16 = 90;
 
@@ -5,7 +5,8 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Clear, Paragraph};
 
 use crate::app::App;
-use crate::model::{AuthKind, Field, MasterField};
+use crate::model::{AuthKind, Field, MasterField, SecretKind};
+use crate::secret::AskPolicy;
 use crate::ui::constants::{LABEL_WIDTH, POPUP_MIN_HEIGHT, POPUP_MIN_WIDTH};
 
 pub(crate) fn field_line(
@@ -44,7 +45,10 @@ pub(crate) fn action_line(label: &str, active: bool) -> Line<'static> {
         .add_modifier(Modifier::BOLD);
     let spans = vec![
         Span::styled(indicator, indicator_style),
-        Span::styled(label.to_string(), Style::default().add_modifier(Modifier::BOLD)),
+        Span::styled(
+            label.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
     ];
     Line::from(spans)
 }
@@ -85,23 +89,27 @@ pub(crate) fn render_input_cursor(
     app: &App,
     area: Rect,
     scroll: usize,
-    name_row: Option<usize>,
-    user_row: Option<usize>,
-    host_row: Option<usize>,
-    _auth_row: Option<usize>,
-    key_row: Option<usize>,
-    pass_row: Option<usize>,
+    active_row: Option<usize>,
 ) {
-    let (row, col) = match app.new_connection.active_field {
-        Field::Name => (name_row, app.new_connection.name.chars().count()),
-        Field::User => (user_row, app.new_connection.user.chars().count()),
-        Field::Host => (host_row, app.new_connection.host.chars().count()),
-        Field::AuthType => return,
-        Field::KeyPath => (key_row, app.new_connection.key_path.chars().count()),
-        Field::Password => (pass_row, app.new_connection.password.chars().count()),
+    let form = &app.new_connection;
+    let col = match form.active_field {
+        Field::Name => form.name.chars().count(),
+        Field::User => form.user.chars().count(),
+        Field::Host => form.host.chars().count(),
+        Field::Port => form.port.chars().count(),
+        Field::JumpHost | Field::AuthType | Field::SecretSource => return,
+        Field::KeyPath => form.key_path.chars().count(),
+        Field::AgentIdentity => form.agent_identity.chars().count(),
+        Field::Password if form.secret_kind == SecretKind::Command => {
+            form.secret_command.chars().count()
+        }
+        Field::Password => form.password.char_count(),
+        Field::KeepaliveInterval => form.keepalive_interval.chars().count(),
+        Field::KeepaliveMissed => form.keepalive_missed.chars().count(),
+        Field::Forwards => form.forwards.chars().count(),
         Field::ActionTest | Field::ActionSave => return,
     };
-    let Some(row) = row else {
+    let Some(row) = active_row else {
         return;
     };
     if row < scroll {
@@ -127,10 +135,10 @@ pub(crate) fn render_master_cursor(
     confirm_row: Option<usize>,
 ) {
     let (row, col) = match app.master_change.active_field {
-        MasterField::Current => (current_row, app.master_change.current.chars().count()),
-        MasterField::New => (new_row, app.master_change.new_password.chars().count()),
-        MasterField::Confirm => (confirm_row, app.master_change.confirm.chars().count()),
-        MasterField::ActionSave => return,
+        MasterField::Current => (current_row, app.master_change.current.char_count()),
+        MasterField::New => (new_row, app.master_change.new_password.char_count()),
+        MasterField::Confirm => (confirm_row, app.master_change.confirm.char_count()),
+        MasterField::ActionSave | MasterField::ActionLayout => return,
     };
     let Some(row) = row else {
         return;
@@ -150,6 +158,17 @@ pub(crate) fn auth_kind_label(kind: AuthKind) -> &'static str {
         AuthKind::PasswordOnly => "Password only",
         AuthKind::PrivateKey => "Private key",
         AuthKind::PrivateKeyWithPassword => "Private key + password",
+        AuthKind::Agent => "SSH agent",
+        AuthKind::KeyboardInteractive => "Keyboard-interactive",
+    }
+}
+
+pub(crate) fn secret_kind_label(kind: SecretKind) -> &'static str {
+    match kind {
+        SecretKind::Stored => "Stored in the vault",
+        SecretKind::Command => "From a command",
+        SecretKind::Ask(AskPolicy::EveryTime) => "Ask every time",
+        SecretKind::Ask(AskPolicy::WhileConnected) => "Ask once per connection",
     }
 }
 
@@ -212,7 +231,12 @@ pub(crate) fn modal_height(content_lines: usize, footer_lines: usize) -> u16 {
     (total as u16).saturating_add(2 + 2)
 }
 
-pub(crate) fn draw_popup_frame(frame: &mut Frame<'_>, area: Rect, title: &str, style: Style) -> Rect {
+pub(crate) fn draw_popup_frame(
+    frame: &mut Frame<'_>,
+    area: Rect,
+    title: &str,
+    style: Style,
+) -> Rect {
     frame.render_widget(Clear, area);
     let block = Block::default().borders(Borders::ALL).border_style(style);
     frame.render_widget(block, area);
@@ -290,6 +314,11 @@ mod tests {
             auth_kind_label(AuthKind::PrivateKeyWithPassword),
             "Private key + password"
         );
+        assert_eq!(auth_kind_label(AuthKind::Agent), "SSH agent");
+        assert_eq!(
+            auth_kind_label(AuthKind::KeyboardInteractive),
+            "Keyboard-interactive"
+        );
     }
 
     #[test]
@@ -3,19 +3,19 @@ use ratatui::layout::{Constraint, Direction, Layout, Rect};
 
 use crate::app::App;
 use crate::model::Mode;
-use crate::ui::constants::{
-    HEADER_HEIGHT, TERMINAL_FOOTER_HEIGHT, help_columns, compact_columns,
-};
+use crate::ui::constants::{HEADER_HEIGHT, TERMINAL_FOOTER_HEIGHT, compact_columns, help_columns};
 use crate::ui::modals::{
-    draw_confirm_delete_modal, draw_master_password_modal, draw_new_connection_modal,
-    draw_notice_modal, draw_transfer_confirm_modal, draw_try_result_modal,
+    draw_auth_prompt_modal, draw_bundle_export_modal, draw_bundle_import_modal,
+    draw_confirm_delete_modal, draw_host_key_modal, draw_master_password_modal,
+    draw_new_connection_modal, draw_notice_modal, draw_save_conflict_modal, draw_ssh_export_modal,
+    draw_transfer_confirm_modal, draw_try_result_modal, draw_unlock_screen,
 };
 use crate::ui::panels::{
     draw_app_header, draw_help_header, draw_open_tabs, draw_saved_list, draw_terminal_footer,
     draw_terminal_tab_bar, draw_terminal_view,
 };
 use crate::ui::pickers::{
-    draw_file_picker_modal, draw_key_picker_modal, draw_remote_picker_modal,
+    draw_file_picker_modal, draw_key_picker_modal, draw_remote_picker_modal, draw_ssh_import_modal,
 };
 
 pub(crate) mod constants;
@@ -25,6 +25,10 @@ mod panels;
 mod pickers;
 
 pub(crate) fn draw_ui(frame: &mut Frame<'_>, app: &App) {
+    if app.is_locked() {
+        draw_unlock_screen(frame, app);
+        return;
+    }
     if app.terminal_tabs_open() {
         let layout = Layout::default()
             .direction(Direction::Vertical)
@@ -63,6 +67,18 @@ pub(crate) fn draw_ui(frame: &mut Frame<'_>, app: &App) {
     if app.remote_picker.is_some() {
         draw_remote_picker_modal(frame, app);
     }
+    if app.ssh_import.is_some() {
+        draw_ssh_import_modal(frame, app);
+    }
+    if app.ssh_export.is_some() {
+        draw_ssh_export_modal(frame, app);
+    }
+    if app.bundle_export.is_some() {
+        draw_bundle_export_modal(frame, app);
+    }
+    if app.bundle_import.is_some() {
+        draw_bundle_import_modal(frame, app);
+    }
     if app
         .transfer
         .as_ref()
@@ -84,6 +100,15 @@ pub(crate) fn draw_ui(frame: &mut Frame<'_>, app: &App) {
     if app.notice.is_some() {
         draw_notice_modal(frame, app);
     }
+    if app.host_key_prompt.is_some() {
+        draw_host_key_modal(frame, app);
+    }
+    if !app.save_conflicts.is_empty() {
+        draw_save_conflict_modal(frame, app);
+    }
+    if app.auth_prompt.is_some() {
+        draw_auth_prompt_modal(frame, app);
+    }
 }
 
 fn draw_main_ui(frame: &mut Frame<'_>, app: &App, area: Rect, show_help_header: bool) {
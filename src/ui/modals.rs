@@ -2,17 +2,23 @@ use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Wrap};
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Wrap};
 
 use crate::app::App;
-use crate::model::{AuthKind, Field, MasterField};
+use crate::model::{
+    AuthKind, BundleExportState, BundleField, Field, MasterField, SecretKind, UnlockConfirm,
+    UnlockField, UnlockMode,
+};
+use crate::secret::AskPolicy;
 use crate::ui::constants::{
-    LABEL_WIDTH, MODAL_MAX_HEIGHT_PERCENT, MODAL_MIN_WIDTH, MODAL_WIDTH_PERCENT,
-    TRANSFER_CONFIRM_WIDTH_PERCENT,
+    AUTH_PROMPT_MAX_LABEL_WIDTH, LABEL_WIDTH, MODAL_MAX_HEIGHT_PERCENT, MODAL_MIN_WIDTH,
+    MODAL_WIDTH_PERCENT, PICKER_FOOTER_HEIGHT, TRANSFER_CONFIRM_WIDTH_PERCENT,
+    TRANSFER_PICKER_HEIGHT, TRANSFER_PICKER_WIDTH,
 };
 use crate::ui::helpers::{
-    action_line, auth_kind_label, centered_rect_abs, centered_rect_by_height, draw_popup_frame,
-    field_line, format_bytes, modal_height, render_input_cursor, render_master_cursor,
+    action_line, auth_kind_label, centered_rect, centered_rect_abs, centered_rect_by_height,
+    draw_popup_frame, field_line, format_bytes, list_state, modal_height, render_input_cursor,
+    render_master_cursor, secret_kind_label,
 };
 
 pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
@@ -36,6 +42,53 @@ pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
             Style::default().fg(Color::Gray),
         )));
     }
+    if app.new_connection.active_field == Field::JumpHost {
+        footer_lines.push(Line::from(Span::styled(
+            "Left/Right to pick a saved connection as jump host",
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    if app.new_connection.auth_kind == AuthKind::Agent {
+        footer_lines.push(Line::from(Span::styled(
+            "F3 to pick an agent identity | leave empty to try all",
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    if matches!(
+        app.new_connection.active_field,
+        Field::KeepaliveInterval | Field::KeepaliveMissed
+    ) {
+        footer_lines.push(Line::from(Span::styled(
+            "Keepalive every N seconds (0 = off) | dead after Max miss unanswered",
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    if app.new_connection.active_field == Field::SecretSource
+        || (app.new_connection.active_field == Field::Password
+            && app.new_connection.secret_kind == SecretKind::Command)
+    {
+        let hint = match app.new_connection.secret_kind {
+            SecretKind::Ask(AskPolicy::EveryTime) => {
+                "Left/Right to choose | asked for on every connect, terminal tab and transfer, never saved"
+            }
+            SecretKind::Ask(AskPolicy::WhileConnected) => {
+                "Left/Right to choose | asked for on connect, kept in memory until disconnect, never saved"
+            }
+            SecretKind::Stored | SecretKind::Command => {
+                "Left/Right to choose | a command's first output line is used at connect time, never saved"
+            }
+        };
+        footer_lines.push(Line::from(Span::styled(
+            hint,
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    if app.new_connection.active_field == Field::Forwards {
+        footer_lines.push(Line::from(Span::styled(
+            "L|R [bind:]port:host:hostport or D [bind:]port, comma-separated | started on connect",
+            Style::default().fg(Color::Gray),
+        )));
+    }
 
     let area_width = (frame.area().width.saturating_mul(MODAL_WIDTH_PERCENT) / 100)
         .min(frame.area().width.saturating_sub(2))
@@ -45,7 +98,7 @@ pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
     let value_width = content_width.saturating_sub(2 + LABEL_WIDTH as u16 + 2) as usize;
     let max_height = frame.area().height.saturating_mul(MODAL_MAX_HEIGHT_PERCENT) / 100;
 
-    let title = if app.edit_index.is_some() {
+    let title = if app.edit_key.is_some() {
         "Edit connection"
     } else {
         "New connection"
@@ -57,6 +110,7 @@ pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
     let host_row;
     let auth_row;
     let mut key_row = None;
+    let mut secret_row = None;
     let mut pass_row = None;
     let action_test_row;
     let action_save_row;
@@ -95,6 +149,28 @@ pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
     ));
     row_idx += 1;
 
+    let port_row = Some(row_idx);
+    lines.push(field_line(
+        "Port",
+        &app.new_connection.port,
+        app.new_connection.active_field == Field::Port,
+        false,
+        LABEL_WIDTH,
+        value_width,
+    ));
+    row_idx += 1;
+
+    let jump_row = Some(row_idx);
+    lines.push(field_line(
+        "Jump via",
+        &app.jump_host_label(app.new_connection.jump_host.as_deref()),
+        app.new_connection.active_field == Field::JumpHost,
+        false,
+        LABEL_WIDTH,
+        value_width,
+    ));
+    row_idx += 1;
+
     auth_row = Some(row_idx);
     lines.push(field_line(
         "Auth",
@@ -120,22 +196,84 @@ pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
         ));
         row_idx += 1;
     }
+    if app.new_connection.auth_kind == AuthKind::Agent {
+        key_row = Some(row_idx);
+        lines.push(field_line(
+            "Identity",
+            &app.new_connection.agent_identity,
+            app.new_connection.active_field == Field::AgentIdentity,
+            false,
+            LABEL_WIDTH,
+            value_width,
+        ));
+        row_idx += 1;
+    }
     if matches!(
         app.new_connection.auth_kind,
         AuthKind::PasswordOnly | AuthKind::PrivateKeyWithPassword
     ) {
-        pass_row = Some(row_idx);
+        secret_row = Some(row_idx);
         lines.push(field_line(
-            "Password",
-            &app.new_connection.password,
-            app.new_connection.active_field == Field::Password,
-            true,
+            "Secret",
+            secret_kind_label(app.new_connection.secret_kind),
+            app.new_connection.active_field == Field::SecretSource,
+            false,
             LABEL_WIDTH,
             value_width,
         ));
         row_idx += 1;
+        let entry = match app.new_connection.secret_kind {
+            SecretKind::Stored => Some(("Password", app.new_connection.password.expose(), true)),
+            SecretKind::Command => {
+                Some(("Command", app.new_connection.secret_command.as_str(), false))
+            }
+            SecretKind::Ask(_) => None,
+        };
+        if let Some((label, value, masked)) = entry {
+            pass_row = Some(row_idx);
+            lines.push(field_line(
+                label,
+                value,
+                app.new_connection.active_field == Field::Password,
+                masked,
+                LABEL_WIDTH,
+                value_width,
+            ));
+            row_idx += 1;
+        }
     }
 
+    let keepalive_row = Some(row_idx);
+    lines.push(field_line(
+        "Keepalive",
+        &app.new_connection.keepalive_interval,
+        app.new_connection.active_field == Field::KeepaliveInterval,
+        false,
+        LABEL_WIDTH,
+        value_width,
+    ));
+    row_idx += 1;
+    let missed_row = Some(row_idx);
+    lines.push(field_line(
+        "Max miss",
+        &app.new_connection.keepalive_missed,
+        app.new_connection.active_field == Field::KeepaliveMissed,
+        false,
+        LABEL_WIDTH,
+        value_width,
+    ));
+    row_idx += 1;
+    let forwards_row = Some(row_idx);
+    lines.push(field_line(
+        "Forwards",
+        &app.new_connection.forwards,
+        app.new_connection.active_field == Field::Forwards,
+        false,
+        LABEL_WIDTH,
+        value_width,
+    ));
+    row_idx += 1;
+
     lines.push(Line::from(""));
     row_idx += 1;
     action_test_row = Some(row_idx);
@@ -181,9 +319,15 @@ pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
         Field::Name => name_row,
         Field::User => user_row,
         Field::Host => host_row,
+        Field::Port => port_row,
+        Field::JumpHost => jump_row,
         Field::AuthType => auth_row,
-        Field::KeyPath => key_row,
+        Field::KeyPath | Field::AgentIdentity => key_row,
+        Field::SecretSource => secret_row,
         Field::Password => pass_row,
+        Field::KeepaliveInterval => keepalive_row,
+        Field::KeepaliveMissed => missed_row,
+        Field::Forwards => forwards_row,
         Field::ActionTest => action_test_row,
         Field::ActionSave => action_save_row,
     };
@@ -210,18 +354,7 @@ pub(crate) fn draw_new_connection_modal(frame: &mut Frame<'_>, app: &App) {
     };
     let paragraph = Paragraph::new(visible_lines).wrap(Wrap { trim: false });
     frame.render_widget(paragraph, layout[0]);
-    render_input_cursor(
-        frame,
-        app,
-        layout[0],
-        scroll,
-        name_row,
-        user_row,
-        host_row,
-        auth_row,
-        key_row,
-        pass_row,
-    );
+    render_input_cursor(frame, app, layout[0], scroll, active_row);
 
     let footer = Paragraph::new(footer_lines)
         .style(Style::default().fg(Color::Gray))
@@ -378,12 +511,12 @@ pub(crate) fn draw_transfer_confirm_modal(frame: &mut Frame<'_>, app: &App) {
 }
 
 pub(crate) fn draw_master_password_modal(frame: &mut Frame<'_>, app: &App) {
-    let height = modal_height(6, 2);
+    let height = modal_height(7, 2);
     let area = centered_rect_by_height(60, height, frame.area());
     let inner = draw_popup_frame(
         frame,
         area,
-        "Change master password",
+        "Vault options",
         Style::default().fg(Color::Yellow),
     );
     let layout = Layout::default()
@@ -391,9 +524,7 @@ pub(crate) fn draw_master_password_modal(frame: &mut Frame<'_>, app: &App) {
         .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
         .split(inner);
 
-    let value_width = layout[0]
-        .width
-        .saturating_sub(2 + LABEL_WIDTH as u16 + 2) as usize;
+    let value_width = layout[0].width.saturating_sub(2 + LABEL_WIDTH as u16 + 2) as usize;
     let mut lines = Vec::new();
     let current_row = Some(0usize);
     let new_row = Some(1usize);
@@ -401,7 +532,7 @@ pub(crate) fn draw_master_password_modal(frame: &mut Frame<'_>, app: &App) {
 
     lines.push(field_line(
         "Current",
-        &app.master_change.current,
+        app.master_change.current.expose(),
         app.master_change.active_field == MasterField::Current,
         true,
         LABEL_WIDTH,
@@ -409,7 +540,7 @@ pub(crate) fn draw_master_password_modal(frame: &mut Frame<'_>, app: &App) {
     ));
     lines.push(field_line(
         "New",
-        &app.master_change.new_password,
+        app.master_change.new_password.expose(),
         app.master_change.active_field == MasterField::New,
         true,
         LABEL_WIDTH,
@@ -417,7 +548,7 @@ pub(crate) fn draw_master_password_modal(frame: &mut Frame<'_>, app: &App) {
     ));
     lines.push(field_line(
         "Confirm",
-        &app.master_change.confirm,
+        app.master_change.confirm.expose(),
         app.master_change.active_field == MasterField::Confirm,
         true,
         LABEL_WIDTH,
@@ -428,6 +559,14 @@ pub(crate) fn draw_master_password_modal(frame: &mut Frame<'_>, app: &App) {
         "Save master password",
         app.master_change.active_field == MasterField::ActionSave,
     ));
+    lines.push(action_line(
+        if app.sealed_vault {
+            "Unseal vault (encrypt passwords only)"
+        } else {
+            "Seal vault (encrypt names, hosts and history too)"
+        },
+        app.master_change.active_field == MasterField::ActionLayout,
+    ));
 
     let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
     frame.render_widget(paragraph, layout[0]);
@@ -448,6 +587,147 @@ pub(crate) fn draw_master_password_modal(frame: &mut Frame<'_>, app: &App) {
     frame.render_widget(footer, layout[1]);
 }
 
+pub(crate) fn draw_unlock_screen(frame: &mut Frame<'_>, app: &App) {
+    let Some(state) = &app.unlock else {
+        return;
+    };
+    let (title, intro) = match state.mode {
+        UnlockMode::Setup => (
+            "Create master password",
+            "It encrypts your stored connections and cannot be recovered.",
+        ),
+        UnlockMode::Startup => ("Unlock vault", "Enter the master password."),
+        UnlockMode::Idle => ("Locked", "Locked after inactivity. Sessions keep running."),
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(intro), Line::from("")];
+    if !app.profile.is_default() || state.mode != UnlockMode::Idle {
+        lines.insert(
+            0,
+            Line::from(vec![
+                Span::raw("Profile: "),
+                Span::styled(app.profile.name(), bold),
+            ]),
+        );
+    }
+    let mut cursor = None;
+    let footer_spans = if let Some(confirm) = &state.pending_confirm {
+        let question = match confirm {
+            UnlockConfirm::RestoreBackup(backup) => format!(
+                "Restore the vault from {}? The current file is kept as config.json.corrupt.",
+                backup.display()
+            ),
+            UnlockConfirm::StartOver => "A forgotten master password cannot be recovered. \
+                Start over with an empty vault? The current one is kept as config.json.forgotten."
+                .to_string(),
+        };
+        lines.push(Line::from(Span::styled(
+            question,
+            Style::default().fg(Color::Yellow),
+        )));
+        vec![
+            Span::styled("Enter", bold),
+            Span::raw(" to confirm, "),
+            Span::styled("Esc", bold),
+            Span::raw(" to cancel"),
+        ]
+    } else {
+        let area_width = frame.area().width.saturating_mul(60) / 100;
+        let value_width = area_width.saturating_sub(4 + LABEL_WIDTH as u16 + 2) as usize;
+        let mut fields = vec![(UnlockField::Password, "Password", &state.password)];
+        if state.mode == UnlockMode::Setup {
+            fields.push((UnlockField::Confirm, "Confirm", &state.confirm));
+        }
+        for (field, label, value) in fields {
+            let active = state.active_field == field;
+            if active {
+                cursor = Some((lines.len(), value.char_count()));
+            }
+            let value = value.expose();
+            lines.push(field_line(
+                label,
+                value,
+                active,
+                true,
+                LABEL_WIDTH,
+                value_width,
+            ));
+        }
+        if state.caps_lock {
+            lines.push(Line::from(Span::styled(
+                "Caps Lock is on",
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        if state.failed_attempts > 0 {
+            let mut text = format!("Failed attempts: {}", state.failed_attempts);
+            if let Some(retry_at) = state.retry_at {
+                let wait = retry_at.saturating_duration_since(std::time::Instant::now());
+                if !wait.is_zero() {
+                    text.push_str(&format!(", try again in {}s", wait.as_secs() + 1));
+                }
+            }
+            lines.push(Line::from(Span::styled(
+                text,
+                Style::default().fg(Color::Gray),
+            )));
+        }
+        let mut spans = vec![Span::styled("Enter", bold)];
+        match state.mode {
+            UnlockMode::Setup => {
+                spans.push(Span::raw(" to continue, "));
+                spans.push(Span::styled("Tab", bold));
+                spans.push(Span::raw(" to move, "));
+                spans.push(Span::styled("F2", bold));
+                spans.push(Span::raw(" switch profile, "));
+            }
+            UnlockMode::Startup => {
+                spans.push(Span::raw(" to unlock, "));
+                spans.push(Span::styled("F5", bold));
+                spans.push(Span::raw(" restore backup, "));
+                spans.push(Span::styled("F4", bold));
+                spans.push(Span::raw(" forgot password, "));
+                spans.push(Span::styled("F2", bold));
+                spans.push(Span::raw(" switch profile, "));
+            }
+            UnlockMode::Idle => {
+                spans.push(Span::raw(" to unlock, "));
+                spans.push(Span::styled("Esc", bold));
+                spans.push(Span::raw(" to clear, "));
+            }
+        }
+        spans.push(Span::styled("Ctrl+C", bold));
+        spans.push(Span::raw(" to quit"));
+        spans
+    };
+    if let Some(err) = &state.error {
+        lines.push(Line::from(Span::styled(
+            err.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    let height = modal_height(lines.len() + 2, 2);
+    let area = centered_rect_by_height(60, height, frame.area());
+    let inner = draw_popup_frame(frame, area, title, Style::default().fg(Color::Yellow));
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
+        .split(inner);
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), layout[0]);
+    if let Some((row, col)) = cursor
+        && row < layout[0].height as usize
+    {
+        let cursor_x = layout[0].x + 2 + LABEL_WIDTH as u16 + 2 + col as u16;
+        frame.set_cursor_position((cursor_x, layout[0].y + row as u16));
+    }
+
+    let footer = Paragraph::new(Line::from(footer_spans))
+        .style(Style::default().fg(Color::Gray))
+        .block(Block::default().borders(Borders::TOP));
+    frame.render_widget(footer, layout[1]);
+}
+
 pub(crate) fn draw_confirm_delete_modal(frame: &mut Frame<'_>, app: &App) {
     let height = modal_height(1, 2);
     let area = centered_rect_by_height(50, height, frame.area());
@@ -459,7 +739,9 @@ pub(crate) fn draw_confirm_delete_modal(frame: &mut Frame<'_>, app: &App) {
     );
 
     let label = app
-        .delete_index
+        .delete_key
+        .as_deref()
+        .and_then(|key| app.saved_position(key))
         .and_then(|index| app.connections.get(index))
         .map(|conn| conn.label())
         .unwrap_or_else(|| "Unknown".to_string());
@@ -469,8 +751,7 @@ pub(crate) fn draw_confirm_delete_modal(frame: &mut Frame<'_>, app: &App) {
         .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
         .split(inner);
 
-    let message = Paragraph::new(format!("Delete {label}?"))
-        .wrap(Wrap { trim: true });
+    let message = Paragraph::new(format!("Delete {label}?")).wrap(Wrap { trim: true });
     frame.render_widget(message, layout[0]);
 
     let footer = Paragraph::new(Line::from(vec![
@@ -495,7 +776,11 @@ pub(crate) fn draw_try_result_modal(frame: &mut Frame<'_>, app: &App) {
     };
     let height = modal_height(2, 2);
     let area = centered_rect_by_height(50, height, frame.area());
-    let title = if result.success { "Try success" } else { "Try failed" };
+    let title = if result.success {
+        "Try success"
+    } else {
+        "Try failed"
+    };
     let inner = draw_popup_frame(frame, area, title, Style::default().fg(Color::Yellow));
 
     let layout = Layout::default()
@@ -522,7 +807,11 @@ pub(crate) fn draw_notice_modal(frame: &mut Frame<'_>, app: &App) {
         None => return,
     };
     let message_lines = notice.message.lines().count().max(1);
-    let footer_lines = if app.notice_action_label().is_some() { 1 } else { 1 };
+    let footer_lines = if app.notice_action_label().is_some() {
+        1
+    } else {
+        1
+    };
     let height = modal_height(message_lines + footer_lines + 2, 1);
     let area = centered_rect_by_height(50, height, frame.area());
     let inner = draw_popup_frame(
@@ -566,13 +855,495 @@ pub(crate) fn draw_notice_modal(frame: &mut Frame<'_>, app: &App) {
     frame.render_widget(footer, layout[1]);
 }
 
+pub(crate) fn draw_host_key_modal(frame: &mut Frame<'_>, app: &App) {
+    let Some(prompt) = &app.host_key_prompt else {
+        return;
+    };
+    let info = &prompt.info;
+    let lines = vec![
+        Line::from(format!(
+            "The authenticity of {} can't be established.",
+            info.address()
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Key type: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(info.key_type.as_str()),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "Fingerprint: ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(info.fingerprint.as_str()),
+        ]),
+    ];
+    let height = modal_height(lines.len() + 1, 2);
+    let area = centered_rect_by_height(60, height, frame.area());
+    let inner = draw_popup_frame(
+        frame,
+        area,
+        "Unknown host key",
+        Style::default().fg(Color::Yellow),
+    );
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
+        .split(inner);
+
+    let message = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(message, layout[0]);
+
+    let footer = Paragraph::new(Line::from(vec![
+        Span::styled("o", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" accept once, "),
+        Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" accept and save, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to reject"),
+    ]))
+    .style(Style::default().fg(Color::Gray))
+    .block(Block::default().borders(Borders::TOP));
+    frame.render_widget(footer, layout[1]);
+}
+
+pub(crate) fn draw_save_conflict_modal(frame: &mut Frame<'_>, app: &App) {
+    let Some(conflict) = app.save_conflicts.first() else {
+        return;
+    };
+    let summary = |conn: &crate::model::ConnectionConfig| {
+        let mut text = format!("{}  {}@{}", conn.label(), conn.user, conn.address());
+        if let Some(jump) = &conn.jump_host {
+            text.push_str(&format!("  via {jump}"));
+        }
+        if !conn.forwards.is_empty() {
+            text.push_str(&format!(
+                "  [{}]",
+                crate::model::format_forwards(&conn.forwards)
+            ));
+        }
+        text
+    };
+    let mut lines = vec![
+        Line::from(format!(
+            "{} was edited here and in another ss-ssh instance.",
+            conflict.ours.label()
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Mine:   ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(summary(&conflict.ours)),
+        ]),
+        Line::from(vec![
+            Span::styled("Theirs: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(summary(&conflict.theirs)),
+        ]),
+    ];
+    if app.save_conflicts.len() > 1 {
+        lines.push(Line::from(Span::styled(
+            format!("{} more after this one", app.save_conflicts.len() - 1),
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    let height = modal_height(lines.len() + 1, 2);
+    let area = centered_rect_by_height(60, height, frame.area());
+    let inner = draw_popup_frame(
+        frame,
+        area,
+        "Conflicting edit",
+        Style::default().fg(Color::Yellow),
+    );
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
+        .split(inner);
+
+    let message = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(message, layout[0]);
+
+    let footer = Paragraph::new(Line::from(vec![
+        Span::styled("m", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" keep mine, "),
+        Span::styled("t", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" take theirs"),
+    ]))
+    .style(Style::default().fg(Color::Gray))
+    .block(Block::default().borders(Borders::TOP));
+    frame.render_widget(footer, layout[1]);
+}
+
+pub(crate) fn draw_auth_prompt_modal(frame: &mut Frame<'_>, app: &App) {
+    let Some(prompt) = &app.auth_prompt else {
+        return;
+    };
+    let request = &prompt.request;
+    let mut intro = Vec::new();
+    if !request.name.trim().is_empty() {
+        intro.push(Line::from(Span::styled(
+            request.name.trim().to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+    }
+    for line in request.instructions.lines() {
+        intro.push(Line::from(line.to_string()));
+    }
+    if !intro.is_empty() {
+        intro.push(Line::from(""));
+    }
+    let labels: Vec<&str> = request
+        .fields
+        .iter()
+        .map(|field| field.text.trim_end_matches(':').trim())
+        .collect();
+    let label_width = labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(LABEL_WIDTH, AUTH_PROMPT_MAX_LABEL_WIDTH);
+
+    let width_percent = 60;
+    let content_width = (frame.area().width.saturating_mul(width_percent) / 100)
+        .saturating_sub(4)
+        .max(1) as usize;
+    let intro_rows: usize = intro
+        .iter()
+        .map(|line| line.width().max(1).div_ceil(content_width))
+        .sum();
+    let height = modal_height(intro_rows + labels.len(), 2);
+    let area = centered_rect_by_height(width_percent, height, frame.area());
+    let inner = draw_popup_frame(
+        frame,
+        area,
+        &format!("Authenticate {}", request.target),
+        Style::default().fg(Color::Yellow),
+    );
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(intro_rows as u16),
+                Constraint::Min(1),
+                Constraint::Length(2),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+    frame.render_widget(Paragraph::new(intro).wrap(Wrap { trim: false }), layout[0]);
+
+    let value_width = layout[1].width.saturating_sub(2 + label_width as u16 + 2) as usize;
+    let lines: Vec<Line> = request
+        .fields
+        .iter()
+        .zip(&labels)
+        .zip(&prompt.answers)
+        .enumerate()
+        .map(|(index, ((field, label), answer))| {
+            field_line(
+                label,
+                answer,
+                index == prompt.active,
+                !field.echo,
+                label_width,
+                value_width,
+            )
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), layout[1]);
+    if prompt.active < layout[1].height as usize {
+        let answer_len = prompt.answers[prompt.active]
+            .chars()
+            .count()
+            .min(value_width);
+        frame.set_cursor_position((
+            layout[1].x + 2 + label_width as u16 + 2 + answer_len as u16,
+            layout[1].y + prompt.active as u16,
+        ));
+    }
+
+    let footer = Paragraph::new(Line::from(vec![
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to continue, "),
+        Span::styled("Up/Down", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to move, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to cancel"),
+    ]))
+    .style(Style::default().fg(Color::Gray))
+    .block(Block::default().borders(Borders::TOP));
+    frame.render_widget(footer, layout[2]);
+}
+
+pub(crate) fn draw_ssh_export_modal(frame: &mut Frame<'_>, app: &App) {
+    let Some(export) = &app.ssh_export else {
+        return;
+    };
+    let lines = vec![
+        Line::from(format!(
+            "Write {} connection(s) as Host blocks. Passwords are never exported.",
+            app.connections.len()
+        )),
+        Line::from(""),
+    ];
+    let height = modal_height(lines.len() + 1, 2);
+    let area = centered_rect_by_height(60, height, frame.area());
+    let inner = draw_popup_frame(
+        frame,
+        area,
+        "Export to ssh config",
+        Style::default().fg(Color::Yellow),
+    );
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(lines.len() as u16),
+                Constraint::Min(1),
+                Constraint::Length(2),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), layout[0]);
+
+    let value_width = layout[1].width.saturating_sub(2 + LABEL_WIDTH as u16 + 2) as usize;
+    let line = field_line("File", &export.path, true, false, LABEL_WIDTH, value_width);
+    frame.render_widget(Paragraph::new(line), layout[1]);
+    let path_len = export.path.chars().count().min(value_width);
+    frame.set_cursor_position((
+        layout[1].x + 2 + LABEL_WIDTH as u16 + 2 + path_len as u16,
+        layout[1].y,
+    ));
+
+    let footer = Paragraph::new(Line::from(vec![
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to write, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to cancel"),
+    ]))
+    .style(Style::default().fg(Color::Gray))
+    .block(Block::default().borders(Borders::TOP));
+    frame.render_widget(footer, layout[2]);
+}
+
+pub(crate) fn draw_bundle_export_modal(frame: &mut Frame<'_>, app: &App) {
+    let Some(export) = &app.bundle_export else {
+        return;
+    };
+    if export.picking {
+        draw_bundle_pick_list(frame, app, export);
+        return;
+    }
+    let count = export.checked.iter().filter(|checked| **checked).count();
+    let fields = [
+        (BundleField::File, "File", export.path.as_str(), false),
+        (
+            BundleField::Passphrase,
+            "Passphrase",
+            export.passphrase.expose(),
+            true,
+        ),
+        (
+            BundleField::Confirm,
+            "Confirm",
+            export.confirm.expose(),
+            true,
+        ),
+    ];
+    draw_bundle_form(
+        frame,
+        "Export bundle",
+        &format!(
+            "{count} connection(s) with their passwords and key passphrases{}. \
+             Anyone with the file and the passphrase can read them.",
+            if export.include_history {
+                " and history"
+            } else {
+                ""
+            }
+        ),
+        &fields,
+        export.active_field,
+        " to write, ",
+    );
+}
+
+fn draw_bundle_pick_list(frame: &mut Frame<'_>, app: &App, export: &BundleExportState) {
+    let area = centered_rect(TRANSFER_PICKER_WIDTH, TRANSFER_PICKER_HEIGHT, frame.area());
+    let inner = draw_popup_frame(
+        frame,
+        area,
+        "Export bundle",
+        Style::default().fg(Color::Yellow),
+    );
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Min(3),
+                Constraint::Length(PICKER_FOOTER_HEIGHT),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+    let history = if export.include_history {
+        "included"
+    } else {
+        "left out"
+    };
+    frame.render_widget(
+        Paragraph::new(format!("History: {history}")).style(Style::default().fg(Color::Gray)),
+        layout[0],
+    );
+    let items: Vec<ListItem> = app
+        .connections
+        .iter()
+        .zip(&export.checked)
+        .map(|(conn, checked)| {
+            let mark = if *checked { "[x]" } else { "[ ]" };
+            ListItem::new(format!(
+                "{mark} {}  {}@{}",
+                conn.label(),
+                conn.user,
+                conn.address()
+            ))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(Span::styled("> ", Style::default().fg(Color::White)));
+    frame.render_stateful_widget(
+        list,
+        layout[1],
+        &mut list_state(export.selected, export.checked.len()),
+    );
+    let footer = Paragraph::new(
+        "Space to toggle, A to toggle all, H for history, Enter to continue, Esc to cancel",
+    )
+    .style(Style::default().fg(Color::Gray))
+    .block(Block::default().borders(Borders::TOP));
+    frame.render_widget(footer, layout[2]);
+}
+
+pub(crate) fn draw_bundle_import_modal(frame: &mut Frame<'_>, app: &App) {
+    let Some(import) = &app.bundle_import else {
+        return;
+    };
+    let fields = [
+        (BundleField::File, "File", import.path.as_str(), false),
+        (
+            BundleField::Passphrase,
+            "Passphrase",
+            import.passphrase.expose(),
+            true,
+        ),
+    ];
+    draw_bundle_form(
+        frame,
+        "Import bundle",
+        "Connections are shown for review before anything is saved.",
+        &fields,
+        import.active_field,
+        " to open, ",
+    );
+}
+
+fn draw_bundle_form(
+    frame: &mut Frame<'_>,
+    title: &str,
+    intro: &str,
+    fields: &[(BundleField, &str, &str, bool)],
+    active_field: BundleField,
+    enter_hint: &str,
+) {
+    let intro = vec![Line::from(intro.to_string()), Line::from("")];
+    let height = modal_height(intro.len() + fields.len() + 1, 2);
+    let area = centered_rect_by_height(60, height, frame.area());
+    let inner = draw_popup_frame(frame, area, title, Style::default().fg(Color::Yellow));
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(intro.len() as u16 + 1),
+                Constraint::Min(1),
+                Constraint::Length(2),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+    frame.render_widget(Paragraph::new(intro).wrap(Wrap { trim: true }), layout[0]);
+
+    let label_width = fields
+        .iter()
+        .map(|(_, label, _, _)| label.len())
+        .fold(LABEL_WIDTH, usize::max);
+    let value_width = layout[1].width.saturating_sub(2 + label_width as u16 + 2) as usize;
+    let mut lines = Vec::new();
+    for (row, (field, label, value, mask)) in fields.iter().enumerate() {
+        let active = *field == active_field;
+        if active {
+            let len = value.chars().count().min(value_width);
+            frame.set_cursor_position((
+                layout[1].x + 2 + label_width as u16 + 2 + len as u16,
+                layout[1].y + row as u16,
+            ));
+        }
+        lines.push(field_line(
+            label,
+            value,
+            active,
+            *mask,
+            label_width,
+            value_width,
+        ));
+    }
+    frame.render_widget(Paragraph::new(lines), layout[1]);
+
+    let footer = Paragraph::new(Line::from(vec![
+        Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to move, "),
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(enter_hint.to_string()),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to cancel"),
+    ]))
+    .style(Style::default().fg(Color::Gray))
+    .block(Block::default().borders(Borders::TOP));
+    frame.render_widget(footer, layout[2]);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::app::App;
     use crate::model::{Mode, TransferDirection, TransferState, TransferStep};
-    use ratatui::backend::TestBackend;
     use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn draw_auth_prompt_modal_smoke() {
+        let mut app = App::for_test();
+        let (reply, _answers) = std::sync::mpsc::channel();
+        app.auth_prompt_tx
+            .send(crate::auth_prompt::AuthPromptRequest {
+                target: "user@host".to_string(),
+                name: "Duo".to_string(),
+                instructions: "Enter your one-time code".to_string(),
+                fields: vec![crate::auth_prompt::AuthPromptField {
+                    text: "Verification code:".to_string(),
+                    echo: false,
+                }],
+                reply,
+            })
+            .unwrap();
+        app.poll_auth_prompts();
+        let backend = TestBackend::new(80, 24);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| draw_auth_prompt_modal(frame, &app))
+            .unwrap();
+    }
 
     #[test]
     fn draw_new_connection_modal_smoke() {
@@ -608,4 +1379,36 @@ mod tests {
             .draw(|frame| draw_transfer_confirm_modal(frame, &app))
             .unwrap();
     }
+
+    #[test]
+    fn draw_bundle_modals_smoke() {
+        let mut app = App::for_test();
+        app.connections = vec![crate::model::ConnectionConfig {
+            name: "web".to_string(),
+            user: "dev".to_string(),
+            host: "web.example".to_string(),
+            port: 22,
+            auth: crate::model::AuthConfig::Agent { identity: None },
+            history: vec![],
+            last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
+        }];
+        app.open_bundle_export();
+        app.open_bundle_import();
+        let backend = TestBackend::new(80, 24);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| draw_bundle_export_modal(frame, &app))
+            .unwrap();
+        app.bundle_export.as_mut().unwrap().picking = false;
+        terminal
+            .draw(|frame| {
+                draw_bundle_export_modal(frame, &app);
+                draw_bundle_import_modal(frame, &app);
+            })
+            .unwrap();
+    }
 }
@@ -1,24 +1,32 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use ratatui::Frame;
 use ratatui::layout::{Alignment, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Wrap};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap};
 use unicode_width::UnicodeWidthChar;
 
 use crate::app::{App, HeaderMode};
-use crate::model::AuthConfig;
-use crate::ui::constants::{HEADER_HEIGHT, HELP_TEXT};
+use crate::forward::ForwardStatus;
+use crate::model::{AuthConfig, ConnectionHealth, ForwardSpec};
+use crate::secret::SecretSource;
+use crate::ui::constants::{HEADER_HEIGHT, HELP_TEXT, SPINNER_FRAME_MILLIS, SPINNER_FRAMES};
+use crate::ui::helpers::{centered_rect_abs, format_bytes};
 
 pub(crate) fn draw_saved_list(frame: &mut Frame<'_>, app: &App, area: Rect) {
     let header_style = Style::default()
         .fg(Color::Cyan)
         .add_modifier(Modifier::BOLD);
-    let connected: HashSet<String> = app
+    let health: HashMap<String, ConnectionHealth> = app
         .open_connections
         .iter()
-        .map(|conn| crate::model::connection_key(&conn.config))
+        .map(|conn| {
+            (
+                crate::model::connection_key(&conn.config),
+                conn.keepalive.health,
+            )
+        })
         .collect();
     let list_height = area.height.saturating_sub(2) as usize;
     let (start, end) = if app.connections.is_empty() || list_height == 0 {
@@ -40,39 +48,63 @@ pub(crate) fn draw_saved_list(frame: &mut Frame<'_>, app: &App, area: Rect) {
             .iter()
             .map(|conn| {
                 let key = crate::model::connection_key(conn);
-                let status_style = if connected.contains(&key) {
-                    Style::default().fg(Color::Green)
-                } else if app.last_error.contains_key(&key) {
-                    Style::default().fg(Color::Red)
-                } else {
-                    Style::default()
+                let (prefix, suffix, status_style) = match health.get(&key) {
+                    Some(ConnectionHealth::Alive) => ("  ", "", Style::default().fg(Color::Green)),
+                    Some(ConnectionHealth::Stale) => {
+                        ("~ ", " (stale)", Style::default().fg(Color::Yellow))
+                    }
+                    Some(ConnectionHealth::Dead) => {
+                        ("x ", " (dead)", Style::default().fg(Color::Red))
+                    }
+                    None if app.last_error.contains_key(&key) => {
+                        ("! ", "", Style::default().fg(Color::Red))
+                    }
+                    None => ("  ", "", Style::default()),
                 };
-                let prefix = if connected.contains(&key) {
-                    "  "
-                } else if app.last_error.contains_key(&key) {
-                    "! "
-                } else {
-                    "  "
+                if let Some(pending) = app.pending_connects.get(&key) {
+                    let frame = (pending.started.elapsed().as_millis() / SPINNER_FRAME_MILLIS)
+                        as usize
+                        % SPINNER_FRAMES.len();
+                    return ListItem::new(Line::from(Span::styled(
+                        format!(
+                            "{} {} (connecting… {})",
+                            SPINNER_FRAMES[frame],
+                            conn.label(),
+                            pending.phase.label()
+                        ),
+                        Style::default().fg(Color::Cyan),
+                    )));
+                }
+                let suffix = match app.reconnect_label(&key) {
+                    Some(label) => format!(" ({label})"),
+                    None => suffix.to_string(),
                 };
                 ListItem::new(Line::from(Span::styled(
-                    format!("{prefix}{}", conn.label()),
+                    format!("{prefix}{}{suffix}", conn.label()),
                     status_style,
                 )))
             })
             .collect()
     };
 
+    let title = if app.profile.is_default() {
+        "Available connections".to_string()
+    } else {
+        format!("Available connections ({})", app.profile.name())
+    };
     let block = Block::default()
-        .title(Line::from(Span::styled(
-            "Available connections",
-            header_style,
-        )))
+        .title(Line::from(Span::styled(title, header_style)))
         .borders(Borders::ALL);
     let inner = block.inner(area);
     frame.render_widget(block, area);
     let list = List::new(items)
         .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-        .highlight_symbol(Span::styled(">", Style::default().fg(Color::White).add_modifier(Modifier::BOLD)));
+        .highlight_symbol(Span::styled(
+            ">",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ));
     let list_area = Rect {
         x: inner.x,
         y: inner.y,
@@ -92,9 +124,23 @@ pub(crate) fn draw_saved_list(frame: &mut Frame<'_>, app: &App, area: Rect) {
     let selected_connected = app
         .connections
         .get(app.selected_saved)
-        .map(|conn| connected.contains(&crate::model::connection_key(conn)))
-        .unwrap_or(false);
-    let connection_commands = if selected_connected {
+        .and_then(|conn| health.get(&crate::model::connection_key(conn)))
+        .is_some_and(|health| *health != ConnectionHealth::Dead);
+    let reconnect_pending = app.connections.get(app.selected_saved).is_some_and(|conn| {
+        app.reconnect_label(&crate::model::connection_key(conn))
+            .is_some()
+    });
+    let selected_connecting = app.connections.get(app.selected_saved).is_some_and(|conn| {
+        app.pending_connects
+            .contains_key(&crate::model::connection_key(conn))
+    });
+    let connection_commands = if selected_connecting {
+        "(n)ew | (e)dit | (Esc) abort connect | (x)delete"
+    } else if reconnect_pending {
+        "(n)ew | (e)dit | (c)ancel reconnect | (x)delete"
+    } else if app.selected_connection_lost() {
+        "(n)ew | (e)dit | (r)econnect | (x)delete"
+    } else if selected_connected {
         "(n)ew | (e)dit | (c)ancel | (x)delete"
     } else {
         "(n)ew | (e)dit | (c)onnect | (x)delete"
@@ -215,15 +261,49 @@ pub(crate) fn draw_open_tabs(
         }
     }
 
-    let connected: HashSet<String> = app
+    let health: HashMap<String, ConnectionHealth> = app
         .open_connections
         .iter()
-        .map(|conn| crate::model::connection_key(&conn.config))
+        .map(|conn| {
+            (
+                crate::model::connection_key(&conn.config),
+                conn.keepalive.health,
+            )
+        })
         .collect();
+    let forwards = app
+        .connections
+        .get(app.selected_saved)
+        .map(|conn| app.forward_status(&crate::model::connection_key(conn)))
+        .unwrap_or_default();
+    let (body_area, forwards_area) = if forwards.is_empty() {
+        (body_area, None)
+    } else {
+        let rows: usize = forwards
+            .iter()
+            .map(|forward| 1 + forward.destinations.len())
+            .sum();
+        let height = (rows as u16 + 2).min(body_area.height / 2);
+        (
+            Rect {
+                height: body_area.height.saturating_sub(height),
+                ..body_area
+            },
+            Some(Rect {
+                y: body_area.y + body_area.height.saturating_sub(height),
+                height,
+                ..body_area
+            }),
+        )
+    };
     let details = if let Some(conn) = app.connections.get(app.selected_saved) {
         let key = crate::model::connection_key(conn);
-        let status = if connected.contains(&key) {
-            "Connected"
+        let status = if let Some(health) = health.get(&key) {
+            match health {
+                ConnectionHealth::Alive => "Connected",
+                ConnectionHealth::Stale => "Connected (keepalive missed)",
+                ConnectionHealth::Dead => "Connection lost",
+            }
         } else if app.last_error.contains_key(&key) {
             "Failed"
         } else if conn.history.is_empty() {
@@ -242,16 +322,42 @@ pub(crate) fn draw_open_tabs(
             ]),
             Line::from(vec![
                 Span::styled("Host: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(&conn.host),
+                Span::raw(match &conn.jump_host {
+                    Some(key) => format!(
+                        "{} (via {})",
+                        conn.address(),
+                        app.jump_host_label(Some(key))
+                    ),
+                    None => conn.address(),
+                }),
             ]),
             Line::from(vec![
                 Span::styled("Auth: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(match &conn.auth {
-                    AuthConfig::Password { .. } => "Password",
-                    AuthConfig::PrivateKey { password: None, .. } => "Private key",
+                    AuthConfig::Password {
+                        password: SecretSource::Command { .. },
+                    } => "Password from command".to_string(),
+                    AuthConfig::Password {
+                        password: SecretSource::Ask { .. },
+                    } => "Password, asked at connect".to_string(),
+                    AuthConfig::Password { .. } => "Password".to_string(),
+                    AuthConfig::PrivateKey { password: None, .. } => "Private key".to_string(),
+                    AuthConfig::PrivateKey {
+                        password: Some(SecretSource::Command { .. }),
+                        ..
+                    } => "Private key + password from command".to_string(),
+                    AuthConfig::PrivateKey {
+                        password: Some(SecretSource::Ask { .. }),
+                        ..
+                    } => "Private key + password, asked at connect".to_string(),
                     AuthConfig::PrivateKey {
                         password: Some(_), ..
-                    } => "Private key + password",
+                    } => "Private key + password".to_string(),
+                    AuthConfig::Agent { identity: None } => "SSH agent".to_string(),
+                    AuthConfig::Agent {
+                        identity: Some(identity),
+                    } => format!("SSH agent ({identity})"),
+                    AuthConfig::KeyboardInteractive => "Keyboard-interactive".to_string(),
                 }),
             ]),
             Line::from(vec![
@@ -260,6 +366,13 @@ pub(crate) fn draw_open_tabs(
             ]),
         ];
 
+        if !conn.forwards.is_empty() {
+            lines.push(Line::from(vec![
+                Span::styled("Forwards: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(crate::model::format_forwards(&conn.forwards)),
+            ]));
+        }
+
         if let Some(err) = app.last_error.get(&key) {
             lines.push(Line::from(vec![
                 Span::styled("Error: ", Style::default().add_modifier(Modifier::BOLD)),
@@ -311,6 +424,9 @@ pub(crate) fn draw_open_tabs(
             .alignment(Alignment::Center)
     };
     frame.render_widget(details, body_area);
+    if let Some(forwards_area) = forwards_area {
+        draw_forwards(frame, &forwards, forwards_area, header_style);
+    }
 
     if let Some(logs_area) = logs_area {
         let log_lines = app
@@ -337,6 +453,65 @@ pub(crate) fn draw_open_tabs(
     }
 }
 
+fn draw_forwards(
+    frame: &mut Frame<'_>,
+    forwards: &[ForwardStatus],
+    area: Rect,
+    header_style: Style,
+) {
+    let lines: Vec<Line> = forwards
+        .iter()
+        .flat_map(|forward| {
+            std::iter::once(forward_line(forward)).chain(forward.destinations.iter().map(
+                |destination| {
+                    Line::from(Span::styled(
+                        format!("  → {destination}"),
+                        Style::default().fg(Color::Gray),
+                    ))
+                },
+            ))
+        })
+        .collect();
+    let panel = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(Line::from(Span::styled("Forwards", header_style))),
+    );
+    frame.render_widget(panel, area);
+}
+
+fn forward_line(forward: &ForwardStatus) -> Line<'static> {
+    let spec = forward.spec.describe();
+    if let Some(err) = &forward.error {
+        return Line::from(vec![
+            Span::raw(format!("{spec}  ")),
+            Span::styled(err.clone(), Style::default().fg(Color::Red)),
+        ]);
+    }
+    if !forward.listening {
+        return Line::from(Span::styled(
+            format!("{spec}  starting…"),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+    let spec = match (&forward.spec, forward.bound_port) {
+        (ForwardSpec::Remote { remote_port: 0, .. }, Some(port)) => {
+            format!("{spec} (bound port {port})")
+        }
+        _ => spec,
+    };
+    Line::from(vec![
+        Span::styled(spec, Style::default().fg(Color::Green)),
+        Span::raw(format!(
+            "  {} open / {} total  up {}  down {}",
+            forward.active,
+            forward.total,
+            format_bytes(forward.bytes_sent),
+            format_bytes(forward.bytes_received)
+        )),
+    ])
+}
+
 pub(crate) fn draw_terminal_tab_bar(frame: &mut Frame<'_>, app: &App, area: Rect) {
     let mut titles = Vec::with_capacity(app.terminal_tabs.len() + 1);
     titles.push(Line::from(Span::styled(
@@ -344,7 +519,14 @@ pub(crate) fn draw_terminal_tab_bar(frame: &mut Frame<'_>, app: &App, area: Rect
         Style::default().add_modifier(Modifier::BOLD),
     )));
     for tab in &app.terminal_tabs {
-        titles.push(Line::from(Span::raw(tab.title.clone())));
+        if tab.disconnected {
+            titles.push(Line::from(Span::styled(
+                format!("{} (disconnected)", tab.title),
+                Style::default().fg(Color::Red),
+            )));
+        } else {
+            titles.push(Line::from(Span::raw(tab.title.clone())));
+        }
     }
     let tabs = Tabs::new(titles)
         .select(app.active_terminal_tab)
@@ -410,6 +592,19 @@ pub(crate) fn draw_terminal_view(frame: &mut Frame<'_>, app: &App, area: Rect) {
     }
     let terminal = Paragraph::new(lines);
     frame.render_widget(terminal, area);
+    if tab.disconnected {
+        let message = "disconnected — press r to reconnect";
+        let overlay = centered_rect_abs(message.chars().count() as u16 + 4, 3, area);
+        frame.render_widget(Clear, overlay);
+        frame.render_widget(
+            Paragraph::new(message)
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                .block(Block::default().borders(Borders::ALL)),
+            overlay,
+        );
+        return;
+    }
     let (row, col) = screen.cursor_position();
     if row < area.height && col < area.width {
         frame.set_cursor_position((area.x + col, area.y + row));
@@ -453,10 +648,10 @@ fn selection_span(text: String, selected: bool) -> Span<'static> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ratatui::backend::TestBackend;
-    use ratatui::Terminal;
     use crate::app::App;
     use crate::model::{AuthConfig, ConnectionConfig};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
 
     #[test]
     fn draw_terminal_footer_renders_keys() {
@@ -466,11 +661,7 @@ mod tests {
             .draw(|frame| draw_terminal_footer(frame, frame.area()))
             .unwrap();
         let buffer = terminal.backend().buffer();
-        let content: String = buffer
-            .content()
-            .iter()
-            .map(|cell| cell.symbol())
-            .collect();
+        let content: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
         assert!(content.contains("F6"));
         assert!(content.contains("F7"));
         assert!(content.contains("F8"));
@@ -483,11 +674,16 @@ mod tests {
             name: "Test".to_string(),
             user: "u".to_string(),
             host: "h".to_string(),
+            port: 22,
             auth: AuthConfig::Password {
-                password: "pw".to_string(),
+                password: "pw".into(),
             },
             history: vec![],
             last_remote_dir: None,
+            jump_host: None,
+            via: vec![],
+            keepalive: crate::model::KeepaliveConfig::default(),
+            forwards: vec![],
         });
         let backend = TestBackend::new(60, 10);
         let mut terminal = Terminal::new(backend).unwrap();
@@ -495,27 +691,21 @@ mod tests {
             .draw(|frame| draw_saved_list(frame, &app, frame.area()))
             .unwrap();
         let buffer = terminal.backend().buffer();
-        let content: String = buffer
-            .content()
-            .iter()
-            .map(|cell| cell.symbol())
-            .collect();
+        let content: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
         assert!(content.contains("(n)ew"));
         assert!(content.contains("(e)dit"));
     }
 }
 
 pub(crate) fn draw_terminal_footer(frame: &mut Frame<'_>, area: Rect) {
-    let footer = Paragraph::new(vec![
-        Line::from(vec![
-            Span::styled("F6", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" previous tab | "),
-            Span::styled("F7", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" next tab | "),
-            Span::styled("F8", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" close tab"),
-        ]),
-    ])
+    let footer = Paragraph::new(vec![Line::from(vec![
+        Span::styled("F6", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" previous tab | "),
+        Span::styled("F7", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" next tab | "),
+        Span::styled("F8", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" close tab"),
+    ])])
     .style(Style::default().fg(Color::Gray))
     .alignment(Alignment::Center)
     .wrap(Wrap { trim: true });
@@ -5,7 +5,9 @@ use ratatui::text::Span;
 use ratatui::widgets::{List, ListItem, Paragraph, Wrap};
 
 use crate::app::App;
-use crate::model::TransferDirection;
+use crate::model::{
+    ImportSource, KeyCandidate, TransferDirection, format_host_port, jump_reference,
+};
 use crate::ui::constants::{
     KEY_PICKER_HEIGHT, KEY_PICKER_WIDTH, PICKER_FOOTER_HEIGHT, TRANSFER_PICKER_HEIGHT,
     TRANSFER_PICKER_WIDTH,
@@ -98,16 +100,22 @@ pub(crate) fn draw_key_picker_modal(frame: &mut Frame<'_>, app: &App) {
     let inner = draw_popup_frame(
         frame,
         area,
-        "Pick recent key",
+        "Pick recent key or agent identity",
         Style::default().fg(Color::Yellow),
     );
 
     let items: Vec<ListItem> = picker
         .keys
         .iter()
-        .map(|entry| {
-            let suffix = if entry.password.is_some() { " (pw)" } else { "" };
-            ListItem::new(format!("{}{}", entry.path, suffix))
+        .map(|entry| match entry {
+            KeyCandidate::File { path, password } => {
+                let suffix = if password.is_some() { " (pw)" } else { "" };
+                ListItem::new(format!("{path}{suffix}"))
+            }
+            KeyCandidate::Agent {
+                comment,
+                fingerprint,
+            } => ListItem::new(format!("agent: {comment} ({fingerprint})")),
         })
         .collect();
 
@@ -167,8 +175,8 @@ pub(crate) fn draw_remote_picker_modal(frame: &mut Frame<'_>, app: &App) {
         )
         .split(inner);
 
-    let header = Paragraph::new(format!("Dir: {}", picker.cwd))
-        .style(Style::default().fg(Color::Gray));
+    let header =
+        Paragraph::new(format!("Dir: {}", picker.cwd)).style(Style::default().fg(Color::Gray));
     frame.render_widget(header, layout[0]);
 
     let items: Vec<ListItem> = picker
@@ -207,13 +215,93 @@ pub(crate) fn draw_remote_picker_modal(frame: &mut Frame<'_>, app: &App) {
     frame.render_widget(footer, layout[2]);
 }
 
+pub(crate) fn draw_ssh_import_modal(frame: &mut Frame<'_>, app: &App) {
+    let import = match &app.ssh_import {
+        Some(import) => import,
+        None => return,
+    };
+    let area = centered_rect(TRANSFER_PICKER_WIDTH, TRANSFER_PICKER_HEIGHT, frame.area());
+    let bundle = import.origin == ImportSource::Bundle;
+    let title = if bundle {
+        "Import bundle"
+    } else {
+        "Import from ssh config"
+    };
+    let inner = draw_popup_frame(frame, area, title, Style::default().fg(Color::Yellow));
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Min(3),
+                Constraint::Length(PICKER_FOOTER_HEIGHT),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+
+    let header = Paragraph::new(format!("File: {}", import.source.display()))
+        .style(Style::default().fg(Color::Gray));
+    frame.render_widget(header, layout[0]);
+
+    let jump_name = |reference: &str| {
+        import
+            .candidates
+            .iter()
+            .map(|candidate| &candidate.config)
+            .chain(app.connections.iter())
+            .find(|config| jump_reference(config) == reference)
+            .map(|config| config.name.clone())
+            .unwrap_or_else(|| reference.to_string())
+    };
+    let items: Vec<ListItem> = import
+        .candidates
+        .iter()
+        .map(|candidate| {
+            let config = &candidate.config;
+            let mark = if candidate.checked { "[x]" } else { "[ ]" };
+            let mut line = format!(
+                "{mark} {}  {}@{}",
+                config.name,
+                config.user,
+                format_host_port(&config.host, config.port)
+            );
+            if let Some(jump) = &config.jump_host {
+                line.push_str(&format!(" via {}", jump_name(jump)));
+            }
+            if candidate.duplicate && bundle && candidate.checked {
+                line.push_str(" (replaces saved)");
+            } else if candidate.duplicate {
+                line.push_str(" (already saved)");
+                return ListItem::new(line).style(Style::default().fg(Color::DarkGray));
+            }
+            ListItem::new(line)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(Span::styled("> ", Style::default().fg(Color::White)));
+    frame.render_stateful_widget(
+        list,
+        layout[1],
+        &mut list_state(import.selected, import.candidates.len()),
+    );
+
+    let footer = Paragraph::new("Space to toggle, A to toggle all, Enter to import, Esc to cancel")
+        .style(Style::default().fg(Color::Gray))
+        .block(ratatui::widgets::Block::default().borders(ratatui::widgets::Borders::TOP));
+    frame.render_widget(footer, layout[2]);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::app::App;
     use crate::model::{FileEntry, FilePickerState, RemoteEntry, RemotePickerState};
-    use ratatui::backend::TestBackend;
     use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
 
     #[test]
     fn draw_file_picker_modal_smoke() {
@@ -264,4 +352,36 @@ mod tests {
             .draw(|frame| draw_remote_picker_modal(frame, &app))
             .unwrap();
     }
+
+    #[test]
+    fn draw_ssh_import_modal_smoke() {
+        let mut app = App::for_test();
+        app.ssh_import = Some(crate::model::SshImportState {
+            source: std::env::temp_dir().join("config"),
+            origin: crate::model::ImportSource::SshConfig,
+            candidates: vec![crate::model::ImportCandidate {
+                config: crate::model::ConnectionConfig {
+                    name: "web".to_string(),
+                    user: "dev".to_string(),
+                    host: "web.example".to_string(),
+                    port: 2222,
+                    auth: crate::model::AuthConfig::Agent { identity: None },
+                    history: vec![],
+                    last_remote_dir: None,
+                    jump_host: Some("bastion".to_string()),
+                    via: vec![],
+                    keepalive: crate::model::KeepaliveConfig::default(),
+                    forwards: vec![],
+                },
+                checked: true,
+                duplicate: false,
+            }],
+            selected: 0,
+        });
+        let backend = TestBackend::new(80, 24);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| draw_ssh_import_modal(frame, &app))
+            .unwrap();
+    }
 }